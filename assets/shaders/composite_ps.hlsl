@@ -0,0 +1,22 @@
+struct PsIn {
+    [[vk::location(0)]] float2 uv: TEXCOORD0;
+};
+
+struct PsOut {
+    float4 color: SV_TARGET0;
+};
+
+[[vk::binding(0, 0)]] Texture2D<float4> albedo_texture;
+[[vk::binding(1, 0)]] Texture2D<float4> normal_texture;
+[[vk::binding(2, 0)]] SamplerState texture_sampler;
+
+// Albedo on the left half, normals on the right half
+PsOut main(PsIn ps) {
+    PsOut ps_out;
+    if (ps.uv.x < 0.5) {
+        ps_out.color = albedo_texture.Sample(texture_sampler, ps.uv);
+    } else {
+        ps_out.color = normal_texture.Sample(texture_sampler, ps.uv);
+    }
+    return ps_out;
+}
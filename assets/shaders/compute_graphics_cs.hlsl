@@ -0,0 +1,14 @@
+// [0] holds the scale of the quad, [1] its color
+[[vk::binding(0, 0)]] RWStructuredBuffer<float4> output_buffer;
+
+[[vk::push_constant]]
+struct {
+    float4 color;
+} push_constants;
+
+[numthreads(1, 1, 1)]
+void main()
+{
+    output_buffer[0] = float4(0.5, 0.5, 0.0, 0.0);
+    output_buffer[1] = push_constants.color;
+}
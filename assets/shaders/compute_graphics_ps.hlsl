@@ -0,0 +1,5 @@
+[[vk::binding(0, 0)]] StructuredBuffer<float4> data;
+
+float4 main() : SV_TARGET0 {
+    return data[1];
+}
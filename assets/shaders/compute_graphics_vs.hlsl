@@ -0,0 +1,8 @@
+[[vk::binding(0, 0)]] StructuredBuffer<float4> data;
+
+// Fullscreen triangle scaled by the value written by the compute shader
+float4 main(uint vid: SV_VertexID) : SV_Position {
+    float2 uv = float2((vid << 1) & 2, vid & 2);
+
+    return float4((uv * 2.0 - 1.0) * data[0].xy, 0, 1.0);
+}
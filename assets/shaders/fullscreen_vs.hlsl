@@ -0,0 +1,16 @@
+struct VsOut {
+    float4 position: SV_Position;
+    [[vk::location(0)]] float2 uv: TEXCOORD0;
+};
+
+// Single triangle covering the whole screen
+VsOut main(uint vid: SV_VertexID) {
+    VsOut vsout;
+
+    float2 uv = float2((vid << 1) & 2, vid & 2);
+
+    vsout.position = float4(uv * 2.0 - 1.0, 0, 1.0);
+    vsout.uv = uv;
+
+    return vsout;
+}
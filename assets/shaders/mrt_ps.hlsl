@@ -0,0 +1,17 @@
+struct PsIn {
+    [[vk::location(0)]] float3 color: TEXCOORD0;
+};
+
+// Target 1 is left unused by the render pass
+struct PsOut {
+    float4 albedo: SV_TARGET0;
+    float4 normal: SV_TARGET2;
+};
+
+PsOut main(PsIn ps) {
+    PsOut ps_out;
+    ps_out.albedo = float4(ps.color, 1.0);
+    // Facing the camera, packed to [0, 1]
+    ps_out.normal = float4(float3(0.0, 0.0, -1.0) * 0.5 + 0.5, 1.0);
+    return ps_out;
+}
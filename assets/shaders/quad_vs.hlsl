@@ -0,0 +1,24 @@
+struct VsOut {
+    float4 position: SV_Position;
+    [[vk::location(0)]] float3 color: TEXCOORD0;
+};
+
+struct Vertex {
+    float2 position;
+    float2 padding;
+    float4 color;
+};
+
+[[vk::binding(0, 0)]] StructuredBuffer<Vertex> vertices;
+
+// With indexed draws SV_VertexID is the index read from the index buffer
+VsOut main(uint vid: SV_VertexID) {
+    VsOut vsout;
+
+    Vertex vertex = vertices[vid];
+
+    vsout.position = float4(vertex.position, 0, 1.0);
+    vsout.color = vertex.color.xyz;
+
+    return vsout;
+}
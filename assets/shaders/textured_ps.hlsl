@@ -0,0 +1,16 @@
+struct PsIn {
+    [[vk::location(0)]] float2 uv: TEXCOORD0;
+};
+
+struct PsOut {
+    float4 color: SV_TARGET0;
+};
+
+[[vk::binding(0, 0)]] Texture2D<float4> color_texture;
+[[vk::binding(1, 0)]] SamplerState color_sampler;
+
+PsOut main(PsIn ps) {
+    PsOut ps_out;
+    ps_out.color = color_texture.Sample(color_sampler, ps.uv);
+    return ps_out;
+}
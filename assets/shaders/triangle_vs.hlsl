@@ -17,12 +17,17 @@ static float2 vertices[3] = {
 
 [[vk::binding(0, 0)]] StructuredBuffer<float4> colors;
 
+struct Transform {
+    float4x4 mvp;
+};
+[[vk::binding(1, 0)]] ConstantBuffer<Transform> transform;
+
 VsOut main(uint vid: SV_VertexID) {
     VsOut vsout;
 
     float2 pos = vertices[vid];
 
-    vsout.position = float4(pos.x, pos.y, 0, 1.0);
+    vsout.position = mul(transform.mvp, float4(pos.x, pos.y, 0, 1.0));
     vsout.color = colors[vid].xyz;
 
     return vsout;
@@ -1,47 +1,287 @@
 mod vulkan;
 
 use ash::vk;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 pub use vulkan::Backend;
 pub use vulkan::CommandBuffer;
+pub use vulkan::DebugName;
+pub use vulkan::DebugScopeGuard;
 pub use vulkan::DescriptorSetLayout;
 pub use vulkan::Device;
+pub use vulkan::Event;
 pub use vulkan::Fence;
+pub use vulkan::Frame;
 pub use vulkan::Framebuffer;
 pub use vulkan::ImageView;
+pub use vulkan::OwnedImageView;
+pub use vulkan::PendingPipeline;
+pub use vulkan::PhysicalDevice;
+pub use vulkan::QueryPool;
 pub use vulkan::RenderPass;
+pub use vulkan::Sampler;
 pub use vulkan::Semaphore;
+pub use vulkan::{StagingBelt, StagingSlice};
+pub use vulkan::SurfaceContext;
+pub use vulkan::SwapchainRecreatedCallback;
 pub use vulkan::{ComputePassEncoder, RenderPassEncoder};
 
 pub type Buffer = Arc<vulkan::Buffer>;
+pub type BufferView = Arc<vulkan::BufferView>;
 pub type Image = Arc<vulkan::Image>;
 pub type GraphicsPipeline = Arc<vulkan::GraphicsPipeline>;
 pub type ComputePipeline = Arc<vulkan::ComputePipeline>;
 pub type DescriptorSet = Arc<vulkan::DescriptorSet>;
 
+/// Any GPU resource, for systems that need to track resources generically
+#[derive(Clone)]
+pub enum Resource {
+    Buffer(Buffer),
+    Image(Image),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResourceKind {
+    Buffer,
+    Image,
+}
+
+impl Resource {
+    pub fn kind(&self) -> ResourceKind {
+        match self {
+            Resource::Buffer(_) => ResourceKind::Buffer,
+            Resource::Image(_) => ResourceKind::Image,
+        }
+    }
+    pub fn as_buffer(&self) -> Option<&Buffer> {
+        match self {
+            Resource::Buffer(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+    pub fn as_image(&self) -> Option<&Image> {
+        match self {
+            Resource::Image(image) => Some(image),
+            _ => None,
+        }
+    }
+    /// Size in bytes, only for buffers
+    pub fn size(&self) -> Option<u64> {
+        self.as_buffer().map(|buffer| buffer.size())
+    }
+    /// Only for images
+    pub fn format(&self) -> Option<ImageFormat> {
+        self.as_image().map(|image| image.desc.format)
+    }
+    /// Only for buffers
+    pub fn buffer_usage(&self) -> Option<BufferUsage> {
+        self.as_buffer().map(|buffer| buffer.usage())
+    }
+    /// Only for images
+    pub fn image_usage(&self) -> Option<ImageUsage> {
+        self.as_image().map(|image| image.desc.usage)
+    }
+}
+
+impl From<Buffer> for Resource {
+    fn from(buffer: Buffer) -> Self {
+        Resource::Buffer(buffer)
+    }
+}
+
+impl From<Image> for Resource {
+    fn from(image: Image) -> Self {
+        Resource::Image(image)
+    }
+}
+
 pub const MAX_COLOR_ATTACHMENTS: usize = 8;
 pub const WHOLE_SIZE: u64 = vk::WHOLE_SIZE;
+pub const SUBPASS_EXTERNAL: u32 = vk::SUBPASS_EXTERNAL;
+pub const REMAINING_MIP_LEVELS: u32 = vk::REMAINING_MIP_LEVELS;
+pub const REMAINING_ARRAY_LAYERS: u32 = vk::REMAINING_ARRAY_LAYERS;
+/// Suitable size for the render pass and framebuffer caches in BackendConfig
+pub const DEFAULT_CACHE_SIZE: usize = 16;
+/// Errors, warnings and info, verbose messages are rarely useful
+pub const DEFAULT_VALIDATION_SEVERITY: ValidationSeverity = ValidationSeverity::from_raw(
+    ValidationSeverity::ERROR.as_raw()
+        | ValidationSeverity::WARNING.as_raw()
+        | ValidationSeverity::INFO.as_raw(),
+);
 
 pub struct BackendConfig {
     pub debugging: bool,
+    pub device_selection: DeviceSelection,
+    /// Transition swapchain images to PRESENT_SRC_KHR once after the swapchain
+    /// is created, so they never are in an undefined layout on first use
+    pub transition_swapchain_images: bool,
+    /// Reported to the driver and tools through VkApplicationInfo
+    pub app_name: String,
+    /// (major, minor, patch)
+    pub app_version: (u32, u32, u32),
+    pub engine_name: String,
+    /// (major, minor, patch)
+    pub engine_version: (u32, u32, u32),
+    /// Number of render passes kept alive by the cache, see Device::cache_stats.
+    /// Has to be at least 1.
+    pub render_pass_cache_size: usize,
+    /// Number of framebuffers kept alive by the cache, see Device::cache_stats.
+    /// Has to be at least 1.
+    pub framebuffer_cache_size: usize,
+    /// Attach to RenderDoc if the application runs under it, see
+    /// Backend::trigger_capture
+    pub renderdoc: bool,
+    /// Severities reported by the validation layer when debugging is enabled,
+    /// see DEFAULT_VALIDATION_SEVERITY
+    pub validation_severity: ValidationSeverity,
+    /// Called for every validation message in addition to logging it, e.g.
+    /// to break on errors
+    pub validation_callback: Option<ValidationCallback>,
+    /// Panic on validation errors. The panic can't unwind out of the
+    /// validation layer, it is raised by the next submit or wait on the
+    /// Device instead. Panics in validation_callback are deferred the same way.
+    pub break_on_validation_error: bool,
+    /// Additional validation layer features, ignored unless debugging
+    pub validation_features: ValidationFeatures,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CacheStats {
+    pub render_pass_hits: u64,
+    pub render_pass_misses: u64,
+    pub framebuffer_hits: u64,
+    pub framebuffer_misses: u64,
+}
+
+/// See Device::memory_report
+#[derive(Clone, Debug, Default)]
+pub struct MemoryReport {
+    /// Sum of the allocations of all live buffers and images
+    pub allocated_bytes: u64,
+    pub allocation_count: u64,
+    pub heaps: Vec<MemoryHeapReport>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryHeapReport {
+    pub size: u64,
+    pub device_local: bool,
+    /// Estimate of how much the process can use, None without VK_EXT_memory_budget
+    pub budget: Option<u64>,
+    /// Current usage by the process, None without VK_EXT_memory_budget
+    pub usage: Option<u64>,
+}
+
+/// Pixels of a swapchain image, see Backend::capture_frame
+#[derive(Clone, Debug)]
+pub struct CaptureImage {
+    /// Tightly packed rows, top row first
+    pub pixels: Vec<u8>,
+    /// Format of the swapchain, e.g. BGRA rather than RGBA on most platforms
+    pub format: ImageFormat,
+    pub extent: Extent2d,
+}
+
+/// Queues of a device, for now a single queue is used for everything
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QueueType {
+    /// Supports graphics, compute, transfer and present
+    Universal,
+}
+
+/// Decides which physical device the backend picks, only devices capable of
+/// presenting to the window surface are considered.
+#[derive(Clone, Debug, Default)]
+pub enum DeviceSelection {
+    /// Prefer discrete GPUs, then integrated, then virtual
+    #[default]
+    HighPerformance,
+    /// Prefer integrated GPUs, then discrete, then virtual
+    LowPower,
+    /// First device whose name contains the given string
+    ByName(String),
+    /// Index into the list of presentation capable devices
+    Index(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// (major, minor, patch)
+    pub api_version: (u32, u32, u32),
+    pub driver_version: u32,
+    pub limits: DeviceLimits,
+}
+
+/// Subset of the device limits most useful for adapting to the hardware
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceLimits {
+    pub max_image_dimension_1d: u32,
+    pub max_image_dimension_2d: u32,
+    pub max_image_dimension_3d: u32,
+    pub max_image_dimension_cube: u32,
+    pub max_image_array_layers: u32,
+    pub max_push_constants_size: u32,
+    pub max_bound_descriptor_sets: u32,
+    pub max_uniform_buffer_range: u32,
+    pub max_storage_buffer_range: u32,
+    pub max_compute_work_group_count: [u32; 3],
+    pub max_compute_work_group_size: [u32; 3],
+    pub max_compute_work_group_invocations: u32,
+    pub max_color_attachments: u32,
+    pub min_uniform_buffer_offset_alignment: u64,
+    pub min_storage_buffer_offset_alignment: u64,
+    pub timestamp_period: f32,
 }
 
 // If we ever decide to abstract away vulkan
+pub type MemoryLocation = gpu_allocator::MemoryLocation;
+/// Invoked from whichever thread triggered the message
+pub type ValidationCallback = Box<dyn Fn(ValidationSeverity, &str) + Send + Sync>;
+
 pub type Extent2d = vk::Extent2D;
 pub type Extent3d = vk::Extent3D;
 
+pub type PhysicalDeviceType = vk::PhysicalDeviceType;
+pub type SurfaceCapabilities = vk::SurfaceCapabilitiesKHR;
+pub type ValidationSeverity = vk::DebugUtilsMessageSeverityFlagsEXT;
+
 pub type ImageAspectFlags = vk::ImageAspectFlags;
 pub type ImageFormat = vk::Format;
+pub type FormatFeatureFlags = vk::FormatFeatureFlags;
 pub type ImageLayout = vk::ImageLayout;
+pub type ImageTiling = vk::ImageTiling;
 pub type ImageType = vk::ImageType;
 pub type ImageViewType = vk::ImageViewType;
+pub type SampleCountFlags = vk::SampleCountFlags;
+
+pub type Filter = vk::Filter;
+pub type SamplerAddressMode = vk::SamplerAddressMode;
+pub type SamplerMipmapMode = vk::SamplerMipmapMode;
 
 pub type AccessFlags = vk::AccessFlags;
+pub type DependencyFlags = vk::DependencyFlags;
+pub type DescriptorBindingFlags = vk::DescriptorBindingFlags;
 pub type DescriptorType = vk::DescriptorType;
 
+pub type CommandBufferLevel = vk::CommandBufferLevel;
+
 pub type IndexType = vk::IndexType;
 
+pub type CompareOp = vk::CompareOp;
+pub type StencilOp = vk::StencilOp;
+pub type StencilFaceFlags = vk::StencilFaceFlags;
+pub type BlendFactor = vk::BlendFactor;
+pub type BlendOp = vk::BlendOp;
+pub type CullModeFlags = vk::CullModeFlags;
+pub type FrontFace = vk::FrontFace;
+
+pub type PipelineStatisticFlags = vk::QueryPipelineStatisticFlags;
+
 pub type PipelineBindPoint = vk::PipelineBindPoint;
 pub type PipelineStageFlags = vk::PipelineStageFlags;
 pub type ShaderStageFlags = vk::ShaderStageFlags;
@@ -57,6 +297,71 @@ pub struct Rect<T> {
     pub height: T,
 }
 
+impl<T> Rect<T> {
+    pub fn new(x: T, y: T, width: T, height: T) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+impl Rect<u32> {
+    pub fn extent(&self) -> Extent2d {
+        Extent2d {
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Rect covering the whole extent
+impl From<Extent2d> for Rect<u32> {
+    fn from(extent: Extent2d) -> Self {
+        Self::new(0, 0, extent.width, extent.height)
+    }
+}
+
+/// Linear RGBA as expected by Vulkan, SRGB attachments convert it to sRGB
+/// when written
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClearColor(pub [f32; 4]);
+
+impl ClearColor {
+    pub const BLACK: Self = Self([0.0, 0.0, 0.0, 1.0]);
+
+    pub fn linear(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self([r, g, b, a])
+    }
+
+    /// Color given in sRGB, e.g. picked in an image editor, alpha is linear
+    pub fn srgb(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self([srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a])
+    }
+
+    /// 8-bit variant of srgb
+    pub fn srgb8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let unorm = |c: u8| c as f32 / 255.0;
+        Self::srgb(unorm(r), unorm(g), unorm(b), unorm(a))
+    }
+}
+
+impl From<ClearColor> for [f32; 4] {
+    fn from(color: ClearColor) -> Self {
+        color.0
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 bitflags::bitflags! {
     pub struct BufferUsage: u32 {
         /// Enable buffer to be mapped for reading
@@ -81,6 +386,26 @@ bitflags::bitflags! {
         const VERTEX = 1 << 7;
         /// Enable use as indirect buffer
         const INDIRECT = 1 << 8;
+        /// Enable use as uniform texel buffer
+        const UNIFORM_TEXEL = 1 << 9;
+        /// Enable use as storage texel buffer
+        const STORAGE_TEXEL = 1 << 10;
+        /// Enable retrieving the device address of the buffer
+        const SHADER_DEVICE_ADDRESS = 1 << 11;
+    }
+}
+
+bitflags::bitflags! {
+    /// Opt-in checks of the validation layer, these are slow and only enabled
+    /// when needed
+    pub struct ValidationFeatures: u32 {
+        /// Instruments shaders to catch out of bounds and uninitialized
+        /// descriptor accesses
+        const GPU_ASSISTED = 1 << 0;
+        /// Reports missing or insufficient barriers
+        const SYNCHRONIZATION = 1 << 1;
+        /// Warnings for API usage that is valid but slow
+        const BEST_PRACTICES = 1 << 2;
     }
 }
 
@@ -100,43 +425,130 @@ bitflags::bitflags! {
 pub struct BufferDesc {
     pub size: u64,
     pub usage: BufferUsage,
+    /// Raises the alignment of the allocation above what the buffer requires,
+    /// must be a power of two
+    pub min_alignment: Option<u64>,
+    /// Overrides the memory location derived from usage, e.g. GpuToCpu for a
+    /// STORAGE buffer read back on the CPU. Buffers in CpuToGpu or GpuToCpu
+    /// can be mapped regardless of MAP_READ/MAP_WRITE.
+    pub memory_location: Option<MemoryLocation>,
+    /// Used for the allocation and as debug name of the buffer
+    pub name: Option<String>,
+}
+
+pub struct BufferViewDesc {
+    pub format: ImageFormat,
+    pub offset: u64,
+    pub range: u64,
 }
 
 pub struct ImageDesc {
     pub image_type: ImageType,
     pub format: ImageFormat,
     pub extent: Extent3d,
-    pub usage: ImageUsage
+    /// At least 1, see mip_level_count for a full mip chain
+    pub mip_levels: u32,
+    pub array_layers: u32,
+    pub samples: SampleCountFlags,
+    pub usage: ImageUsage,
+    /// Allows views with a different but compatible format, e.g. SRGB views
+    /// of an UNORM image
+    pub mutable_format: bool,
+    /// Allows CUBE and CUBE_ARRAY views, requires a square TYPE_2D image with
+    /// at least 6 array layers
+    pub cube_compatible: bool,
+    /// Used for the allocation and as debug name of the image
+    pub name: Option<String>,
 }
 
 #[derive(Copy, Clone, Default, Eq, Hash, PartialEq)]
 pub struct ImageViewDesc {
     pub view_type: ImageViewType,
     pub aspect_mask: ImageAspectFlags,
-    // TODO: Is format needed or can we just use the image's format?
+    /// Has to match the image format unless it was created with
+    /// ImageDesc::mutable_format
     pub format: ImageFormat,
     pub base_mip_level: u32,
     pub level_count: u32,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
 }
 
 pub enum DescriptorResource<'a> {
+    /// For dynamic bindings, range is the size seen by each draw and the
+    /// offset given at bind time is added to offset
     Buffer {
         buffer: &'a Buffer,
         offset: u64,
         range: u64,
     },
+    /// For UNIFORM_TEXEL_BUFFER and STORAGE_TEXEL_BUFFER bindings
+    TexelBuffer {
+        view: &'a BufferView,
+    },
+    /// For SAMPLED_IMAGE and STORAGE_IMAGE bindings, layout is the layout the
+    /// image is in when accessed
+    Image {
+        view: &'a ImageView,
+        layout: ImageLayout,
+    },
+    /// For SAMPLER bindings
+    Sampler {
+        sampler: &'a Sampler,
+    },
+    /// For INPUT_ATTACHMENT bindings, view has to be in SHADER_READ_ONLY_OPTIMAL
+    InputAttachment {
+        view: &'a ImageView,
+    },
+}
+
+impl<'a> DescriptorResource<'a> {
+    pub fn whole_buffer(buffer: &'a Buffer) -> Self {
+        Self::Buffer {
+            buffer,
+            offset: 0,
+            range: WHOLE_SIZE,
+        }
+    }
+
+    pub fn buffer_range(buffer: &'a Buffer, offset: u64, size: u64) -> Self {
+        Self::Buffer {
+            buffer,
+            offset,
+            range: size,
+        }
+    }
 }
 
 pub struct Descriptor<'a> {
     pub binding: u32,
-    pub resource: &'a DescriptorResource<'a>,
+    /// First element to write for array bindings
+    pub array_element: u32,
+    /// Written to consecutive array elements starting at array_element
+    pub resources: &'a [DescriptorResource<'a>],
 }
 
+#[derive(Clone, Debug)]
 pub struct DescriptorSetLayoutEntry {
     pub binding: u32,
     pub stage_flags: ShaderStageFlags,
     pub ty: DescriptorType,
     pub count: u32,
+    /// E.g. PARTIALLY_BOUND and UPDATE_AFTER_BIND for bindless arrays. With
+    /// VARIABLE_DESCRIPTOR_COUNT, count is the upper bound and sets are
+    /// allocated with the full count
+    pub flags: DescriptorBindingFlags,
+}
+
+pub struct SamplerDesc {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mipmap_mode: SamplerMipmapMode,
+    /// Used for all of u, v and w
+    pub address_mode: SamplerAddressMode,
+    /// None disables anisotropic filtering, clamped to
+    /// Device::max_sampler_anisotropy and ignored if the device lacks support
+    pub max_anisotropy: Option<f32>,
 }
 
 pub struct DescriptorSetLayoutDesc<'a> {
@@ -147,11 +559,24 @@ pub enum ShaderSource<'a> {
     Hlsl(&'a str),
 }
 
+/// Value of a specialization constant
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpecValue {
+    Bool(bool),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+}
+
 pub struct ShaderModuleDesc<'a> {
     pub source: ShaderSource<'a>,
     pub stage: ShaderStageFlags,
+    /// Specialization constants as (constant_id, value), applied when the
+    /// pipeline is created
+    pub specialization: &'a [(u32, SpecValue)],
 }
 
+#[derive(Clone, Debug)]
 pub struct PushConstantRange {
     pub stage_flags: ShaderStageFlags,
     pub offset: u32,
@@ -165,8 +590,74 @@ pub struct ColorAttachmentDesc {
     pub layout: ImageLayout,
 }
 
+/// Cleared to depth 1.0 and stencil 0 at the start of the render pass
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct DepthStencilAttachmentDesc {
+    pub format: ImageFormat,
+    /// Layout after the render pass
+    pub layout: ImageLayout,
+}
+
+/// Attachments are referenced by their index in
+/// RenderPassDesc::color_attachments, None for unused slots
+pub struct SubpassDesc<'a> {
+    pub color_attachments: &'a [Option<u32>],
+    pub input_attachments: &'a [Option<u32>],
+    /// Indices into RenderPassDesc::resolve_attachments, either empty or one
+    /// per color attachment
+    pub resolve_attachments: &'a [Option<u32>],
+    /// Uses RenderPassDesc::depth_stencil_attachment
+    pub depth_stencil_attachment: bool,
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct SubpassDependency {
+    /// SUBPASS_EXTERNAL for commands before the render pass
+    pub src_subpass: u32,
+    /// SUBPASS_EXTERNAL for commands after the render pass
+    pub dst_subpass: u32,
+    pub src_stage_mask: PipelineStageFlags,
+    pub dst_stage_mask: PipelineStageFlags,
+    pub src_access_mask: AccessFlags,
+    pub dst_access_mask: AccessFlags,
+    pub dependency_flags: DependencyFlags,
+}
+
+/// Framebuffer attachments are the color attachments followed by the resolve
+/// attachments, skipping None, and last the depth stencil attachment
 pub struct RenderPassDesc<'a> {
     pub color_attachments: &'a [Option<ColorAttachmentDesc>],
+    /// Sample count of the color attachments
+    pub samples: SampleCountFlags,
+    /// Single sampled targets for multisampled color attachments. Without
+    /// explicit subpasses, resolve attachment i resolves color attachment i
+    pub resolve_attachments: &'a [Option<ColorAttachmentDesc>],
+    /// Empty for a single subpass writing to all color attachments
+    pub subpasses: &'a [SubpassDesc<'a>],
+    pub dependencies: &'a [SubpassDependency],
+    /// Has the same sample count as the color attachments. Used by the
+    /// implicit subpass, explicit subpasses opt in through
+    /// SubpassDesc::depth_stencil_attachment
+    pub depth_stencil_attachment: Option<DepthStencilAttachmentDesc>,
+}
+
+pub struct RenderingAttachment<'a> {
+    pub image_view: &'a ImageView,
+    /// Layout of the image during rendering
+    pub layout: ImageLayout,
+    pub load_op: AttachmentLoadOp,
+    pub store_op: AttachmentStoreOp,
+    /// Used if load_op is CLEAR
+    pub clear_color: ClearColor,
+}
+
+pub struct RenderingInfo<'a> {
+    pub render_area: Rect<u32>,
+    pub color_attachments: &'a [RenderingAttachment<'a>],
+    /// Also the stencil attachment if its format has a stencil aspect. Cleared
+    /// to depth 1.0 and stencil 0 like render pass attachments, clear_color
+    /// is ignored
+    pub depth_attachment: Option<RenderingAttachment<'a>>,
 }
 
 pub struct Attachment<'a> {
@@ -179,13 +670,167 @@ pub struct FramebufferDesc<'a> {
     pub extent: Extent2d,
 }
 
+/// What a graphics pipeline renders to
+pub enum RenderTarget<'a> {
+    RenderPass(&'a RenderPass),
+    /// For use with CommandBuffer::begin_rendering, requires dynamic rendering
+    Dynamic {
+        color_formats: &'a [ImageFormat],
+        /// Format of RenderingInfo::depth_attachment
+        depth_format: Option<ImageFormat>,
+    },
+}
+
 /// TODO: Are there any point to creating shader modules separately?
 /// TODO: Maybe this could be general for both graphics and compute?
 pub struct GraphicsPipelineDesc<'a> {
     pub shader_modules: &'a [ShaderModuleDesc<'a>],
     pub descriptor_set_layouts: &'a [&'a DescriptorSetLayout],
     pub push_constant_ranges: &'a [PushConstantRange],
-    pub render_pass: &'a RenderPass,
+    pub target: RenderTarget<'a>,
+    /// Index of the subpass the pipeline is used in, 0 for dynamic rendering
+    pub subpass: u32,
+    /// Has to match the samples of the render pass
+    pub samples: SampleCountFlags,
+    /// Requires a depth stencil attachment with a stencil aspect
+    pub stencil: StencilState,
+    /// Enables depth bias, can be changed with RenderPassEncoder::set_depth_bias
+    pub depth_bias: Option<DepthBias>,
+    /// Blending of all color attachments, None disables blending
+    pub blend: Option<BlendState>,
+    /// State that is set with the RenderPassEncoder rather than baked into
+    /// the pipeline
+    pub dynamic_states: DynamicStates,
+}
+
+bitflags::bitflags! {
+    /// Optional dynamic state of a graphics pipeline. Defaults (zero blend
+    /// constants, line width 1 and the stencil references of the StencilState)
+    /// are reapplied on bind_pipeline.
+    pub struct DynamicStates: u32 {
+        /// See RenderPassEncoder::set_blend_constants
+        const BLEND_CONSTANTS = 1 << 0;
+        /// See RenderPassEncoder::set_line_width
+        const LINE_WIDTH = 1 << 1;
+        /// See RenderPassEncoder::set_stencil_reference
+        const STENCIL_REFERENCE = 1 << 2;
+    }
+}
+
+/// Blend equations for color and alpha, result = src * src_factor op dst * dst_factor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlendState {
+    pub src_color_factor: BlendFactor,
+    pub dst_color_factor: BlendFactor,
+    pub color_op: BlendOp,
+    pub src_alpha_factor: BlendFactor,
+    pub dst_alpha_factor: BlendFactor,
+    pub alpha_op: BlendOp,
+}
+
+impl BlendState {
+    /// Standard alpha blending, non premultiplied
+    pub const ALPHA: Self = Self {
+        src_color_factor: BlendFactor::SRC_ALPHA,
+        dst_color_factor: BlendFactor::ONE_MINUS_SRC_ALPHA,
+        color_op: BlendOp::ADD,
+        src_alpha_factor: BlendFactor::ONE,
+        dst_alpha_factor: BlendFactor::ONE_MINUS_SRC_ALPHA,
+        alpha_op: BlendOp::ADD,
+    };
+}
+
+/// Depth bias applied to fragment depth, typically used for shadow maps
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DepthBias {
+    pub constant_factor: f32,
+    /// Maximum bias, ignored if the device doesn't support depthBiasClamp
+    pub clamp: f32,
+    pub slope_factor: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StencilFaceState {
+    pub fail_op: StencilOp,
+    pub pass_op: StencilOp,
+    pub depth_fail_op: StencilOp,
+    pub compare_op: CompareOp,
+    pub compare_mask: u32,
+    pub write_mask: u32,
+    pub reference: u32,
+}
+
+/// Keeps the stencil value and always passes
+impl Default for StencilFaceState {
+    fn default() -> Self {
+        Self {
+            fail_op: StencilOp::KEEP,
+            pass_op: StencilOp::KEEP,
+            depth_fail_op: StencilOp::KEEP,
+            compare_op: CompareOp::ALWAYS,
+            compare_mask: 0xff,
+            write_mask: 0xff,
+            reference: 0,
+        }
+    }
+}
+
+/// Stencil test disabled by default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StencilState {
+    pub test_enable: bool,
+    pub front: StencilFaceState,
+    pub back: StencilFaceState,
+}
+
+/// Resources used by a shader, see Device::reflect_shader
+#[derive(Clone, Debug, Default)]
+pub struct ShaderReflection {
+    /// Layout entries per set index, sorted by binding. Unbounded arrays get
+    /// a count of 0 and have to be given an upper bound before use.
+    pub descriptor_sets: BTreeMap<u32, Vec<DescriptorSetLayoutEntry>>,
+    pub push_constant_range: Option<PushConstantRange>,
+    /// Workgroup size of compute shaders
+    pub local_size: Option<(u32, u32, u32)>,
+}
+
+impl ShaderReflection {
+    /// Combines the reflection of several stages of a pipeline, stage flags
+    /// of bindings and push constants used by both are merged
+    pub fn merge(mut self, other: ShaderReflection) -> Self {
+        for (set, entries) in other.descriptor_sets {
+            let merged = self.descriptor_sets.entry(set).or_default();
+            for entry in entries {
+                match merged.iter_mut().find(|e| e.binding == entry.binding) {
+                    Some(existing) => existing.stage_flags |= entry.stage_flags,
+                    None => merged.push(entry),
+                }
+            }
+            merged.sort_by_key(|entry| entry.binding);
+        }
+
+        self.push_constant_range = match (self.push_constant_range, other.push_constant_range) {
+            (Some(a), Some(b)) => {
+                let offset = a.offset.min(b.offset);
+                Some(PushConstantRange {
+                    stage_flags: a.stage_flags | b.stage_flags,
+                    offset,
+                    size: (a.offset + a.size).max(b.offset + b.size) - offset,
+                })
+            },
+            (a, b) => a.or(b),
+        };
+        self.local_size = self.local_size.or(other.local_size);
+        self
+    }
+}
+
+/// One batch of Device::submit_batch
+pub struct SubmitDesc<'a> {
+    pub command_buffers: &'a [&'a CommandBuffer],
+    /// Semaphores to wait for, with the stages that wait on each of them
+    pub wait_semaphores: &'a [(&'a Semaphore, PipelineStageFlags)],
+    pub signal_semaphores: &'a [&'a Semaphore],
 }
 
 pub struct ComputePipelineDesc<'a> {
@@ -195,27 +840,204 @@ pub struct ComputePipelineDesc<'a> {
 }
 
 
+#[derive(Clone, Copy, Debug)]
+pub enum QueryType {
+    /// Number of samples passing depth and stencil tests
+    Occlusion,
+    /// One counter per enabled statistic
+    PipelineStatistics(PipelineStatisticFlags),
+}
+
+pub struct QueryPoolDesc {
+    pub ty: QueryType,
+    pub count: u32,
+}
+
+/// Applies to all memory accesses, not tied to a specific resource
+pub struct MemoryBarrier {
+    pub src_access_mask: AccessFlags,
+    pub dst_access_mask: AccessFlags,
+}
+
+/// For a queue ownership transfer set queue_transfer to the source and
+/// destination queue, the same barrier then has to be recorded on both
+/// queues: a release on the source queue followed by an acquire on the
+/// destination queue, synchronized with a semaphore.
 pub struct BufferBarrier<'a> {
     pub buffer: &'a Buffer,
     pub src_access_mask: AccessFlags,
     pub dst_access_mask: AccessFlags,
+    pub queue_transfer: Option<(QueueType, QueueType)>,
 }
 
+/// See BufferBarrier for queue ownership transfers
 pub struct ImageBarrier<'a> {
     pub image: &'a Image,
     pub src_access_mask: AccessFlags,
     pub dst_access_mask: AccessFlags,
     pub old_layout: ImageLayout,
     pub new_layout: ImageLayout,
-    pub aspect_mask: ImageAspectFlags, // TODO: Make proper subresource range
+    pub subresource_range: ImageSubresourceRange,
+    pub queue_transfer: Option<(QueueType, QueueType)>,
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ImageSubresourceRange {
+    pub aspect_mask: ImageAspectFlags,
+    pub base_mip_level: u32,
+    /// REMAINING_MIP_LEVELS for all levels from base_mip_level
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    /// REMAINING_ARRAY_LAYERS for all layers from base_array_layer
+    pub layer_count: u32,
+}
+
+impl ImageSubresourceRange {
+    /// All mip levels and array layers
+    pub fn all(aspect_mask: ImageAspectFlags) -> Self {
+        Self {
+            aspect_mask,
+            base_mip_level: 0,
+            level_count: REMAINING_MIP_LEVELS,
+            base_array_layer: 0,
+            layer_count: REMAINING_ARRAY_LAYERS,
+        }
+    }
+
+    /// A single mip level of all array layers
+    pub fn mip_level(aspect_mask: ImageAspectFlags, mip_level: u32) -> Self {
+        Self {
+            base_mip_level: mip_level,
+            level_count: 1,
+            ..Self::all(aspect_mask)
+        }
+    }
+}
+
+/// Number of mip levels in a full mip chain of extent, down to 1x1x1
+pub fn mip_level_count(extent: Extent3d) -> u32 {
+    let largest = extent.width.max(extent.height).max(extent.depth).max(1);
+    32 - largest.leading_zeros()
+}
+
+/// Errors from resource creation that callers may want to react to, e.g. by
+/// evicting resources. Returned wrapped in anyhow::Error, use downcast_ref.
+#[derive(Debug)]
+pub enum ResourceError {
+    OutOfDeviceMemory,
+    OutOfHostMemory,
+}
+
+impl std::fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceError::OutOfDeviceMemory => write!(f, "Out of device memory"),
+            ResourceError::OutOfHostMemory => write!(f, "Out of host memory"),
+        }
+    }
+}
+
+impl std::error::Error for ResourceError {}
+
+/// Errors from Backend::try_new, lets apps fall back gracefully on machines
+/// without a usable Vulkan driver
+#[derive(Debug)]
+pub enum BackendError {
+    /// No Vulkan loader found or the driver is incompatible
+    NoVulkan,
+    InstanceCreationFailed(anyhow::Error),
+    SurfaceCreationFailed(anyhow::Error),
+    /// No device can present to the window or matches the DeviceSelection
+    NoSuitableDevice,
+    DeviceCreationFailed(anyhow::Error),
+    SwapchainCreationFailed(anyhow::Error),
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::NoVulkan => write!(f, "Vulkan not available"),
+            BackendError::InstanceCreationFailed(err) => {
+                write!(f, "Failed to create vulkan instance: {}", err)
+            },
+            BackendError::SurfaceCreationFailed(err) => {
+                write!(f, "Failed to create vulkan surface: {}", err)
+            },
+            BackendError::NoSuitableDevice => write!(f, "Failed to find a suitable device"),
+            BackendError::DeviceCreationFailed(err) => {
+                write!(f, "Failed to create vulkan device: {}", err)
+            },
+            BackendError::SwapchainCreationFailed(err) => {
+                write!(f, "Failed to create swapchain: {}", err)
+            },
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
 #[derive(Debug)]
 pub enum BeginFrameError {
     OutdatedSwapchain,
+    /// Backend was created without a surface
+    Headless,
+    /// The surface has to be recreated
+    SurfaceLost,
+    DeviceLost,
+    Other(vk::Result),
 }
 
 #[derive(Debug)]
 pub enum EndFrameError {
     OutdatedSwapchain,
+    /// Backend was created without a surface
+    Headless,
+    /// The surface has to be recreated
+    SurfaceLost,
+    DeviceLost,
+    Other(vk::Result),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PresentResult {
+    Presented,
+    /// Presented, but the swapchain no longer matches the surface exactly
+    Suboptimal,
+    /// Nothing was presented, the swapchain needs to be recreated
+    OutdatedSwapchain,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_clear_color_is_linear() {
+        let color = ClearColor::srgb(0.5, 0.0, 1.0, 0.5);
+        assert!((color.0[0] - 0.214).abs() < 0.001);
+        assert_eq!(color.0[1..], [0.0, 1.0, 0.5]);
+
+        // 8-bit 128 is just above 0.5
+        let color = ClearColor::srgb8(128, 128, 128, 255);
+        assert!((color.0[0] - 0.216).abs() < 0.001);
+        assert_eq!(color.0[3], 1.0);
+    }
+
+    #[test]
+    fn rect_from_extent_covers_it() {
+        let extent = Extent2d {
+            width: 1280,
+            height: 720,
+        };
+        let rect = Rect::from(extent);
+        assert_eq!((rect.x, rect.y), (0, 0));
+        assert_eq!(rect.extent(), extent);
+
+        let rect2d = vk::Rect2D::from(&rect);
+        assert_eq!(rect2d.offset, vk::Offset2D::default());
+        assert_eq!(rect2d.extent, extent);
+        let viewport = vk::Viewport::from(&Rect::new(8, 16, 32, 64));
+        assert_eq!((viewport.x, viewport.y), (8.0, 16.0));
+        assert_eq!((viewport.width, viewport.height), (32.0, 64.0));
+    }
 }
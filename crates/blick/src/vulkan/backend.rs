@@ -4,7 +4,7 @@ use anyhow::Result;
 
 use ash::vk;
 use ash_window::enumerate_required_extensions;
-use raw_window_handle::HasRawDisplayHandle;
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 use std::sync::Arc;
 use winit::window::Window;
 
@@ -16,129 +16,110 @@ pub struct Frame {
     pub swapchain_image: super::swapchain::SwapchainImage,
 }
 
-pub struct Backend {
+/// Surface and swapchain of a window. A backend owns the context of the window
+/// it was created with, additional windows sharing the same device get their
+/// own through Backend::create_surface_context.
+pub struct SurfaceContext {
     swapchain_desc: super::SwapchainDesc,
     swapchain: super::Swapchain,
+    /// Swapchain replaced by the last recreation, kept alive until the next
+    /// one since frames presented from it might still be in flight
+    old_swapchain: Option<super::Swapchain>,
+    /// Declared after the swapchains, they have to be destroyed first
+    surface: super::Surface,
+    /// Refreshed on swapchain recreation
+    surface_capabilities: crate::SurfaceCapabilities,
 
+    /// Semaphores of the last frame presented for each swapchain image, kept
+    /// alive until the image is acquired again since present doesn't wait.
+    retired_semaphores: Vec<Option<(crate::Semaphore, crate::Semaphore)>>,
+
+    transition_images: bool,
+    device: Arc<super::Device>,
+    /// Has to outlive the surface
     #[allow(dead_code)]
     instance: Arc<super::Instance>,
-    device: Arc<super::Device>,
-    surface: super::Surface,
 }
 
-impl Backend {
-    pub fn new(
-        window: &Window,
-        config: BackendConfig,
-    ) -> Self {
-        let instance = Arc::new(
-            super::Instance::new(
-                enumerate_required_extensions(window.raw_display_handle()).unwrap(),
-                config.debugging,
-            )
-                .expect("Failed to create vulkan instance")
-        );
+/// See Backend::on_swapchain_recreated
+pub type SwapchainRecreatedCallback = Box<dyn FnMut(&super::SwapchainDesc)>;
 
-        let surface = super::Surface::new(
-            &instance,
-            window,
-        )
-            .expect("Failed to create vulkan surface");
-
-        let physical_devices = instance
-            .enumerate_physical_devices()
-            .expect("Failed to enumerate physical devices");
-
-        log::info!("Available devices:");
-        physical_devices.iter().for_each(|device| {
-            log::info!("    {:?}", super::vk_to_string(&device.properties.device_name));
-        });
-
-        // Filter devices supporting presentation
-        let physical_devices = physical_devices
-            .into_iter()
-            .filter(|device| {
-                device.queue_families
-                    .iter()
-                    .any(|queue_family| {
-                        surface.supports_queue_family(device, queue_family.index)
-                    })
-            });
-
-        // Pick first GPU, if no GPU pick first integrated
-        let physical_device = physical_devices
-            .rev() // Rev due to max_by_key picking from the bottom
-            .max_by_key(|device| match device.properties.device_type {
-                vk::PhysicalDeviceType::VIRTUAL_GPU => 10,
-                vk::PhysicalDeviceType::INTEGRATED_GPU => 100,
-                vk::PhysicalDeviceType::DISCRETE_GPU => 1000,
-                _ => 0,
-            })
-            .unwrap();
-
-        log::info!(
-            "Using device: {}",
-            super::vk_to_string(&physical_device.properties.device_name)
-        );
+pub struct Backend {
+    /// None for headless backends
+    presentation: Option<SurfaceContext>,
 
-        let device = Arc::new(
-            super::Device::new(
-                &instance,
-                physical_device,
-                &config,
-            )
-                .expect("Failed to create vulkan device")
-        );
+    instance: Arc<super::Instance>,
+    device: Arc<super::Device>,
 
-        let surface_capabilities = surface.query_surface_capabilities(
-            &device.inner.physical_device
-        );
+    transition_swapchain_images: bool,
+    /// Set when a recreation changed the swapchain format
+    swapchain_format_changed: bool,
+    swapchain_recreated: Option<SwapchainRecreatedCallback>,
 
-        let surface_formats = surface.query_surface_formats(
-            &device.inner.physical_device
-        );
+    /// Only available if enabled and running under RenderDoc
+    renderdoc: Option<super::renderdoc::RenderDoc>,
+    /// Capture requested with trigger_capture, started on the next begin_frame
+    capture_requested: bool,
+    capturing: bool,
+}
 
-        let surface_present_modes = surface.query_surface_present_modes(
-            &device.inner.physical_device
-        );
+impl SurfaceContext {
+    fn new(
+        instance: &Arc<super::Instance>,
+        device: &Arc<super::Device>,
+        surface: super::Surface,
+        width: u32,
+        height: u32,
+        transition_images: bool,
+    ) -> Result<Self> {
+        let physical_device = &device.inner.physical_device;
+        let surface_capabilities = surface.query_surface_capabilities(physical_device);
+        let surface_formats = surface.query_surface_formats(physical_device);
+        let surface_present_modes = surface.query_surface_present_modes(physical_device);
     
         let swapchain_desc = make_swapchain_desc(
-            window.inner_size().width,
-            window.inner_size().height,
+            width,
+            height,
             &surface_capabilities,
             &surface_formats,
             &surface_present_modes,
             true
         );
 
-        let swapchain = super::Swapchain::new(
+        let mut swapchain = super::Swapchain::new(
             &device.inner,
             &surface,
             &swapchain_desc,
             None,
-        );
+        )
+            .map_err(|err| anyhow::anyhow!("Failed to create swapchain: {:?}", err))?;
 
-        Self {
-            surface,
-            swapchain,
-            swapchain_desc,
-            instance,
-            device,
+        if transition_images {
+            transition_swapchain_images(device, &mut swapchain)?;
         }
+
+        let retired_semaphores = (0..swapchain.image_count()).map(|_| None).collect();
+
+        Ok(Self {
+            swapchain_desc,
+            swapchain,
+            old_swapchain: None,
+            surface,
+            surface_capabilities,
+            retired_semaphores,
+            transition_images,
+            device: device.clone(),
+            instance: instance.clone(),
+        })
     }
 
     /// Acquires swapchain image
-    pub fn begin_frame(&mut self) -> Result<Frame, crate::BeginFrameError> {
-        // TODO: Investigate best way of setting up a frame
-
+    pub fn begin_frame(&self) -> Result<Frame, crate::BeginFrameError> {
         // TODO: Don't recreate every frame
         let image_available = self.device.create_semaphore().unwrap();
         let render_finished = self.device.create_semaphore().unwrap();
 
-        let swapchain_image = match self.swapchain.acquire_next_image(&image_available) {
-            Ok(image) => image,
-            Err(_) => return Err(crate::BeginFrameError::OutdatedSwapchain),
-        };
+        let swapchain_image = self.swapchain.acquire_next_image(&image_available)?;
 
         Ok(Frame {
             image_available,
@@ -146,49 +127,747 @@ impl Backend {
             swapchain_image,
         })
     }
-    pub fn end_frame(&mut self, frame: Frame) -> Result<(), crate::EndFrameError> {
-        match self.swapchain.present_image(
+
+    /// Presents the frame without waiting for the device, see Backend::present
+    pub fn present(
+        &mut self,
+        frame: Frame,
+    ) -> Result<crate::PresentResult, crate::EndFrameError> {
+        let result = match self.swapchain.present_image(
             &self.device.inner.universal_queue,
             &frame.swapchain_image,
             &frame.render_finished,
         ) {
-            Ok(_) => (),
+            Ok(false) => Ok(crate::PresentResult::Presented),
+            Ok(true) => Ok(crate::PresentResult::Suboptimal),
             Err(swapchain::SwapchainError::Outdated) => {
-                return Err(crate::EndFrameError::OutdatedSwapchain)
+                Ok(crate::PresentResult::OutdatedSwapchain)
             },
+            Err(err) => Err(err.into()),
+        };
+
+        // Replaces semaphores of the previous present of this image, since the
+        // image was acquired again that present has completed.
+        if let Some(slot) = self.retired_semaphores
+            .get_mut(frame.swapchain_image.index as usize)
+        {
+            *slot = Some((frame.image_available, frame.render_finished));
         }
 
-        self.device.wait_idle().unwrap();
+        result
+    }
 
-        Ok(())
+    /// Does nothing if the resulting extent is the same as the current one,
+    /// returns whether the swapchain was recreated
+    pub fn resize_swapchain(&mut self, width: u32, height: u32) -> bool {
+        // Current extent follows the window
+        self.surface_capabilities = self.surface.query_surface_capabilities(
+            &self.device.inner.physical_device
+        );
+
+        let extent = make_swapchain_extent(
+            &self.surface_capabilities,
+            width,
+            height
+        );
+        if extent == self.swapchain_desc.extent {
+            return false;
+        }
+
+        self.recreate_swapchain(width, height);
+        true
     }
 
-    pub fn resize_swapchain(&mut self, width: u32, height: u32) {
-        let surface_capabilities = self.surface.query_surface_capabilities(
+    /// Recreates the swapchain even if the extent is unchanged, e.g. after
+    /// OutdatedSwapchain
+    pub fn recreate_swapchain(&mut self, width: u32, height: u32) {
+        self.surface_capabilities = self.surface.query_surface_capabilities(
             &self.device.inner.physical_device
         );
 
         self.swapchain_desc = super::SwapchainDesc {
             extent: make_swapchain_extent(
-                &surface_capabilities,
+                &self.surface_capabilities,
                 width,
                 height
             ),
             ..self.swapchain_desc
         };
 
-        self.swapchain = super::Swapchain::new(
+        let swapchain = super::Swapchain::new(
             &self.device.inner,
             &self.surface,
             &self.swapchain_desc,
             Some(&self.swapchain),
+        )
+            .expect("Failed to create swapchain");
+        // Drops the swapchain from the recreation before this one
+        self.old_swapchain = Some(
+            std::mem::replace(&mut self.swapchain, swapchain)
         );
+
+        // Old swapchain images won't be acquired again, make sure nothing is still using
+        // the semaphores of their frames before releasing them.
+        self.device.wait_idle().unwrap();
+        self.retired_semaphores = (0..self.swapchain.image_count())
+            .map(|_| None)
+            .collect();
+
+        if self.transition_images {
+            transition_swapchain_images(&self.device, &mut self.swapchain)
+                .expect("Failed to transition swapchain images");
+        }
+    }
+
+    /// Recreates the swapchain with the given composite alpha and pre-transform,
+    /// e.g. PRE_MULTIPLIED for transparent windows. Fails if the surface doesn't
+    /// support them.
+    pub fn set_swapchain_composition(
+        &mut self,
+        composite_alpha: vk::CompositeAlphaFlagsKHR,
+        pre_transform: vk::SurfaceTransformFlagsKHR,
+    ) -> Result<()> {
+        if !self.surface_capabilities.supported_composite_alpha.contains(composite_alpha) {
+            anyhow::bail!("Composite alpha {:?} not supported by the surface", composite_alpha);
+        }
+        if !self.surface_capabilities.supported_transforms.contains(pre_transform) {
+            anyhow::bail!("Pre-transform {:?} not supported by the surface", pre_transform);
+        }
+
+        self.swapchain_desc.composite_alpha = composite_alpha;
+        self.swapchain_desc.pre_transform = pre_transform;
+        let extent = self.swapchain_desc.extent;
+        self.recreate_swapchain(extent.width, extent.height);
+        Ok(())
+    }
+
+    pub fn swapchain_desc(&self) -> &super::SwapchainDesc {
+        &self.swapchain_desc
+    }
+    /// Capabilities as of the last swapchain (re)creation
+    pub fn surface_capabilities(&self) -> &crate::SurfaceCapabilities {
+        &self.surface_capabilities
+    }
+}
+
+impl Backend {
+    /// Panics on failure, see try_new
+    pub fn new(
+        window: &Window,
+        config: BackendConfig,
+    ) -> Self {
+        Self::try_new(window, config)
+            .expect("Failed to create backend")
+    }
+
+    pub fn try_new(
+        window: &Window,
+        config: BackendConfig,
+    ) -> Result<Self, crate::BackendError> {
+        Self::try_from_raw_handle(
+            window,
+            window.inner_size().width,
+            window.inner_size().height,
+            config,
+        )
+    }
+
+    /// For windowing libraries other than winit, width and height are the
+    /// initial size of the window in pixels. Panics on failure, see
+    /// try_from_raw_handle
+    pub fn from_raw_handle<W: HasRawWindowHandle + HasRawDisplayHandle>(
+        window: &W,
+        width: u32,
+        height: u32,
+        config: BackendConfig,
+    ) -> Self {
+        Self::try_from_raw_handle(window, width, height, config)
+            .expect("Failed to create backend")
+    }
+
+    pub fn try_from_raw_handle<W: HasRawWindowHandle + HasRawDisplayHandle>(
+        window: &W,
+        width: u32,
+        height: u32,
+        mut config: BackendConfig,
+    ) -> Result<Self, crate::BackendError> {
+        use crate::BackendError;
+
+        let required_extensions = enumerate_required_extensions(window.raw_display_handle())
+            .map_err(|err| BackendError::InstanceCreationFailed(err.into()))?;
+
+        // RenderDoc has to be attached before the instance is created
+        let renderdoc = config.renderdoc
+            .then(super::renderdoc::RenderDoc::new)
+            .flatten();
+        let instance = create_instance(required_extensions, &mut config)?;
+
+        let surface = super::Surface::new(
+            &instance,
+            window,
+        )
+            .map_err(BackendError::SurfaceCreationFailed)?;
+
+        let device = create_device(&instance, Some(&surface), &config)?;
+
+        let presentation = SurfaceContext::new(
+            &instance,
+            &device,
+            surface,
+            width,
+            height,
+            config.transition_swapchain_images,
+        )
+            .map_err(BackendError::SwapchainCreationFailed)?;
+
+        Ok(Self {
+            presentation: Some(presentation),
+            instance,
+            device,
+            transition_swapchain_images: config.transition_swapchain_images,
+            swapchain_format_changed: false,
+            swapchain_recreated: None,
+            renderdoc,
+            capture_requested: false,
+            capturing: false,
+        })
     }
+
+    /// Backend without surface and swapchain, e.g. for compute work and
+    /// tests. Frames can't be begun or presented. Neither the surface nor the
+    /// swapchain extensions are enabled, so no surface can be added later.
+    pub fn new_headless(mut config: BackendConfig) -> Result<Self, crate::BackendError> {
+        let renderdoc = config.renderdoc
+            .then(super::renderdoc::RenderDoc::new)
+            .flatten();
+        let instance = create_instance(&[], &mut config)?;
+        let device = create_device(&instance, None, &config)?;
+
+        Ok(Self {
+            presentation: None,
+            instance,
+            device,
+            transition_swapchain_images: config.transition_swapchain_images,
+            swapchain_format_changed: false,
+            swapchain_recreated: None,
+            renderdoc,
+            capture_requested: false,
+            capturing: false,
+        })
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.presentation.is_none()
+    }
+
+    /// Creates a surface and swapchain for another window presenting with the
+    /// same device. Frames of the context are begun and presented through it,
+    /// Device::end_frame/release_frame are called once for all windows.
+    /// Requires the backend to have been created with a window, headless
+    /// instances lack the surface extensions.
+    pub fn create_surface_context<W: HasRawWindowHandle + HasRawDisplayHandle>(
+        &self,
+        window: &W,
+        width: u32,
+        height: u32,
+    ) -> Result<SurfaceContext> {
+        let surface = super::Surface::new(&self.instance, window)?;
+
+        if !surface.supports_queue_family(
+            &self.device.inner.physical_device,
+            self.device.inner.universal_queue.family.index,
+        ) {
+            anyhow::bail!("Device can't present to the new surface");
+        }
+
+        SurfaceContext::new(
+            &self.instance,
+            &self.device,
+            surface,
+            width,
+            height,
+            self.transition_swapchain_images,
+        )
+    }
+
+    /// Acquires swapchain image
+    pub fn begin_frame(&mut self) -> Result<Frame, crate::BeginFrameError> {
+        // TODO: Investigate best way of setting up a frame
+        let frame = self.presentation
+            .as_ref()
+            .ok_or(crate::BeginFrameError::Headless)?
+            .begin_frame()?;
+
+        if let Some(renderdoc) = self.renderdoc.as_ref() {
+            if std::mem::take(&mut self.capture_requested) {
+                renderdoc.start_frame_capture();
+                self.capturing = true;
+            }
+        }
+
+        Ok(frame)
+    }
+
+    /// Captures the next frame, from begin_frame until it is presented, if
+    /// running under RenderDoc with BackendConfig::renderdoc. No-op otherwise.
+    pub fn trigger_capture(&mut self) {
+        if self.renderdoc.is_some() {
+            self.capture_requested = true;
+        }
+    }
+    /// Presents the frame and waits for the device to be idle, resources
+    /// dropped during the frame are destroyed
+    pub fn end_frame(&mut self, frame: Frame) -> Result<(), crate::EndFrameError> {
+        let present_result = self.present(frame);
+
+        let frame_index = self.device.end_frame();
+        self.device.wait_idle().map_err(end_frame_error)?;
+        self.device.release_frame(frame_index);
+
+        if present_result? == crate::PresentResult::OutdatedSwapchain {
+            return Err(crate::EndFrameError::OutdatedSwapchain);
+        }
+
+        Ok(())
+    }
+
+    /// Submits the command buffers of the frame and ends it, see end_frame
+    /// and submit_frame_commands.
+    pub fn submit_frame(
+        &mut self,
+        frame: Frame,
+        command_buffers: &[&crate::CommandBuffer],
+    ) -> Result<(), crate::EndFrameError> {
+        self.submit_frame_commands(&frame, command_buffers)?;
+        self.end_frame(frame)
+    }
+
+    /// Submits the command buffers of the frame without ending it. The
+    /// submission waits for the swapchain image to be acquired before
+    /// COLOR_ATTACHMENT_OUTPUT and signals render_finished for the present.
+    pub fn submit_frame_commands(
+        &self,
+        frame: &Frame,
+        command_buffers: &[&crate::CommandBuffer],
+    ) -> Result<(), crate::EndFrameError> {
+        self.device
+            .submit_batch(
+                &[crate::SubmitDesc {
+                    command_buffers,
+                    wait_semaphores: &[(
+                        &frame.image_available,
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                    )],
+                    signal_semaphores: &[&frame.render_finished],
+                }],
+                None,
+            )
+            .map_err(end_frame_error)
+    }
+
+    /// Reads back the swapchain image of frame and blocks until done. Call
+    /// between submit_frame_commands and end_frame or present, the submitted
+    /// commands have to leave the image in PRESENT_SRC_KHR. Requires the
+    /// surface to support TRANSFER_SRC, see SwapchainDesc::usage.
+    pub fn capture_frame(&self, frame: &Frame) -> Result<crate::CaptureImage> {
+        let image = &frame.swapchain_image.image;
+        if !self.swapchain_desc().usage.contains(crate::ImageUsage::TRANSFER_SRC) {
+            anyhow::bail!("Surface doesn't support reading back swapchain images");
+        }
+        let texel_size = super::image::texel_size(image.desc.format)
+            .ok_or_else(|| anyhow::anyhow!(
+                "Unsupported format {:?} for capture_frame",
+                image.desc.format,
+            ))?;
+        let extent = image.desc.extent;
+
+        let buffer = self.device.create_buffer(crate::BufferDesc {
+            size: texel_size as u64 * extent.width as u64 * extent.height as u64,
+            usage: crate::BufferUsage::MAP_READ | crate::BufferUsage::TRANSFER_DST,
+            min_alignment: None,
+            memory_location: None,
+            name: Some("capture".to_owned()),
+        })?;
+
+        let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            crate::ImageBarrier {
+                image,
+                src_access_mask,
+                dst_access_mask,
+                old_layout,
+                new_layout,
+                subresource_range: crate::ImageSubresourceRange::all(
+                    crate::ImageAspectFlags::COLOR,
+                ),
+                queue_transfer: None,
+            }
+        };
+
+        // Submitted after the frame on the same queue, the barriers order the
+        // copy after its rendering
+        self.device.run_commands(|command_buffer| {
+            command_buffer.transition(
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::PRESENT_SRC_KHR,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    vk::AccessFlags::TRANSFER_READ,
+                )],
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+            );
+            command_buffer.copy_image_to_buffer(
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                0,
+                extent,
+                &buffer,
+                0,
+            );
+            command_buffer.transition(
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::PRESENT_SRC_KHR,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::empty(),
+                )],
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            );
+        })?;
+
+        let pixels = buffer.read_slice(0, buffer.size() as usize)?;
+        super::buffer::destroy_idle(buffer);
+
+        Ok(crate::CaptureImage {
+            pixels,
+            format: image.desc.format,
+            extent: crate::Extent2d {
+                width: extent.width,
+                height: extent.height,
+            },
+        })
+    }
+
+    /// Presents the frame without waiting for the device, it is up to the
+    /// caller to synchronize reuse of any resources used by the frame and to
+    /// release dropped resources through Device::end_frame/release_frame.
+    /// Errors other than an outdated swapchain are returned as EndFrameError.
+    pub fn present(
+        &mut self,
+        frame: Frame,
+    ) -> Result<crate::PresentResult, crate::EndFrameError> {
+        let result = self.presentation
+            .as_mut()
+            .ok_or(crate::EndFrameError::Headless)?
+            .present(frame);
+
+        if let Some(renderdoc) = self.renderdoc.as_ref() {
+            if std::mem::take(&mut self.capturing) {
+                renderdoc.end_frame_capture();
+            }
+        }
+
+        result
+    }
+
+    /// Does nothing for headless backends or if the resulting extent is the
+    /// same as the current one, see recreate_swapchain
+    pub fn resize_swapchain(&mut self, width: u32, height: u32) {
+        let recreated = match self.presentation.as_mut() {
+            Some(presentation) => presentation.resize_swapchain(width, height),
+            None => false,
+        };
+        if recreated {
+            self.notify_swapchain_recreated();
+        }
+    }
+
+    /// Recreates the swapchain even if the extent is unchanged, e.g. after
+    /// OutdatedSwapchain. Does nothing for headless backends
+    pub fn recreate_swapchain(&mut self, width: u32, height: u32) {
+        if let Some(presentation) = self.presentation.as_mut() {
+            presentation.recreate_swapchain(width, height);
+            self.notify_swapchain_recreated();
+        }
+    }
+
+    /// See SurfaceContext::set_swapchain_composition, panics for headless backends
+    pub fn set_swapchain_composition(
+        &mut self,
+        composite_alpha: vk::CompositeAlphaFlagsKHR,
+        pre_transform: vk::SurfaceTransformFlagsKHR,
+    ) -> Result<()> {
+        self.presentation
+            .as_mut()
+            .expect("Headless backend has no surface or swapchain")
+            .set_swapchain_composition(composite_alpha, pre_transform)?;
+        self.notify_swapchain_recreated();
+        Ok(())
+    }
+    /// Recreates the surface and swapchain, e.g. after SurfaceLost or when the
+    /// window has been recreated. On failure the backend is left without a
+    /// surface, as if headless, until a later call succeeds.
+    pub fn recreate_surface(&mut self, window: &Window) -> Result<()> {
+        self.recreate_surface_from_raw_handle(
+            window,
+            window.inner_size().width,
+            window.inner_size().height,
+        )
+    }
+
+    /// See recreate_surface and from_raw_handle
+    pub fn recreate_surface_from_raw_handle<W: HasRawWindowHandle + HasRawDisplayHandle>(
+        &mut self,
+        window: &W,
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        // Nothing may use the swapchains or their semaphores after this
+        self.device.wait_idle()?;
+
+        // The old swapchain and surface have to be gone before new ones are
+        // created for the same window, the window can only have one of each
+        let old_format = self.presentation
+            .take()
+            .map(|presentation| presentation.swapchain_desc.format);
+
+        let surface = super::Surface::new(&self.instance, window)?;
+
+        if !surface.supports_queue_family(
+            &self.device.inner.physical_device,
+            self.device.inner.universal_queue.family.index,
+        ) {
+            anyhow::bail!("Device can't present to the new surface");
+        }
+
+        // Old swapchain belongs to the old surface, can't be reused
+        let presentation = SurfaceContext::new(
+            &self.instance,
+            &self.device,
+            surface,
+            width,
+            height,
+            self.transition_swapchain_images,
+        )?;
+
+        if old_format.is_some_and(|format| format != presentation.swapchain_desc.format) {
+            self.swapchain_format_changed = true;
+        }
+
+        self.presentation = Some(presentation);
+        self.notify_swapchain_recreated();
+        Ok(())
+    }
+
+    /// Registers a callback invoked with the new description whenever the
+    /// swapchain is recreated, replacing any previous callback. Render passes
+    /// and pipelines built for the old format or extent are stale after this.
+    pub fn on_swapchain_recreated(
+        &mut self,
+        callback: SwapchainRecreatedCallback,
+    ) {
+        self.swapchain_recreated = Some(callback);
+    }
+
+    fn notify_swapchain_recreated(&mut self) {
+        if let (Some(callback), Some(presentation)) = (
+            self.swapchain_recreated.as_mut(),
+            self.presentation.as_ref(),
+        ) {
+            callback(&presentation.swapchain_desc);
+        }
+    }
+
     pub fn device(&self) -> &super::Device {
         &self.device
     }
+    /// Capabilities as of the last swapchain (re)creation, panics for headless
+    /// backends
+    pub fn surface_capabilities(&self) -> &crate::SurfaceCapabilities {
+        &self.presentation().surface_capabilities
+    }
+    pub fn device_info(&self) -> crate::DeviceInfo {
+        self.device.info()
+    }
+    /// Panics for headless backends
     pub fn swapchain_desc(&self) -> &super::SwapchainDesc {
-        &self.swapchain_desc
+        &self.presentation().swapchain_desc
+    }
+    /// Format of the current swapchain, panics for headless backends
+    pub fn swapchain_format(&self) -> crate::ImageFormat {
+        self.presentation().swapchain_desc.format
+    }
+    /// Present mode of the current swapchain, kept across resizes. Panics for
+    /// headless backends
+    pub fn swapchain_present_mode(&self) -> vk::PresentModeKHR {
+        self.presentation().swapchain_desc.present_mode
+    }
+    /// Whether the swapchain format changed since the last call, in which case
+    /// render passes and pipelines using the old format have to be rebuilt
+    pub fn take_swapchain_format_changed(&mut self) -> bool {
+        std::mem::take(&mut self.swapchain_format_changed)
+    }
+
+    fn presentation(&self) -> &SurfaceContext {
+        self.presentation
+            .as_ref()
+            .expect("Headless backend has no surface or swapchain")
+    }
+}
+
+fn create_instance(
+    required_extensions: &'static [*const std::os::raw::c_char],
+    config: &mut BackendConfig,
+) -> Result<Arc<super::Instance>, crate::BackendError> {
+    super::Instance::new(required_extensions, config)
+        .map(Arc::new)
+        .map_err(|err| {
+            let no_vulkan = err.downcast_ref::<ash::LoadingError>().is_some()
+                || err.downcast_ref::<vk::Result>()
+                    == Some(&vk::Result::ERROR_INCOMPATIBLE_DRIVER);
+            if no_vulkan {
+                crate::BackendError::NoVulkan
+            } else {
+                crate::BackendError::InstanceCreationFailed(err)
+            }
+        })
+}
+
+/// Only devices able to present to surface are considered if given
+fn create_device(
+    instance: &Arc<super::Instance>,
+    surface: Option<&super::Surface>,
+    config: &BackendConfig,
+) -> Result<Arc<super::Device>, crate::BackendError> {
+    use crate::BackendError;
+
+    let physical_devices = instance
+        .enumerate_physical_devices()
+        .map_err(BackendError::InstanceCreationFailed)?;
+
+    log::info!("Available devices:");
+    physical_devices.iter().for_each(|device| {
+        log::info!("    {:?}", super::vk_to_string(&device.properties.device_name));
+    });
+
+    // Filter devices supporting presentation
+    let physical_devices = physical_devices
+        .into_iter()
+        .filter(|device| {
+            match surface {
+                Some(surface) => device.queue_families
+                    .iter()
+                    .any(|queue_family| {
+                        surface.supports_queue_family(device, queue_family.index)
+                    }),
+                None => true,
+            }
+        });
+
+    let physical_device = select_physical_device(
+        physical_devices.collect(),
+        &config.device_selection,
+    )
+        .ok_or(BackendError::NoSuitableDevice)?;
+
+    log::info!(
+        "Using device: {}",
+        super::vk_to_string(&physical_device.properties.device_name)
+    );
+
+    Ok(Arc::new(
+        super::Device::new(
+            instance,
+            physical_device,
+            surface.is_some(),
+            config,
+        )
+            .map_err(BackendError::DeviceCreationFailed)?
+    ))
+}
+
+/// Transitions all images of a newly created swapchain from UNDEFINED to
+/// PRESENT_SRC_KHR in a single submit, waits for the transition to complete.
+fn transition_swapchain_images(
+    device: &super::Device,
+    swapchain: &mut super::Swapchain,
+) -> Result<()> {
+    let image_barriers = swapchain.images()
+        .iter()
+        .map(|swapchain_image| {
+            crate::ImageBarrier {
+                image: &swapchain_image.image,
+                src_access_mask: vk::AccessFlags::empty(),
+                dst_access_mask: vk::AccessFlags::empty(),
+                old_layout: vk::ImageLayout::UNDEFINED,
+                new_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                subresource_range: crate::ImageSubresourceRange::all(
+                    vk::ImageAspectFlags::COLOR,
+                ),
+                queue_transfer: None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    device.run_commands(|command_buffer| {
+        command_buffer.transition(
+            &[],
+            &[],
+            &image_barriers,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        );
+    })?;
+
+    swapchain.set_image_layout(vk::ImageLayout::PRESENT_SRC_KHR);
+    Ok(())
+}
+
+/// Picks a device from the list of candidates according to the selection
+fn select_physical_device(
+    physical_devices: Vec<super::PhysicalDevice>,
+    selection: &crate::DeviceSelection,
+) -> Option<super::PhysicalDevice> {
+    match selection {
+        crate::DeviceSelection::HighPerformance => {
+            // Pick first GPU, if no GPU pick first integrated
+            physical_devices
+                .into_iter()
+                .rev() // Rev due to max_by_key picking from the bottom
+                .max_by_key(|device| match device.properties.device_type {
+                    vk::PhysicalDeviceType::VIRTUAL_GPU => 10,
+                    vk::PhysicalDeviceType::INTEGRATED_GPU => 100,
+                    vk::PhysicalDeviceType::DISCRETE_GPU => 1000,
+                    _ => 0,
+                })
+        },
+        crate::DeviceSelection::LowPower => {
+            // Pick first integrated, if no integrated pick first GPU
+            physical_devices
+                .into_iter()
+                .rev()
+                .max_by_key(|device| match device.properties.device_type {
+                    vk::PhysicalDeviceType::VIRTUAL_GPU => 10,
+                    vk::PhysicalDeviceType::DISCRETE_GPU => 100,
+                    vk::PhysicalDeviceType::INTEGRATED_GPU => 1000,
+                    _ => 0,
+                })
+        },
+        crate::DeviceSelection::ByName(name) => {
+            physical_devices
+                .into_iter()
+                .find(|device| device.name().contains(name.as_str()))
+        },
+        crate::DeviceSelection::Index(index) => {
+            physical_devices
+                .into_iter()
+                .nth(*index)
+        },
     }
 }
 
@@ -209,6 +888,14 @@ fn make_swapchain_extent(
     }
 }
 
+fn end_frame_error(err: anyhow::Error) -> crate::EndFrameError {
+    match err.downcast_ref::<vk::Result>() {
+        Some(&vk::Result::ERROR_DEVICE_LOST) => crate::EndFrameError::DeviceLost,
+        Some(result) => crate::EndFrameError::Other(*result),
+        None => crate::EndFrameError::Other(vk::Result::ERROR_UNKNOWN),
+    }
+}
+
 /// Creates a compatible swapchain config
 fn make_swapchain_desc(
     width: u32,
@@ -256,6 +943,42 @@ fn make_swapchain_desc(
         image_count
     };
 
+    // Allow blitting and copying directly to swapchain images, and reading
+    // them back for Backend::capture_frame
+    let mut usage = crate::ImageUsage::COLOR_ATTACHMENT;
+    if surface_capabilities
+        .supported_usage_flags
+        .contains(vk::ImageUsageFlags::TRANSFER_DST)
+    {
+        usage |= crate::ImageUsage::TRANSFER_DST;
+    }
+    if surface_capabilities
+        .supported_usage_flags
+        .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+    {
+        usage |= crate::ImageUsage::TRANSFER_SRC;
+    }
+
+    let composite_alpha = [
+        vk::CompositeAlphaFlagsKHR::OPAQUE,
+        vk::CompositeAlphaFlagsKHR::INHERIT,
+        vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+        vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+    ]
+        .into_iter()
+        .find(|alpha| surface_capabilities.supported_composite_alpha.contains(*alpha))
+        // At least one bit is guaranteed to be supported
+        .unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE);
+
+    let pre_transform = if surface_capabilities
+        .supported_transforms
+        .contains(vk::SurfaceTransformFlagsKHR::IDENTITY)
+    {
+        vk::SurfaceTransformFlagsKHR::IDENTITY
+    } else {
+        surface_capabilities.current_transform
+    };
+
     super::SwapchainDesc {
         format: surface_format.format,
         color_space: surface_format.color_space,
@@ -263,6 +986,9 @@ fn make_swapchain_desc(
         // TODO:
         present_mode,
         image_count,
+        usage,
+        composite_alpha,
+        pre_transform,
     }
 }
 
@@ -2,6 +2,7 @@ use crate::BufferDesc;
 
 use anyhow::Result;
 use ash::vk;
+use ash::vk::Handle;
 
 use gpu_allocator::MemoryLocation;
 use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, AllocationScheme};
@@ -10,12 +11,23 @@ use std::sync::Arc;
 
 pub struct Buffer {
     pub(super) raw: vk::Buffer,
+    size: u64,
+    usage: crate::BufferUsage,
+    /// Mapped memory does not need explicit flush/invalidate
+    coherent: bool,
     allocation: Option<Allocation>,
     device: Arc<super::DeviceInner>,
 }
 
+pub struct BufferView {
+    pub(super) raw: vk::BufferView,
+    /// Keeps the buffer alive for as long as the view
+    _buffer: crate::Buffer,
+    device: Arc<super::DeviceInner>,
+}
+
 impl Buffer {
-    pub(super) fn new(device: &Arc<super::DeviceInner>, desc: BufferDesc) -> Self {
+    pub(super) fn new(device: &Arc<super::DeviceInner>, desc: BufferDesc) -> Result<Self> {
         let buffer_create_info = vk::BufferCreateInfo::builder()
             .size(desc.size)
             .usage((&desc.usage).into())
@@ -24,65 +36,302 @@ impl Buffer {
 
         let buffer = unsafe {
             device.raw.create_buffer(&buffer_create_info, None)
-                .expect("Failed to create buffer")
+                .map_err(super::resource_error)?
         };
 
-        let memory_requirements = unsafe {
+        let mut memory_requirements = unsafe {
             device.raw.get_buffer_memory_requirements(buffer)
         };
 
-        let allocation = device.allocator
-            .as_ref()
-            .unwrap()
-            .lock()
-            .unwrap()
-            .allocate(
-                &AllocationCreateDesc {
-                    name: "buffer",
-                    requirements: memory_requirements,
-                    location: MemoryLocation::from(&desc.usage),
-                    linear: true,
-                    allocation_scheme: AllocationScheme::GpuAllocatorManaged,
-                }
-            )
-            .expect("Failed to allocate buffer memory");
+        if let Some(min_alignment) = desc.min_alignment {
+            memory_requirements.alignment = memory_requirements.alignment.max(min_alignment);
+        }
+        // Keeps flush and invalidate ranges, which are rounded to whole atoms,
+        // within the allocation in case the memory isn't coherent
+        let atom_size = device.physical_device.properties.limits.non_coherent_atom_size;
+        memory_requirements.alignment = memory_requirements.alignment.max(atom_size);
+        memory_requirements.size = (memory_requirements.size + atom_size - 1) & !(atom_size - 1);
 
-        unsafe {
-            device.raw.bind_buffer_memory(buffer, allocation.memory(), allocation.offset())
-                .expect("Failed to bind buffer memory")
+        let location = desc.memory_location
+            .unwrap_or_else(|| MemoryLocation::from(&desc.usage));
+
+        let allocation = match device.allocate(
+            &AllocationCreateDesc {
+                name: desc.name.as_deref().unwrap_or("buffer"),
+                requirements: memory_requirements,
+                location,
+                linear: true,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            }
+        ) {
+            Ok(allocation) => allocation,
+            Err(err) => {
+                unsafe { device.raw.destroy_buffer(buffer, None) };
+                return Err(super::allocation_error(err));
+            }
         };
 
-        Self {
+        if let Err(err) = unsafe {
+            device.raw.bind_buffer_memory(buffer, allocation.memory(), allocation.offset())
+        } {
+            device.free(allocation)?;
+            unsafe { device.raw.destroy_buffer(buffer, None) };
+            return Err(super::resource_error(err));
+        }
+
+        if let Some(name) = desc.name.as_deref() {
+            device.set_object_name(buffer, name);
+        }
+
+        Ok(Self {
             raw: buffer,
+            size: desc.size,
+            usage: desc.usage,
+            // gpu_allocator only requires HOST_COHERENT for these locations,
+            // other memory might still be host visible and mapped, in which
+            // case flush and invalidate are required
+            coherent: matches!(location, MemoryLocation::CpuToGpu | MemoryLocation::GpuToCpu),
             allocation: Some(allocation),
             device: device.clone(),
+        })
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn usage(&self) -> crate::BufferUsage {
+        self.usage
+    }
+
+    /// Requires the buffer to be created with SHADER_DEVICE_ADDRESS
+    pub fn device_address(&self) -> u64 {
+        debug_assert!(
+            self.usage.contains(crate::BufferUsage::SHADER_DEVICE_ADDRESS),
+            "device_address: buffer not created with SHADER_DEVICE_ADDRESS"
+        );
+
+        unsafe {
+            self.device.raw.get_buffer_device_address(
+                &vk::BufferDeviceAddressInfo::builder()
+                    .buffer(self.raw)
+                    .build()
+            )
         }
     }
+
+    /// Requires the buffer to be created with MAP_READ or MAP_WRITE, or in a
+    /// host visible memory location
     pub fn mapped_ptr<T>(&self) -> Result<*mut T> {
+        match self.allocation.as_ref().unwrap().mapped_ptr() {
+            Some(ptr) => Ok(ptr.as_ptr() as *mut _),
+            None => Err(anyhow::anyhow!(
+                "Buffer is not mappable, requires MAP_READ or MAP_WRITE usage"
+            )),
+        }
+    }
+
+    /// Copies data into the buffer, offset is given in number of elements
+    pub fn write_slice<T: bytemuck::Pod>(&self, offset: usize, data: &[T]) -> Result<()> {
+        let byte_offset = self.check_range::<T>(offset, data.len())?;
+        let ptr = self.mapped_ptr::<u8>()?;
+        let bytes = bytemuck::cast_slice::<T, u8>(data);
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                ptr.add(byte_offset),
+                bytes.len(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Reads count elements from the buffer, offset is given in number of elements
+    pub fn read_slice<T: bytemuck::Pod>(&self, offset: usize, count: usize) -> Result<Vec<T>> {
+        let byte_offset = self.check_range::<T>(offset, count)?;
+        let ptr = self.mapped_ptr::<u8>()?;
+
+        let mut data = vec![T::zeroed(); count];
+        let bytes = bytemuck::cast_slice_mut::<T, u8>(&mut data);
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                ptr.add(byte_offset),
+                bytes.as_mut_ptr(),
+                bytes.len(),
+            );
+        }
+        Ok(data)
+    }
+
+    /// Makes host writes to mapped memory visible to the device, no-op if the
+    /// memory is coherent. Size can be WHOLE_SIZE.
+    pub fn flush(&self, offset: u64, size: u64) -> Result<()> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let range = self.mapped_memory_range(offset, size)?;
+        unsafe {
+            self.device.raw.flush_mapped_memory_ranges(&[range])?;
+        }
+        Ok(())
+    }
+
+    /// Makes device writes to mapped memory visible to the host, no-op if the
+    /// memory is coherent. Size can be WHOLE_SIZE.
+    pub fn invalidate(&self, offset: u64, size: u64) -> Result<()> {
+        if self.coherent {
+            return Ok(());
+        }
+
+        let range = self.mapped_memory_range(offset, size)?;
+        unsafe {
+            self.device.raw.invalidate_mapped_memory_ranges(&[range])?;
+        }
+        Ok(())
+    }
+
+    /// Range within the device memory, aligned to nonCoherentAtomSize
+    fn mapped_memory_range(&self, offset: u64, size: u64) -> Result<vk::MappedMemoryRange> {
+        let size = if size == vk::WHOLE_SIZE {
+            self.size.saturating_sub(offset)
+        } else {
+            size
+        };
+
+        let in_bounds = offset.checked_add(size).is_some_and(|end| end <= self.size);
+        if !in_bounds {
+            anyhow::bail!(
+                "Range of {} bytes at {} out of bounds for buffer of size {}",
+                size,
+                offset,
+                self.size,
+            );
+        }
+
+        let allocation = self.allocation.as_ref().unwrap();
+        if allocation.mapped_ptr().is_none() {
+            anyhow::bail!("Buffer is not mappable, requires MAP_READ or MAP_WRITE usage");
+        }
+
+        let atom_size = self.device.physical_device.properties.limits.non_coherent_atom_size;
+        let begin = allocation.offset() + offset;
+        let end = begin + size;
+        // nonCoherentAtomSize is guaranteed to be a power of two
+        let begin = begin & !(atom_size - 1);
+        let end = ((end + atom_size - 1) & !(atom_size - 1))
+            .min(allocation.offset() + allocation.size());
+
         Ok(
-            self.allocation
-                .as_ref()
-                .unwrap()
-                .mapped_ptr()
-                .unwrap()
-                .as_ptr() as *mut _
+            vk::MappedMemoryRange::builder()
+                .memory(unsafe { allocation.memory() })
+                .offset(begin)
+                .size(end - begin)
+                .build()
         )
     }
+
+    /// Returns byte offset of range if within bounds of buffer
+    fn check_range<T>(&self, offset: usize, count: usize) -> Result<usize> {
+        let element_size = std::mem::size_of::<T>();
+        let byte_offset = offset.checked_mul(element_size);
+        let byte_end = count
+            .checked_mul(element_size)
+            .zip(byte_offset)
+            .and_then(|(size, byte_offset)| byte_offset.checked_add(size));
+
+        match (byte_offset, byte_end) {
+            (Some(byte_offset), Some(byte_end)) if byte_end as u64 <= self.size => Ok(byte_offset),
+            _ => anyhow::bail!(
+                "Range of {} elements at {} out of bounds for buffer of size {}",
+                count,
+                offset,
+                self.size,
+            ),
+        }
+    }
+}
+
+/// Destroys buffer right away if this is the last reference, for transient
+/// buffers known to be unused by the GPU. Otherwise it is destroyed as usual
+/// once the last reference is dropped.
+pub(super) fn destroy_idle(buffer: crate::Buffer) {
+    let mut buffer = match Arc::try_unwrap(buffer) {
+        Ok(buffer) => buffer,
+        Err(_) => return,
+    };
+
+    if let Some(allocation) = buffer.allocation.take() {
+        buffer.device.free(allocation)
+            .expect("Failed to free buffer memory");
+    }
+    unsafe {
+        buffer.device.raw.destroy_buffer(buffer.raw, None);
+    }
 }
 
 impl Drop for Buffer {
     fn drop(&mut self) {
-        self.device.allocator
-            .as_ref()
-            .unwrap()
-            .lock()
-            .unwrap()
-            .free(self.allocation.take().unwrap())
-            .expect("Failed to free buffer memory");
+        // Already destroyed by destroy_idle
+        let allocation = match self.allocation.take() {
+            Some(allocation) => allocation,
+            None => return,
+        };
+        let raw = self.raw;
 
-        unsafe {
-            self.device.raw.destroy_buffer(self.raw, None);
-        }
+        self.device.defer_destroy(move |device| {
+            device.free(allocation)
+                .expect("Failed to free buffer memory");
+
+            unsafe {
+                device.raw.destroy_buffer(raw, None);
+            }
+        });
+    }
+}
+
+impl BufferView {
+    pub(super) fn new(
+        device: &Arc<super::DeviceInner>,
+        buffer: &crate::Buffer,
+        desc: crate::BufferViewDesc,
+    ) -> Result<Self> {
+        let create_info = vk::BufferViewCreateInfo::builder()
+            .buffer(buffer.raw)
+            .format(desc.format)
+            .offset(desc.offset)
+            .range(desc.range)
+            .build();
+
+        let raw = unsafe {
+            device.raw.create_buffer_view(&create_info, None)?
+        };
+
+        Ok(Self {
+            raw,
+            _buffer: buffer.clone(),
+            device: device.clone(),
+        })
+    }
+}
+
+impl Drop for BufferView {
+    fn drop(&mut self) {
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_buffer_view(raw, None);
+        });
+    }
+}
+
+impl super::DebugName for Buffer {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::BUFFER, self.raw.as_raw())
+    }
+}
+
+impl super::DebugName for BufferView {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::BUFFER_VIEW, self.raw.as_raw())
     }
 }
 
@@ -104,18 +353,65 @@ impl From<&crate::BufferUsage> for vk::BufferUsageFlags {
 
         if usage.contains(crate::BufferUsage::TRANSFER_SRC) {
             flags |= vk::BufferUsageFlags::TRANSFER_SRC;
-        } else if usage.contains(crate::BufferUsage::TRANSFER_DST) {
+        }
+        if usage.contains(crate::BufferUsage::TRANSFER_DST) {
             flags |= vk::BufferUsageFlags::TRANSFER_DST;
-        } else if usage.contains(crate::BufferUsage::UNIFORM) {
+        }
+        if usage.contains(crate::BufferUsage::UNIFORM) {
             flags |= vk::BufferUsageFlags::UNIFORM_BUFFER;
-        } else if usage.contains(crate::BufferUsage::STORAGE) {
+        }
+        if usage.contains(crate::BufferUsage::STORAGE) {
             flags |= vk::BufferUsageFlags::STORAGE_BUFFER;
-        } else if usage.contains(crate::BufferUsage::INDEX) {
+        }
+        if usage.contains(crate::BufferUsage::INDEX) {
             flags |= vk::BufferUsageFlags::INDEX_BUFFER;
-        } else if usage.contains(crate::BufferUsage::VERTEX) {
+        }
+        if usage.contains(crate::BufferUsage::VERTEX) {
             flags |= vk::BufferUsageFlags::VERTEX_BUFFER;
         }
+        if usage.contains(crate::BufferUsage::INDIRECT) {
+            flags |= vk::BufferUsageFlags::INDIRECT_BUFFER;
+        }
+        if usage.contains(crate::BufferUsage::UNIFORM_TEXEL) {
+            flags |= vk::BufferUsageFlags::UNIFORM_TEXEL_BUFFER;
+        }
+        if usage.contains(crate::BufferUsage::STORAGE_TEXEL) {
+            flags |= vk::BufferUsageFlags::STORAGE_TEXEL_BUFFER;
+        }
+        if usage.contains(crate::BufferUsage::SHADER_DEVICE_ADDRESS) {
+            flags |= vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+        }
 
         flags
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ash::vk;
+
+    #[test]
+    fn combined_usage_flags() {
+        let usage = crate::BufferUsage::STORAGE
+            | crate::BufferUsage::UNIFORM_TEXEL
+            | crate::BufferUsage::STORAGE_TEXEL
+            | crate::BufferUsage::TRANSFER_DST;
+        assert_eq!(
+            vk::BufferUsageFlags::from(&usage),
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::UNIFORM_TEXEL_BUFFER
+                | vk::BufferUsageFlags::STORAGE_TEXEL_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+        );
+
+        let usage = crate::BufferUsage::VERTEX
+            | crate::BufferUsage::INDEX
+            | crate::BufferUsage::INDIRECT;
+        assert_eq!(
+            vk::BufferUsageFlags::from(&usage),
+            vk::BufferUsageFlags::VERTEX_BUFFER
+                | vk::BufferUsageFlags::INDEX_BUFFER
+                | vk::BufferUsageFlags::INDIRECT_BUFFER,
+        );
+    }
+}
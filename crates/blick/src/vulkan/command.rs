@@ -1,10 +1,25 @@
 use ash::vk;
-use std::ffi::CString;
+use ash::vk::Handle;
 use std::sync::Arc;
 
+/// How the encoder's pass was begun, decides how it ends
+enum PassKind {
+    RenderPass(crate::RenderPass),
+    /// Secondary command buffer continuing a pass begun in a primary one,
+    /// the pass itself is unknown
+    Continued,
+    /// Dynamic rendering, see CommandBuffer::begin_rendering
+    Rendering {
+        color_attachment_count: u32,
+    },
+}
+
 pub struct RenderPassEncoder<'a> {
     parent: &'a mut CommandBuffer,
     active_pipeline: Option<&'a crate::GraphicsPipeline>,
+    index_buffer_bound: bool,
+    kind: PassKind,
+    subpass: u32,
 }
 
 pub struct ComputePassEncoder<'a> {
@@ -12,9 +27,20 @@ pub struct ComputePassEncoder<'a> {
     active_pipeline: Option<&'a crate::ComputePipeline>,
 }
 
+/// Ends the debug label when dropped, records to the command buffer through
+/// Deref in the meantime
+pub struct DebugScopeGuard<'a> {
+    parent: &'a mut CommandBuffer,
+}
+
 pub struct CommandBuffer {
     pub(super) raw: vk::CommandBuffer,
+    level: vk::CommandBufferLevel,
     command_pool: vk::CommandPool,
+    /// Framebuffers used by the recorded commands. The framebuffer cache may
+    /// evict them at any time, so they are kept alive until the command
+    /// buffer is recorded again or dropped.
+    framebuffers: Vec<crate::Framebuffer>,
     device: Arc<super::DeviceInner>,
 }
 
@@ -24,22 +50,38 @@ impl<'a> RenderPassEncoder<'a> {
         pass: &crate::RenderPass,
         framebuffer: &crate::Framebuffer,
         render_area: &crate::Rect<u32>
+    ) -> Self {
+        Self::begin_with_contents(
+            parent,
+            pass,
+            framebuffer,
+            render_area,
+            vk::SubpassContents::INLINE,
+        )
+    }
+
+    fn begin_with_contents(
+        parent: &'a mut CommandBuffer,
+        pass: &crate::RenderPass,
+        framebuffer: &crate::Framebuffer,
+        render_area: &crate::Rect<u32>,
+        contents: vk::SubpassContents,
     ) -> Self {
         let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
             .render_pass(pass.raw())
             .framebuffer(framebuffer.raw())
-            .render_area(vk::Rect2D {
-                offset: vk::Offset2D { x: 0, y: 0 },
-                extent: vk::Extent2D {
-                    width: render_area.width,
-                    height: render_area.height,
-                },
-            })
+            .render_area(render_area.into())
             .clear_values(
                 // TODO:
                 &(0..pass.num_attachments())
-                    .map(|_| vk::ClearValue {
-                        color: vk::ClearColorValue { float32: [0.0, 0.0, 0.0, 1.0] },
+                    .map(|index| if Some(index) == pass.depth_stencil_attachment() {
+                        vk::ClearValue {
+                            depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
+                        }
+                    } else {
+                        vk::ClearValue {
+                            color: vk::ClearColorValue { float32: crate::ClearColor::BLACK.into() },
+                        }
                     })
                     .collect::<Vec<_>>()
             )
@@ -49,31 +91,411 @@ impl<'a> RenderPassEncoder<'a> {
             parent.device.raw.cmd_begin_render_pass(
                 parent.raw,
                 &render_pass_begin_info,
-                vk::SubpassContents::INLINE
+                contents
             );
         }
+        parent.framebuffers.push(framebuffer.clone());
+
+        Self {
+            parent,
+            active_pipeline: None,
+            index_buffer_bound: false,
+            kind: PassKind::RenderPass(pass.clone()),
+            subpass: 0,
+        }
+    }
 
+    /// Records into a secondary command buffer that continues a render pass
+    fn continue_pass(parent: &'a mut CommandBuffer) -> Self {
         Self {
             parent,
             active_pipeline: None,
+            index_buffer_bound: false,
+            kind: PassKind::Continued,
+            subpass: 0,
+        }
+    }
+
+    fn begin_rendering(
+        parent: &'a mut CommandBuffer,
+        info: &crate::RenderingInfo,
+    ) -> anyhow::Result<Self> {
+        if parent.device.dynamic_rendering.is_none() {
+            anyhow::bail!("VK_KHR_dynamic_rendering not supported");
+        }
+
+        let color_attachments = info.color_attachments
+            .iter()
+            .map(|attachment| {
+                vk::RenderingAttachmentInfo::builder()
+                    .image_view(attachment.image_view.raw)
+                    .image_layout(attachment.layout)
+                    .load_op(attachment.load_op)
+                    .store_op(attachment.store_op)
+                    .clear_value(vk::ClearValue {
+                        color: vk::ClearColorValue { float32: attachment.clear_color.into() },
+                    })
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let depth_attachment = info.depth_attachment
+            .as_ref()
+            .map(|attachment| {
+                vk::RenderingAttachmentInfo::builder()
+                    .image_view(attachment.image_view.raw)
+                    .image_layout(attachment.layout)
+                    .load_op(attachment.load_op)
+                    .store_op(attachment.store_op)
+                    .clear_value(vk::ClearValue {
+                        depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
+                    })
+                    .build()
+            });
+        let aspect_mask = info.depth_attachment
+            .as_ref()
+            .map_or(vk::ImageAspectFlags::empty(), |attachment| {
+                super::image::aspect_mask(attachment.image_view.format)
+            });
+
+        let mut rendering_info = vk::RenderingInfo::builder()
+            .render_area((&info.render_area).into())
+            .layer_count(1)
+            .color_attachments(&color_attachments);
+        if let Some(depth_attachment) = &depth_attachment {
+            if aspect_mask.contains(vk::ImageAspectFlags::DEPTH) {
+                rendering_info = rendering_info.depth_attachment(depth_attachment);
+            }
+            if aspect_mask.contains(vk::ImageAspectFlags::STENCIL) {
+                rendering_info = rendering_info.stencil_attachment(depth_attachment);
+            }
+        }
+        let rendering_info = rendering_info.build();
+
+        unsafe {
+            parent.device.dynamic_rendering
+                .as_ref()
+                .unwrap()
+                .cmd_begin_rendering(parent.raw, &rendering_info);
+        }
+
+        Ok(Self {
+            parent,
+            active_pipeline: None,
+            index_buffer_bound: false,
+            kind: PassKind::Rendering {
+                color_attachment_count: color_attachments.len() as u32,
+            },
+            subpass: 0,
+        })
+    }
+
+    /// Advances to the next subpass, contents are recorded inline
+    pub fn next_subpass(mut self) -> Self {
+        match &self.kind {
+            PassKind::RenderPass(pass) => debug_assert!(
+                self.subpass + 1 < pass.subpass_count(),
+                "next_subpass: render pass only has {} subpasses",
+                pass.subpass_count(),
+            ),
+            PassKind::Continued => {}
+            PassKind::Rendering { .. } => debug_assert!(
+                false,
+                "next_subpass: dynamic rendering has no subpasses"
+            ),
+        }
+
+        unsafe {
+            self.parent.device.raw.cmd_next_subpass(
+                self.parent.raw,
+                vk::SubpassContents::INLINE
+            );
         }
+        self.subpass += 1;
+        self.active_pipeline = None;
+        self
+    }
+
+    /// Memory barrier between commands of the current subpass, e.g. between a
+    /// draw writing a storage buffer and a draw reading it. Barriers can't be
+    /// recorded with CommandBuffer::transition while the encoder is alive, and
+    /// within a render pass they require a subpass self-dependency
+    /// (src_subpass == dst_subpass) covering the stages and access masks. The
+    /// dependency flags are taken from that self-dependency, BY_REGION for
+    /// passes continued in secondary command buffers.
+    pub fn barrier(
+        self,
+        src_stage_mask: crate::PipelineStageFlags,
+        dst_stage_mask: crate::PipelineStageFlags,
+        src_access_mask: crate::AccessFlags,
+        dst_access_mask: crate::AccessFlags,
+    ) -> Self {
+        let dependency_flags = match &self.kind {
+            PassKind::RenderPass(pass) => {
+                let dependency = pass.self_dependencies(self.subpass).find(|dependency| {
+                    dependency.src_stage_mask.contains(src_stage_mask)
+                        && dependency.dst_stage_mask.contains(dst_stage_mask)
+                        && dependency.src_access_mask.contains(src_access_mask)
+                        && dependency.dst_access_mask.contains(dst_access_mask)
+                });
+                debug_assert!(
+                    dependency.is_some(),
+                    "barrier: no self-dependency of subpass {} covers the barrier",
+                    self.subpass,
+                );
+                dependency.map_or(vk::DependencyFlags::BY_REGION, |dependency| {
+                    dependency.dependency_flags
+                })
+            },
+            PassKind::Continued => vk::DependencyFlags::BY_REGION,
+            PassKind::Rendering { .. } => {
+                debug_assert!(false, "barrier: not supported within dynamic rendering");
+                vk::DependencyFlags::BY_REGION
+            },
+        };
+
+        let memory_barrier = vk::MemoryBarrier::builder()
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .build();
+        unsafe {
+            self.parent.device.raw.cmd_pipeline_barrier(
+                self.parent.raw,
+                src_stage_mask,
+                dst_stage_mask,
+                dependency_flags,
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+        }
+        self
+    }
+
+    /// Only valid for passes begun with begin_render_pass_secondary
+    pub fn execute_commands(self, command_buffers: &[&CommandBuffer]) -> Self {
+        self.parent.execute_commands(command_buffers);
+        self
     }
 
     pub fn bind_pipeline(
         mut self,
         pipeline: &'a crate::GraphicsPipeline
     ) -> Self {
+        if let PassKind::RenderPass(pass) = &self.kind {
+            debug_assert!(
+                !pipeline.dynamic_rendering,
+                "bind_pipeline: pipeline created for dynamic rendering used in a render pass"
+            );
+            debug_assert!(
+                pipeline.subpass == self.subpass,
+                "bind_pipeline: pipeline created for subpass {}, current subpass is {}",
+                pipeline.subpass,
+                self.subpass,
+            );
+            debug_assert!(
+                pipeline.samples == pass.samples(),
+                "bind_pipeline: pipeline has {:?} samples, render pass has {:?}",
+                pipeline.samples,
+                pass.samples(),
+            );
+            debug_assert!(
+                Some(pipeline.color_attachment_count) == pass.color_attachment_count(self.subpass),
+                "bind_pipeline: pipeline has {} color attachments, subpass has {:?}",
+                pipeline.color_attachment_count,
+                pass.color_attachment_count(self.subpass),
+            );
+        }
+        if let PassKind::Rendering { color_attachment_count } = self.kind {
+            debug_assert!(
+                pipeline.dynamic_rendering,
+                "bind_pipeline: pipeline created for a render pass used in dynamic rendering"
+            );
+            debug_assert!(
+                pipeline.color_attachment_count == color_attachment_count,
+                "bind_pipeline: pipeline has {} color attachments, rendering has {}",
+                pipeline.color_attachment_count,
+                color_attachment_count,
+            );
+        }
+
         unsafe {
             self.parent.device.raw.cmd_bind_pipeline(
                 self.parent.raw,
                 vk::PipelineBindPoint::GRAPHICS,
                 pipeline.raw
             );
+
+            // Dynamic state is not part of the pipeline, restore its defaults
+            if let Some(eds) = self.parent.device.extended_dynamic_state.as_ref() {
+                eds.cmd_set_cull_mode(self.parent.raw, pipeline.cull_mode);
+                eds.cmd_set_front_face(self.parent.raw, pipeline.front_face);
+                eds.cmd_set_depth_test_enable(self.parent.raw, pipeline.depth_test_enable);
+                eds.cmd_set_depth_write_enable(self.parent.raw, pipeline.depth_write_enable);
+                eds.cmd_set_depth_compare_op(self.parent.raw, pipeline.depth_compare_op);
+            }
+            if let Some(bias) = pipeline.depth_bias {
+                self.parent.device.raw.cmd_set_depth_bias(
+                    self.parent.raw,
+                    bias.constant_factor,
+                    bias.clamp,
+                    bias.slope_factor,
+                );
+            }
+            let device = &self.parent.device.raw;
+            if pipeline.dynamic_states.contains(crate::DynamicStates::BLEND_CONSTANTS) {
+                device.cmd_set_blend_constants(self.parent.raw, &[0.0; 4]);
+            }
+            if pipeline.dynamic_states.contains(crate::DynamicStates::LINE_WIDTH) {
+                device.cmd_set_line_width(self.parent.raw, 1.0);
+            }
+            if pipeline.dynamic_states.contains(crate::DynamicStates::STENCIL_REFERENCE) {
+                let (front, back) = pipeline.stencil_reference;
+                device.cmd_set_stencil_reference(
+                    self.parent.raw,
+                    vk::StencilFaceFlags::FRONT,
+                    front,
+                );
+                device.cmd_set_stencil_reference(
+                    self.parent.raw,
+                    vk::StencilFaceFlags::BACK,
+                    back,
+                );
+            }
         }
         self.active_pipeline = Some(pipeline);
         self
     }
 
+    /// Overrides the cull mode of the bound pipeline until next bind_pipeline
+    /// Requires VK_EXT_extended_dynamic_state
+    pub fn set_cull_mode(self, cull_mode: crate::CullModeFlags) -> Self {
+        unsafe {
+            self.parent.device.extended_dynamic_state
+                .as_ref()
+                .expect("VK_EXT_extended_dynamic_state not supported")
+                .cmd_set_cull_mode(self.parent.raw, cull_mode);
+        }
+        self
+    }
+
+    /// Overrides the front face of the bound pipeline until next bind_pipeline
+    /// Requires VK_EXT_extended_dynamic_state
+    pub fn set_front_face(self, front_face: crate::FrontFace) -> Self {
+        unsafe {
+            self.parent.device.extended_dynamic_state
+                .as_ref()
+                .expect("VK_EXT_extended_dynamic_state not supported")
+                .cmd_set_front_face(self.parent.raw, front_face);
+        }
+        self
+    }
+
+    /// Overrides depth testing of the bound pipeline until next bind_pipeline
+    /// Requires VK_EXT_extended_dynamic_state
+    pub fn set_depth_test_enable(self, enable: bool) -> Self {
+        unsafe {
+            self.parent.device.extended_dynamic_state
+                .as_ref()
+                .expect("VK_EXT_extended_dynamic_state not supported")
+                .cmd_set_depth_test_enable(self.parent.raw, enable);
+        }
+        self
+    }
+
+    /// Overrides depth writes of the bound pipeline until next bind_pipeline
+    /// Requires VK_EXT_extended_dynamic_state
+    pub fn set_depth_write_enable(self, enable: bool) -> Self {
+        unsafe {
+            self.parent.device.extended_dynamic_state
+                .as_ref()
+                .expect("VK_EXT_extended_dynamic_state not supported")
+                .cmd_set_depth_write_enable(self.parent.raw, enable);
+        }
+        self
+    }
+
+    /// Overrides the depth compare op of the bound pipeline until next bind_pipeline
+    /// Requires VK_EXT_extended_dynamic_state
+    pub fn set_depth_compare_op(self, compare_op: crate::CompareOp) -> Self {
+        unsafe {
+            self.parent.device.extended_dynamic_state
+                .as_ref()
+                .expect("VK_EXT_extended_dynamic_state not supported")
+                .cmd_set_depth_compare_op(self.parent.raw, compare_op);
+        }
+        self
+    }
+
+    /// Overrides the depth bias of the bound pipeline until next bind_pipeline
+    /// The pipeline has to be created with depth bias enabled
+    pub fn set_depth_bias(self, bias: &crate::DepthBias) -> Self {
+        debug_assert!(
+            self.active_pipeline.is_some_and(|pipeline| pipeline.depth_bias.is_some()),
+            "set_depth_bias: bound pipeline was created without depth bias"
+        );
+        let bias = self.parent.device.supported_depth_bias(*bias);
+        unsafe {
+            self.parent.device.raw.cmd_set_depth_bias(
+                self.parent.raw,
+                bias.constant_factor,
+                bias.clamp,
+                bias.slope_factor,
+            );
+        }
+        self
+    }
+
+    /// Sets the constant used by CONSTANT_* blend factors until next
+    /// bind_pipeline. The pipeline has to be created with BLEND_CONSTANTS
+    pub fn set_blend_constants(self, constants: [f32; 4]) -> Self {
+        self.debug_assert_dynamic(crate::DynamicStates::BLEND_CONSTANTS, "set_blend_constants");
+        unsafe {
+            self.parent.device.raw.cmd_set_blend_constants(self.parent.raw, &constants);
+        }
+        self
+    }
+
+    /// Sets the line width until next bind_pipeline, widths other than 1.0
+    /// require the wideLines feature. The pipeline has to be created with LINE_WIDTH
+    pub fn set_line_width(self, width: f32) -> Self {
+        self.debug_assert_dynamic(crate::DynamicStates::LINE_WIDTH, "set_line_width");
+        debug_assert!(
+            width == 1.0 || self.parent.device.wide_lines,
+            "set_line_width: wideLines not supported"
+        );
+        unsafe {
+            self.parent.device.raw.cmd_set_line_width(self.parent.raw, width);
+        }
+        self
+    }
+
+    /// Overrides the stencil reference of the given faces until next
+    /// bind_pipeline. The pipeline has to be created with STENCIL_REFERENCE
+    pub fn set_stencil_reference(
+        self,
+        faces: crate::StencilFaceFlags,
+        reference: u32,
+    ) -> Self {
+        self.debug_assert_dynamic(crate::DynamicStates::STENCIL_REFERENCE, "set_stencil_reference");
+        unsafe {
+            self.parent.device.raw.cmd_set_stencil_reference(
+                self.parent.raw,
+                faces,
+                reference,
+            );
+        }
+        self
+    }
+
+    fn debug_assert_dynamic(&self, state: crate::DynamicStates, name: &str) {
+        debug_assert!(
+            self.active_pipeline.is_some_and(|pipeline| pipeline.dynamic_states.contains(state)),
+            "{}: bound pipeline was created without {:?}",
+            name,
+            state,
+        );
+    }
+
     pub fn set_viewport(
         self,
         rect: &crate::Rect<f32>,
@@ -101,16 +523,7 @@ impl<'a> RenderPassEncoder<'a> {
             self.parent.device.raw.cmd_set_scissor(
                 self.parent.raw,
                 0,
-                &[vk::Rect2D {
-                    offset: vk::Offset2D {
-                        x: rect.x as _,
-                        y: rect.y as _,
-                    },
-                    extent: vk::Extent2D {
-                        width: rect.width,
-                        height: rect.height
-                    },
-                }],
+                &[rect.into()],
             );
         }
         self
@@ -122,27 +535,30 @@ impl<'a> RenderPassEncoder<'a> {
             self.parent.device.raw.cmd_set_viewport(
                 self.parent.raw,
                 0,
-                &[vk::Viewport{
-                    x: rect.x as _,
-                    y: rect.y as _,
-                    width: rect.width as _,
-                    height: rect.height as _,
-                    min_depth: 0.0,
-                    max_depth: 1.0,
-                }],
+                &[rect.into()],
             );
             self.parent.device.raw.cmd_set_scissor(
                 self.parent.raw,
                 0,
-                &[vk::Rect2D {
-                    offset: vk::Offset2D {
-                        x: rect.x as _,
-                        y: rect.y as _,
-                    },
-                    extent: vk::Extent2D {
-                        width: rect.width,
-                        height: rect.height
-                    },
+                &[rect.into()],
+            );
+        }
+        self
+    }
+
+    /// Sets a viewport with negative height covering rect, flipping Y so that
+    /// clip space follows the D3D convention of Y pointing up. Scissor is
+    /// unaffected, see set_scissor.
+    pub fn set_viewport_flipped(self, rect: &crate::Rect<u32>) -> Self {
+        let viewport: vk::Viewport = rect.into();
+        unsafe {
+            self.parent.device.raw.cmd_set_viewport(
+                self.parent.raw,
+                0,
+                &[vk::Viewport {
+                    y: viewport.y + viewport.height,
+                    height: -viewport.height,
+                    ..viewport
                 }],
             );
         }
@@ -153,6 +569,16 @@ impl<'a> RenderPassEncoder<'a> {
         self,
         index: u32,
         set: &crate::DescriptorSet,
+    ) -> Self {
+        self.bind_descriptor_set_with_offsets(index, set, &[])
+    }
+
+    /// One offset per dynamic binding in the set, in binding order
+    pub fn bind_descriptor_set_with_offsets(
+        self,
+        index: u32,
+        set: &crate::DescriptorSet,
+        dynamic_offsets: &[u32],
     ) -> Self {
         unsafe {
             self.parent.device.raw.cmd_bind_descriptor_sets(
@@ -161,31 +587,60 @@ impl<'a> RenderPassEncoder<'a> {
                 self.active_pipeline.unwrap().pipeline_layout,
                 index,
                 &[set.raw],
-                &[],
+                dynamic_offsets,
             );
         }
         self
     }
 
+    /// Panics if the data isn't within the push constant ranges of the pipeline
     pub fn push_constants(self, offset: u32, data: &[u8]) -> Self {
+        let pipeline = self.active_pipeline.unwrap();
+        let stage_flags = push_constant_stages(
+            &pipeline.push_constant_ranges,
+            offset,
+            data.len() as u32,
+        );
         unsafe {
             self.parent.device.raw.cmd_push_constants(
                 self.parent.raw,
-                self.active_pipeline.unwrap().pipeline_layout,
-                vk::ShaderStageFlags::COMPUTE,
-                offset as _,
+                pipeline.pipeline_layout,
+                stage_flags,
+                offset,
                 data,
             );
         }
         self
     }
 
+    /// Same as push_constants with the bytes of value, T should be #[repr(C)]
+    /// to match the shader layout
+    pub fn push_constants_typed<T: bytemuck::Pod>(self, offset: u32, value: &T) -> Self {
+        self.push_constants(offset, bytemuck::bytes_of(value))
+    }
+
     pub fn bind_index_buffer(
-        self,
+        mut self,
         buffer: &crate::Buffer,
         offset: u64,
         index_type: crate::IndexType,
     ) -> Self {
+        debug_assert!(
+            buffer.usage().contains(crate::BufferUsage::INDEX),
+            "bind_index_buffer: buffer not created with INDEX usage"
+        );
+        let index_size: u64 = match index_type {
+            vk::IndexType::UINT16 => 2,
+            vk::IndexType::UINT32 => 4,
+            _ => 1,
+        };
+        // Index sizes are powers of two
+        debug_assert!(
+            offset & (index_size - 1) == 0,
+            "bind_index_buffer: offset {} not aligned to {:?}",
+            offset,
+            index_type,
+        );
         unsafe {
             self.parent.device.raw.cmd_bind_index_buffer(
                 self.parent.raw,
@@ -194,6 +649,31 @@ impl<'a> RenderPassEncoder<'a> {
                 index_type
             );
         }
+        self.index_buffer_bound = true;
+        self
+    }
+
+    /// Occlusion queries count samples drawn until end_query
+    pub fn begin_query(self, pool: &crate::QueryPool, query: u32) -> Self {
+        unsafe {
+            self.parent.device.raw.cmd_begin_query(
+                self.parent.raw,
+                pool.raw,
+                query,
+                vk::QueryControlFlags::empty(),
+            );
+        }
+        self
+    }
+
+    pub fn end_query(self, pool: &crate::QueryPool, query: u32) -> Self {
+        unsafe {
+            self.parent.device.raw.cmd_end_query(
+                self.parent.raw,
+                pool.raw,
+                query,
+            );
+        }
         self
     }
 
@@ -204,6 +684,11 @@ impl<'a> RenderPassEncoder<'a> {
         first_vertex: u32,
         first_instance: u32,
     ) -> Self {
+        debug_assert!(
+            self.active_pipeline.is_some(),
+            "draw: no graphics pipeline bound"
+        );
+
         unsafe {
             self.parent.device.raw.cmd_draw(
                 self.parent.raw,
@@ -224,6 +709,15 @@ impl<'a> RenderPassEncoder<'a> {
         vertex_offset: i32,
         first_instance: u32,
     ) -> Self {
+        debug_assert!(
+            self.active_pipeline.is_some(),
+            "draw_indexed: no graphics pipeline bound"
+        );
+        debug_assert!(
+            self.index_buffer_bound,
+            "draw_indexed: no index buffer bound"
+        );
+
         unsafe {
             self.parent.device.raw.cmd_draw_indexed(
                 self.parent.raw,
@@ -236,12 +730,142 @@ impl<'a> RenderPassEncoder<'a> {
         }
         self
     }
+
+    /// Draws with parameters read from VkDrawIndirectCommand structs in
+    /// buffer, the number of draws is read from count_buffer and clamped to
+    /// max_draw_count. Requires VK_KHR_draw_indirect_count, see
+    /// Device::supports_draw_indirect_count
+    pub fn draw_indirect_count(
+        self,
+        buffer: &crate::Buffer,
+        offset: u64,
+        count_buffer: &crate::Buffer,
+        count_offset: u64,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Self {
+        debug_assert!(
+            self.active_pipeline.is_some(),
+            "draw_indirect_count: no graphics pipeline bound"
+        );
+        self.debug_assert_indirect_buffers(
+            "draw_indirect_count",
+            buffer,
+            offset,
+            count_buffer,
+            count_offset,
+            stride,
+            std::mem::size_of::<vk::DrawIndirectCommand>(),
+        );
+
+        unsafe {
+            self.parent.device.draw_indirect_count
+                .as_ref()
+                .expect("VK_KHR_draw_indirect_count not supported")
+                .cmd_draw_indirect_count(
+                    self.parent.raw,
+                    buffer.raw,
+                    offset,
+                    count_buffer.raw,
+                    count_offset,
+                    max_draw_count,
+                    stride,
+                );
+        }
+        self
+    }
+
+    /// Indexed variant of draw_indirect_count, reads
+    /// VkDrawIndexedIndirectCommand structs
+    pub fn draw_indexed_indirect_count(
+        self,
+        buffer: &crate::Buffer,
+        offset: u64,
+        count_buffer: &crate::Buffer,
+        count_offset: u64,
+        max_draw_count: u32,
+        stride: u32,
+    ) -> Self {
+        debug_assert!(
+            self.active_pipeline.is_some(),
+            "draw_indexed_indirect_count: no graphics pipeline bound"
+        );
+        debug_assert!(
+            self.index_buffer_bound,
+            "draw_indexed_indirect_count: no index buffer bound"
+        );
+        self.debug_assert_indirect_buffers(
+            "draw_indexed_indirect_count",
+            buffer,
+            offset,
+            count_buffer,
+            count_offset,
+            stride,
+            std::mem::size_of::<vk::DrawIndexedIndirectCommand>(),
+        );
+
+        unsafe {
+            self.parent.device.draw_indirect_count
+                .as_ref()
+                .expect("VK_KHR_draw_indirect_count not supported")
+                .cmd_draw_indexed_indirect_count(
+                    self.parent.raw,
+                    buffer.raw,
+                    offset,
+                    count_buffer.raw,
+                    count_offset,
+                    max_draw_count,
+                    stride,
+                );
+        }
+        self
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn debug_assert_indirect_buffers(
+        &self,
+        name: &str,
+        buffer: &crate::Buffer,
+        offset: u64,
+        count_buffer: &crate::Buffer,
+        count_offset: u64,
+        stride: u32,
+        command_size: usize,
+    ) {
+        debug_assert!(
+            buffer.usage().contains(crate::BufferUsage::INDIRECT)
+                && count_buffer.usage().contains(crate::BufferUsage::INDIRECT),
+            "{}: buffers not created with INDIRECT usage",
+            name,
+        );
+        debug_assert!(
+            offset & 3 == 0 && count_offset & 3 == 0,
+            "{}: offsets have to be multiples of 4",
+            name,
+        );
+        debug_assert!(
+            stride & 3 == 0 && stride as usize >= command_size,
+            "{}: stride {} has to be a multiple of 4 and at least {}",
+            name,
+            stride,
+            command_size,
+        );
+    }
 }
 
 impl<'a> Drop for RenderPassEncoder<'a> {
     fn drop(&mut self) {
-        unsafe {
-            self.parent.device.raw.cmd_end_render_pass(self.parent.raw);
+        match self.kind {
+            PassKind::RenderPass(_) => unsafe {
+                self.parent.device.raw.cmd_end_render_pass(self.parent.raw);
+            },
+            PassKind::Continued => {}
+            PassKind::Rendering { .. } => unsafe {
+                self.parent.device.dynamic_rendering
+                    .as_ref()
+                    .unwrap()
+                    .cmd_end_rendering(self.parent.raw);
+            },
         }
     }
 }
@@ -274,6 +898,16 @@ impl<'a> ComputePassEncoder<'a> {
         self,
         index: u32,
         set: &crate::DescriptorSet,
+    ) -> Self {
+        self.bind_descriptor_set_with_offsets(index, set, &[])
+    }
+
+    /// One offset per dynamic binding in the set, in binding order
+    pub fn bind_descriptor_set_with_offsets(
+        self,
+        index: u32,
+        set: &crate::DescriptorSet,
+        dynamic_offsets: &[u32],
     ) -> Self {
         unsafe {
             self.parent.device.raw.cmd_bind_descriptor_sets(
@@ -282,26 +916,39 @@ impl<'a> ComputePassEncoder<'a> {
                 self.active_pipeline.unwrap().pipeline_layout,
                 index,
                 &[set.raw],
-                &[],
+                dynamic_offsets,
             );
         }
 
         self
     }
 
+    /// Panics if the data isn't within the push constant ranges of the pipeline
     pub fn push_constants(self, offset: u32, data: &[u8]) -> Self {
+        let pipeline = self.active_pipeline.unwrap();
+        let stage_flags = push_constant_stages(
+            &pipeline.push_constant_ranges,
+            offset,
+            data.len() as u32,
+        );
         unsafe {
             self.parent.device.raw.cmd_push_constants(
                 self.parent.raw,
-                self.active_pipeline.unwrap().pipeline_layout,
-                vk::ShaderStageFlags::COMPUTE,
-                offset as _,
+                pipeline.pipeline_layout,
+                stage_flags,
+                offset,
                 data,
             );
         }
         self
     }
 
+    /// Same as push_constants with the bytes of value, T should be #[repr(C)]
+    /// to match the shader layout
+    pub fn push_constants_typed<T: bytemuck::Pod>(self, offset: u32, value: &T) -> Self {
+        self.push_constants(offset, bytemuck::bytes_of(value))
+    }
+
     pub fn dispatch(self, x: u32, y: u32, z: u32) -> Self {
         unsafe {
             self.parent.device.raw.cmd_dispatch(
@@ -313,11 +960,21 @@ impl<'a> ComputePassEncoder<'a> {
         }
         self
     }
+
+    /// Dispatches enough groups of local_size (x, y, z) to cover extent,
+    /// rounding up so partial groups at the edges are included
+    pub fn dispatch_for(self, extent: crate::Extent3d, local_size: (u32, u32, u32)) -> Self {
+        self.dispatch(
+            extent.width.div_ceil(local_size.0),
+            extent.height.div_ceil(local_size.1),
+            extent.depth.div_ceil(local_size.2),
+        )
+    }
 }
 
 
 impl CommandBuffer {
-    pub fn new(device: &Arc<super::DeviceInner>) -> Self {
+    pub fn new(device: &Arc<super::DeviceInner>, level: vk::CommandBufferLevel) -> Self {
         // TODO: 1 pool per command buffer for now, change this
         let pool_create_info = vk::CommandPoolCreateInfo::builder()
             .queue_family_index(device.universal_queue.family.index)
@@ -331,7 +988,7 @@ impl CommandBuffer {
 
         let allocate_info = vk::CommandBufferAllocateInfo::builder()
             .command_pool(command_pool)
-            .level(vk::CommandBufferLevel::PRIMARY)
+            .level(level)
             .command_buffer_count(1)
             .build();
 
@@ -342,13 +999,26 @@ impl CommandBuffer {
 
         Self {
             raw: command_buffer,
+            level,
             command_pool,
+            framebuffers: Vec::new(),
             device: device.clone(),
         }
     }
 
+    /// Destroys the command buffer right away, for transient command buffers
+    /// known to have completed
+    pub(super) fn destroy_idle(mut self) {
+        unsafe {
+            self.device.raw.destroy_command_pool(self.command_pool, None);
+        }
+        self.command_pool = vk::CommandPool::null();
+    }
+
     /// Begins recording
     pub fn begin(&mut self) {
+        self.framebuffers.clear();
+
         unsafe {
             self.device.raw.begin_command_buffer(
                 self.raw,
@@ -359,6 +1029,42 @@ impl CommandBuffer {
                 .expect("Failed to begin command buffer");
         }
     }
+    /// Begins recording of a secondary command buffer to be executed within
+    /// the given render pass, record draws through continue_render_pass
+    pub fn begin_secondary(
+        &mut self,
+        pass: &crate::RenderPass,
+        framebuffer: &crate::Framebuffer,
+    ) {
+        debug_assert!(
+            self.level == vk::CommandBufferLevel::SECONDARY,
+            "begin_secondary: not a secondary command buffer"
+        );
+
+        let inheritance_info = vk::CommandBufferInheritanceInfo::builder()
+            .render_pass(pass.raw())
+            .subpass(0)
+            .framebuffer(framebuffer.raw())
+            .build();
+
+        self.framebuffers.clear();
+        self.framebuffers.push(framebuffer.clone());
+
+        unsafe {
+            self.device.raw.begin_command_buffer(
+                self.raw,
+                &vk::CommandBufferBeginInfo::builder()
+                    .flags(
+                        vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT
+                        | vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE
+                    )
+                    .inheritance_info(&inheritance_info)
+                    .build()
+            )
+                .expect("Failed to begin command buffer");
+        }
+    }
+
     pub fn end(&mut self) {
         unsafe {
             self.device.raw.end_command_buffer(self.raw)
@@ -380,6 +1086,57 @@ impl CommandBuffer {
         )
     }
 
+    /// Begins a render pass whose contents are recorded in secondary command
+    /// buffers, see RenderPassEncoder::execute_commands
+    pub fn begin_render_pass_secondary(
+        &mut self,
+        pass: &crate::RenderPass,
+        framebuffer: &crate::Framebuffer,
+        render_area: &crate::Rect<u32>
+    ) -> RenderPassEncoder<'_> {
+        RenderPassEncoder::begin_with_contents(
+            self,
+            pass,
+            framebuffer,
+            render_area,
+            vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
+        )
+    }
+
+    /// Renders directly to the attachments without render pass or framebuffer,
+    /// rendering ends when the encoder is dropped.
+    /// Fails without VK_KHR_dynamic_rendering, see
+    /// Device::supports_dynamic_rendering
+    pub fn begin_rendering(
+        &mut self,
+        info: &crate::RenderingInfo,
+    ) -> anyhow::Result<RenderPassEncoder<'_>> {
+        RenderPassEncoder::begin_rendering(self, info)
+    }
+
+    /// For secondary command buffers begun with begin_secondary
+    pub fn continue_render_pass(&mut self) -> RenderPassEncoder<'_> {
+        RenderPassEncoder::continue_pass(self)
+    }
+
+    pub fn execute_commands(&mut self, command_buffers: &[&CommandBuffer]) {
+        debug_assert!(
+            command_buffers
+                .iter()
+                .all(|cb| cb.level == vk::CommandBufferLevel::SECONDARY),
+            "execute_commands: only secondary command buffers can be executed"
+        );
+
+        let command_buffers = command_buffers
+            .iter()
+            .map(|cb| cb.raw)
+            .collect::<Vec<_>>();
+
+        unsafe {
+            self.device.raw.cmd_execute_commands(self.raw, &command_buffers);
+        }
+    }
+
     pub fn begin_compute_pass(
         &mut self,
     ) -> ComputePassEncoder {
@@ -390,49 +1147,48 @@ impl CommandBuffer {
 
     pub fn transition<'a>(
         &mut self,
+        memory_barriers: &'a [crate::MemoryBarrier],
         buffer_barriers: &'a [crate::BufferBarrier],
         image_barriers: &'a [crate::ImageBarrier],
         // TODO: Nicer way to handle these?
         src_stage_mask: crate::PipelineStageFlags,
         dst_stage_mask: crate::PipelineStageFlags,
     ) {
-        // TODO: Transition between queues
+        let memory_barriers = global_memory_barriers(memory_barriers);
+        let buffer_memory_barriers = buffer_memory_barriers(&self.device, buffer_barriers);
+        let image_memory_barriers = image_memory_barriers(&self.device, image_barriers);
+        for barrier in image_barriers {
+            barrier.image.track_barrier(barrier, dst_stage_mask);
+        }
 
-        let buffer_memory_barriers = buffer_barriers
-            .iter()
-            .map(|barrier| {
-                vk::BufferMemoryBarrier::builder()
-                    .buffer(barrier.buffer.raw)
-                    .src_access_mask(barrier.src_access_mask)
-                    .dst_access_mask(barrier.dst_access_mask)
-                    .offset(0)
-                    .size(vk::WHOLE_SIZE) // TODO: ?
-                    .build()
-            })
-            .collect::<Vec<_>>();
+        unsafe {
+            self.device.raw.cmd_pipeline_barrier(
+                self.raw,
+                src_stage_mask,
+                dst_stage_mask,
+                vk::DependencyFlags::empty(),
+                &memory_barriers,
+                &buffer_memory_barriers,
+                &image_memory_barriers
+            );
+        }
+    }
 
-        let image_memory_barriers = image_barriers
-            .iter()
-            .map(|barrier| {
-                vk::ImageMemoryBarrier::builder()
-                    .image(barrier.image.raw)
-                    .src_access_mask(barrier.src_access_mask)
-                    .dst_access_mask(barrier.dst_access_mask)
-                    .old_layout(barrier.old_layout)
-                    .new_layout(barrier.new_layout)
-                    .subresource_range(vk::ImageSubresourceRange::builder()
-                        .aspect_mask(barrier.aspect_mask)
-                        // TODO: Add remaining subresource range
-                        .base_mip_level(0)
-                        .level_count(vk::REMAINING_MIP_LEVELS)
-                        .base_array_layer(0)
-                        .layer_count(vk::REMAINING_ARRAY_LAYERS)
-                        .build()
-                    )
-                    .build()
-                }
-            )
-            .collect::<Vec<_>>();
+    /// Transitions the whole image to new_layout, taking the old layout and
+    /// source access from its tracked state, see Image::track_layout.
+    /// Panics if tracking isn't enabled for the image.
+    pub fn transition_to(
+        &mut self,
+        image: &crate::Image,
+        new_layout: crate::ImageLayout,
+        dst_access_mask: crate::AccessFlags,
+        dst_stage_mask: crate::PipelineStageFlags,
+    ) {
+        let (image_memory_barriers, src_stage_mask) = image.tracked_transition(
+            new_layout,
+            dst_access_mask,
+            dst_stage_mask,
+        );
 
         unsafe {
             self.device.raw.cmd_pipeline_barrier(
@@ -440,18 +1196,231 @@ impl CommandBuffer {
                 src_stage_mask,
                 dst_stage_mask,
                 vk::DependencyFlags::empty(),
-                &[], // TODO: When to use these?
+                &[],
+                &[],
+                &image_memory_barriers
+            );
+        }
+    }
+
+    /// Signals event once all work in stage_mask prior to this has completed
+    pub fn set_event(
+        &mut self,
+        event: &crate::Event,
+        stage_mask: crate::PipelineStageFlags,
+    ) {
+        unsafe {
+            self.device.raw.cmd_set_event(self.raw, event.raw, stage_mask);
+        }
+    }
+
+    pub fn reset_event(
+        &mut self,
+        event: &crate::Event,
+        stage_mask: crate::PipelineStageFlags,
+    ) {
+        unsafe {
+            self.device.raw.cmd_reset_event(self.raw, event.raw, stage_mask);
+        }
+    }
+
+    /// Like transition but only waits for work prior to the set_event of each
+    /// event, src_stage_mask has to include the stages used in set_event
+    pub fn wait_events<'a>(
+        &mut self,
+        events: &[&crate::Event],
+        src_stage_mask: crate::PipelineStageFlags,
+        dst_stage_mask: crate::PipelineStageFlags,
+        memory_barriers: &'a [crate::MemoryBarrier],
+        buffer_barriers: &'a [crate::BufferBarrier],
+        image_barriers: &'a [crate::ImageBarrier],
+    ) {
+        let events = events
+            .iter()
+            .map(|event| event.raw)
+            .collect::<Vec<_>>();
+
+        let memory_barriers = global_memory_barriers(memory_barriers);
+        let buffer_memory_barriers = buffer_memory_barriers(&self.device, buffer_barriers);
+        let image_memory_barriers = image_memory_barriers(&self.device, image_barriers);
+        for barrier in image_barriers {
+            barrier.image.track_barrier(barrier, dst_stage_mask);
+        }
+
+        unsafe {
+            self.device.raw.cmd_wait_events(
+                self.raw,
+                &events,
+                src_stage_mask,
+                dst_stage_mask,
+                &memory_barriers,
                 &buffer_memory_barriers,
                 &image_memory_barriers
             );
         }
     }
 
-    pub fn copy_buffer(&mut self) { unimplemented!() }
+    /// Queries have to be reset before use, must be recorded outside of passes
+    pub fn reset_query_pool(
+        &mut self,
+        pool: &crate::QueryPool,
+        first_query: u32,
+        query_count: u32,
+    ) {
+        unsafe {
+            self.device.raw.cmd_reset_query_pool(
+                self.raw,
+                pool.raw,
+                first_query,
+                query_count,
+            );
+        }
+    }
+
+    pub fn copy_buffer(
+        &mut self,
+        src: &crate::Buffer,
+        src_offset: u64,
+        dst: &crate::Buffer,
+        dst_offset: u64,
+        size: u64,
+    ) {
+        unsafe {
+            self.device.raw.cmd_copy_buffer(
+                self.raw,
+                src.raw,
+                dst.raw,
+                &[vk::BufferCopy {
+                    src_offset,
+                    dst_offset,
+                    size,
+                }],
+            );
+        }
+    }
+
+    /// Copies tightly packed texels from src to all array layers of a mip
+    /// level of dst, dst has to be in TRANSFER_DST_OPTIMAL or GENERAL
+    pub fn copy_buffer_to_image(
+        &mut self,
+        src: &crate::Buffer,
+        src_offset: u64,
+        dst: &crate::Image,
+        dst_layout: crate::ImageLayout,
+        mip_level: u32,
+        extent: crate::Extent3d,
+    ) {
+        unsafe {
+            self.device.raw.cmd_copy_buffer_to_image(
+                self.raw,
+                src.raw,
+                dst.raw,
+                dst_layout,
+                &[vk::BufferImageCopy {
+                    buffer_offset: src_offset,
+                    // Tightly packed
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: super::image::aspect_mask(dst.desc.format),
+                        mip_level,
+                        base_array_layer: 0,
+                        layer_count: dst.desc.array_layers,
+                    },
+                    image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                    image_extent: extent,
+                }],
+            );
+        }
+    }
+
+    /// Copies all array layers of a mip level of src to tightly packed texels
+    /// in dst, src has to be in TRANSFER_SRC_OPTIMAL or GENERAL
+    pub fn copy_image_to_buffer(
+        &mut self,
+        src: &crate::Image,
+        src_layout: crate::ImageLayout,
+        mip_level: u32,
+        extent: crate::Extent3d,
+        dst: &crate::Buffer,
+        dst_offset: u64,
+    ) {
+        unsafe {
+            self.device.raw.cmd_copy_image_to_buffer(
+                self.raw,
+                src.raw,
+                src_layout,
+                dst.raw,
+                &[vk::BufferImageCopy {
+                    buffer_offset: dst_offset,
+                    // Tightly packed
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: super::image::aspect_mask(src.desc.format),
+                        mip_level,
+                        base_array_layer: 0,
+                        layer_count: src.desc.array_layers,
+                    },
+                    image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                    image_extent: extent,
+                }],
+            );
+        }
+    }
+
+    /// Blits all array layers of a mip level of src to a mip level of dst,
+    /// scaling between the extents of the levels
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_image(
+        &mut self,
+        src: &crate::Image,
+        src_layout: crate::ImageLayout,
+        src_mip_level: u32,
+        dst: &crate::Image,
+        dst_layout: crate::ImageLayout,
+        dst_mip_level: u32,
+        filter: crate::Filter,
+    ) {
+        let offsets = |image: &crate::Image, mip_level: u32| {
+            let extent = super::image::mip_extent(image.desc.extent, mip_level);
+            [
+                vk::Offset3D { x: 0, y: 0, z: 0 },
+                vk::Offset3D {
+                    x: extent.width as i32,
+                    y: extent.height as i32,
+                    z: extent.depth as i32,
+                },
+            ]
+        };
+        let subresource = |image: &crate::Image, mip_level: u32| vk::ImageSubresourceLayers {
+            aspect_mask: super::image::aspect_mask(image.desc.format),
+            mip_level,
+            base_array_layer: 0,
+            layer_count: image.desc.array_layers,
+        };
+
+        unsafe {
+            self.device.raw.cmd_blit_image(
+                self.raw,
+                src.raw,
+                src_layout,
+                dst.raw,
+                dst_layout,
+                &[vk::ImageBlit {
+                    src_subresource: subresource(src, src_mip_level),
+                    src_offsets: offsets(src, src_mip_level),
+                    dst_subresource: subresource(dst, dst_mip_level),
+                    dst_offsets: offsets(dst, dst_mip_level),
+                }],
+                filter,
+            );
+        }
+    }
 
     pub fn begin_debug_label(&self, label: &str) {
         if let Some(debug_utils) = self.device.instance.debug_utils.as_ref() {
-            let label = CString::new(label).unwrap();
+            let label = super::debug_cstring(label);
             let label = vk::DebugUtilsLabelEXT::builder()
                 .label_name(&label)
                 .build();
@@ -467,6 +1436,32 @@ impl CommandBuffer {
         // Else debugging not enabled, do nothing
     }
     
+    /// Label is active until the returned guard is dropped
+    pub fn debug_scope(&mut self, label: &str) -> DebugScopeGuard<'_> {
+        self.begin_debug_label(label);
+        DebugScopeGuard {
+            parent: self,
+        }
+    }
+
+    pub fn insert_debug_label(&self, label: &str) {
+        if let Some(debug_utils) = self.device.instance.debug_utils.as_ref() {
+            let label = super::debug_cstring(label);
+            let label = vk::DebugUtilsLabelEXT::builder()
+                .label_name(&label)
+                .build();
+
+            unsafe {
+                debug_utils
+                    .cmd_insert_debug_utils_label(
+                        self.raw,
+                        &label
+                    );
+            }
+        }
+        // Else debugging not enabled, do nothing
+    }
+
     pub fn end_debug_label(&self) {
         if let Some(debug_utils) = self.device.instance.debug_utils.as_ref() {
             unsafe {
@@ -481,10 +1476,163 @@ impl CommandBuffer {
 
 }
 
+impl<'a> std::ops::Deref for DebugScopeGuard<'a> {
+    type Target = CommandBuffer;
+
+    fn deref(&self) -> &Self::Target {
+        self.parent
+    }
+}
+
+impl<'a> std::ops::DerefMut for DebugScopeGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.parent
+    }
+}
+
+impl<'a> Drop for DebugScopeGuard<'a> {
+    fn drop(&mut self) {
+        self.parent.end_debug_label();
+    }
+}
+
 impl Drop for CommandBuffer {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_command_pool(self.command_pool, None);
+        if self.command_pool == vk::CommandPool::null() {
+            return;
+        }
+
+        // Might still be executing, destroying the pool frees the buffer
+        let command_pool = self.command_pool;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_command_pool(command_pool, None);
+        });
+    }
+}
+
+impl super::DebugName for CommandBuffer {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::COMMAND_BUFFER, self.raw.as_raw())
+    }
+}
+
+fn global_memory_barriers(
+    barriers: &[crate::MemoryBarrier],
+) -> Vec<vk::MemoryBarrier> {
+    barriers
+        .iter()
+        .map(|barrier| {
+            vk::MemoryBarrier::builder()
+                .src_access_mask(barrier.src_access_mask)
+                .dst_access_mask(barrier.dst_access_mask)
+                .build()
+        })
+        .collect()
+}
+
+fn buffer_memory_barriers(
+    device: &super::DeviceInner,
+    barriers: &[crate::BufferBarrier],
+) -> Vec<vk::BufferMemoryBarrier> {
+    barriers
+        .iter()
+        .map(|barrier| {
+            let (src_queue_family_index, dst_queue_family_index) =
+                device.queue_family_indices(barrier.queue_transfer);
+
+            vk::BufferMemoryBarrier::builder()
+                .buffer(barrier.buffer.raw)
+                .src_access_mask(barrier.src_access_mask)
+                .dst_access_mask(barrier.dst_access_mask)
+                .src_queue_family_index(src_queue_family_index)
+                .dst_queue_family_index(dst_queue_family_index)
+                .offset(0)
+                .size(vk::WHOLE_SIZE) // TODO: ?
+                .build()
+        })
+        .collect()
+}
+
+fn image_memory_barriers(
+    device: &super::DeviceInner,
+    barriers: &[crate::ImageBarrier],
+) -> Vec<vk::ImageMemoryBarrier> {
+    barriers
+        .iter()
+        .map(|barrier| {
+            let (src_queue_family_index, dst_queue_family_index) =
+                device.queue_family_indices(barrier.queue_transfer);
+
+            vk::ImageMemoryBarrier::builder()
+                .image(barrier.image.raw)
+                .src_access_mask(barrier.src_access_mask)
+                .dst_access_mask(barrier.dst_access_mask)
+                .old_layout(barrier.old_layout)
+                .new_layout(barrier.new_layout)
+                .src_queue_family_index(src_queue_family_index)
+                .dst_queue_family_index(dst_queue_family_index)
+                .subresource_range((&barrier.subresource_range).into())
+                .build()
+            }
+        )
+        .collect()
+}
+
+/// Stages of all ranges overlapping offset..offset + size, each of them has to
+/// contain the whole update
+fn push_constant_stages(
+    ranges: &[vk::PushConstantRange],
+    offset: u32,
+    size: u32,
+) -> vk::ShaderStageFlags {
+    let end = offset + size;
+    let mut stage_flags = vk::ShaderStageFlags::empty();
+    for range in ranges
+        .iter()
+        .filter(|range| range.offset < end && offset < range.offset + range.size)
+    {
+        assert!(
+            range.offset <= offset && end <= range.offset + range.size,
+            "push_constants: {}..{} is not within the range {}..{} of {:?}",
+            offset,
+            end,
+            range.offset,
+            range.offset + range.size,
+            range.stage_flags,
+        );
+        stage_flags |= range.stage_flags;
+    }
+    assert!(
+        !stage_flags.is_empty(),
+        "push_constants: {}..{} is outside the push constant ranges of the pipeline",
+        offset,
+        end,
+    );
+    stage_flags
+}
+
+impl From<&crate::Rect<u32>> for vk::Rect2D {
+    fn from(rect: &crate::Rect<u32>) -> Self {
+        vk::Rect2D {
+            offset: vk::Offset2D {
+                x: rect.x as i32,
+                y: rect.y as i32,
+            },
+            extent: rect.extent(),
+        }
+    }
+}
+
+/// Viewport covering the rect with the full 0..1 depth range
+impl From<&crate::Rect<u32>> for vk::Viewport {
+    fn from(rect: &crate::Rect<u32>) -> Self {
+        vk::Viewport {
+            x: rect.x as f32,
+            y: rect.y as f32,
+            width: rect.width as f32,
+            height: rect.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
         }
     }
 }
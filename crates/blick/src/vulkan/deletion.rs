@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+
+type Destructor = Box<dyn FnOnce(&super::DeviceInner) + Send>;
+
+/// Defers destruction of resources until the GPU no longer uses them.
+/// Destructors are tagged with the frame they were queued in and are run once
+/// that frame has been released, see Device::end_frame and
+/// Device::release_frame.
+pub(super) struct DeletionQueue {
+    frame: u64,
+    pending: VecDeque<(u64, Destructor)>,
+}
+
+impl DeletionQueue {
+    pub(super) fn new() -> Self {
+        Self {
+            frame: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub(super) fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub(super) fn push(&mut self, destructor: Destructor) {
+        self.pending.push_back((self.frame, destructor));
+    }
+
+    /// Ends the current frame, returns its index
+    pub(super) fn end_frame(&mut self) -> u64 {
+        self.frame += 1;
+        self.frame - 1
+    }
+
+    /// Takes destructors of all frames up to and including frame
+    pub(super) fn take_until(&mut self, frame: u64) -> Vec<Destructor> {
+        let count = self.pending
+            .iter()
+            .take_while(|(f, _)| *f <= frame)
+            .count();
+
+        self.pending
+            .drain(..count)
+            .map(|(_, destructor)| destructor)
+            .collect()
+    }
+
+    pub(super) fn take_all(&mut self) -> Vec<Destructor> {
+        self.pending
+            .drain(..)
+            .map(|(_, destructor)| destructor)
+            .collect()
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use ash::vk;
+use ash::vk::Handle;
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -11,6 +12,10 @@ pub struct DescriptorSetLayout {
     pub(super) raw: vk::DescriptorSetLayout,
     type_count: HashMap<vk::DescriptorType, u32>,
     bindings: SharedBindingDesc,
+    /// Any binding is UPDATE_AFTER_BIND, needs a pool created for it
+    update_after_bind: bool,
+    /// Count of the VARIABLE_DESCRIPTOR_COUNT binding, if any
+    variable_count: Option<u32>,
     device: Arc<super::DeviceInner>,
 }
 
@@ -25,10 +30,20 @@ impl DescriptorSetLayout {
     pub(super) fn new(
         device: &Arc<super::DeviceInner>,
         desc: crate::DescriptorSetLayoutDesc,
-    ) -> Self {
+    ) -> Result<Self> {
         let mut type_count = HashMap::new();
         
         for entry in desc.entries.iter() {
+            if entry.flags.contains(vk::DescriptorBindingFlags::UPDATE_AFTER_BIND)
+                && !device.update_after_bind_types.contains(&entry.ty)
+            {
+                anyhow::bail!(
+                    "Binding {}: UPDATE_AFTER_BIND is not supported for {:?} on this device",
+                    entry.binding,
+                    entry.ty,
+                );
+            }
+
             type_count
                 .entry(entry.ty)
                 .and_modify(|c| *c += entry.count)
@@ -46,14 +61,41 @@ impl DescriptorSetLayout {
             })
             .collect::<Vec<_>>();
 
+        let binding_flags = desc.entries
+            .iter()
+            .map(|binding| binding.flags)
+            .collect::<Vec<_>>();
+
+        let update_after_bind = binding_flags
+            .iter()
+            .any(|flags| flags.contains(vk::DescriptorBindingFlags::UPDATE_AFTER_BIND));
+
+        let variable_count = desc.entries
+            .iter()
+            .find(|binding| {
+                binding.flags.contains(vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT)
+            })
+            .map(|binding| binding.count);
+
+        let mut binding_flags_create_info
+            = vk::DescriptorSetLayoutBindingFlagsCreateInfo::builder()
+                .binding_flags(&binding_flags)
+                .build();
+
+        let flags = if update_after_bind {
+            vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL
+        } else {
+            vk::DescriptorSetLayoutCreateFlags::empty()
+        };
+
         let create_info = vk::DescriptorSetLayoutCreateInfo::builder()
-            .flags(vk::DescriptorSetLayoutCreateFlags::empty()) // TODO:
+            .flags(flags)
             .bindings(&bindings)
+            .push_next(&mut binding_flags_create_info)
             .build();
 
         let raw = unsafe {
-            device.raw.create_descriptor_set_layout(&create_info, None)
-                .expect("Failed to create descriptor set layout")
+            device.raw.create_descriptor_set_layout(&create_info, None)?
         };
 
         let bindings = bindings
@@ -61,20 +103,23 @@ impl DescriptorSetLayout {
             .map(|b| (b.binding, b))
             .collect::<HashMap<u32, vk::DescriptorSetLayoutBinding>>();
 
-        Self {
+        Ok(Self {
             raw,
             type_count,
             bindings: Arc::new(bindings),
+            update_after_bind,
+            variable_count,
             device: device.clone(),
-        }
+        })
     }
 }
 
 impl Drop for DescriptorSetLayout {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_descriptor_set_layout(self.raw, None);
-        }
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_descriptor_set_layout(raw, None);
+        });
     }
 }
 
@@ -93,11 +138,17 @@ impl DescriptorSet {
             })
             .collect::<Vec<_>>();
 
+        let flags = if layout.update_after_bind {
+            vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND
+        } else {
+            vk::DescriptorPoolCreateFlags::empty()
+        };
+
         // TODO: Not using 1 pool per set, this is just to get started
         let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::builder()
             .pool_sizes(&pool_sizes)
             .max_sets(1)
-            .flags(vk::DescriptorPoolCreateFlags::empty())
+            .flags(flags)
             .build();
 
         let pool = unsafe {
@@ -108,13 +159,23 @@ impl DescriptorSet {
                 .expect("Failed to create descriptor pool")
         };
 
+        let variable_counts = layout.variable_count
+            .map(|count| vec![count])
+            .unwrap_or_default();
+        let mut variable_count_allocate_info
+            = vk::DescriptorSetVariableDescriptorCountAllocateInfo::builder()
+                .descriptor_counts(&variable_counts)
+                .build();
+
+        let mut allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(pool)
+            .set_layouts(std::slice::from_ref(&layout.raw));
+        if layout.variable_count.is_some() {
+            allocate_info = allocate_info.push_next(&mut variable_count_allocate_info);
+        }
+
         let raw = unsafe {
-            device.raw.allocate_descriptor_sets(
-                &vk::DescriptorSetAllocateInfo::builder()
-                    .descriptor_pool(pool)
-                    .set_layouts(&[layout.raw])
-                    .build()
-            )
+            device.raw.allocate_descriptor_sets(&allocate_info.build())
                 .expect("Failed to allocate descriptor set")
         }[0];
         
@@ -132,38 +193,141 @@ impl DescriptorSet {
     ) -> Result<()> {
         let mut writes = Vec::with_capacity(entries.len());
 
-        let mut buffer_writes = Vec::new();
+        // Writes point into these, reserve up front so they never reallocate
+        let resource_count = entries
+            .iter()
+            .map(|entry| entry.resources.len())
+            .sum();
+        let mut buffer_writes = Vec::with_capacity(resource_count);
+        let mut texel_buffer_writes = Vec::with_capacity(resource_count);
+        let mut image_writes = Vec::with_capacity(resource_count);
 
         for entry in entries {
             let binding_info = match self.bindings.get(&entry.binding) {
                 Some(b) => b,
-                None => panic!("Binding {} not found in descriptor set", entry.binding),
+                None => anyhow::bail!(
+                    "Binding {} not found in descriptor set",
+                    entry.binding
+                ),
             };
 
-            // TODO: Check that binding info matches provided resource type?
+            for resource in entry.resources {
+                if !resource_matches(resource, binding_info.descriptor_type) {
+                    anyhow::bail!(
+                        "Binding {} is of type {:?}, can't be written with a {} resource",
+                        entry.binding,
+                        binding_info.descriptor_type,
+                        resource_name(resource),
+                    );
+                }
+            }
+
+            let end = entry.array_element as usize + entry.resources.len();
+            if end > binding_info.descriptor_count as usize {
+                anyhow::bail!(
+                    "Array elements {}..{} out of range for binding {} with {} descriptors",
+                    entry.array_element,
+                    end,
+                    entry.binding,
+                    binding_info.descriptor_count,
+                );
+            }
 
-            let mut write = vk::WriteDescriptorSet::builder()
+            if entry.resources.is_empty() {
+                continue;
+            }
+
+            let write = vk::WriteDescriptorSet::builder()
                 .dst_set(self.raw)
                 .dst_binding(entry.binding)
+                .dst_array_element(entry.array_element)
                 .descriptor_type(binding_info.descriptor_type);
 
-            write = match entry.resource {
-                crate::DescriptorResource::Buffer {
-                    buffer,
-                    offset,
-                    range,
-                } => {
-                    let index = buffer_writes.len();
-
-                    buffer_writes.push(
-                        vk::DescriptorBufferInfo::builder()
-                            .buffer(buffer.raw)
-                            .offset(*offset)
-                            .range(*range)
-                            .build()
-                    );
+            // All resources are of the same kind since they match the binding
+            let buffer_index = buffer_writes.len();
+            let texel_buffer_index = texel_buffer_writes.len();
+            let image_index = image_writes.len();
+
+            for resource in entry.resources {
+                match resource {
+                    crate::DescriptorResource::Buffer {
+                        buffer,
+                        offset,
+                        range,
+                    } => {
+                        let in_bounds = if *range == vk::WHOLE_SIZE {
+                            *offset < buffer.size()
+                        } else {
+                            offset.checked_add(*range)
+                                .is_some_and(|end| end <= buffer.size())
+                        };
+                        if !in_bounds {
+                            anyhow::bail!(
+                                "Binding {}: range {}+{} exceeds buffer size {}",
+                                entry.binding,
+                                offset,
+                                range,
+                                buffer.size(),
+                            );
+                        }
+
+                        buffer_writes.push(
+                            vk::DescriptorBufferInfo::builder()
+                                .buffer(buffer.raw)
+                                .offset(*offset)
+                                .range(*range)
+                                .build()
+                        );
+                    }
+                    crate::DescriptorResource::TexelBuffer {
+                        view,
+                    } => {
+                        texel_buffer_writes.push(view.raw);
+                    }
+                    crate::DescriptorResource::Image {
+                        view,
+                        layout,
+                    } => {
+                        image_writes.push(
+                            vk::DescriptorImageInfo::builder()
+                                .image_view(view.raw)
+                                .image_layout(*layout)
+                                .build()
+                        );
+                    }
+                    crate::DescriptorResource::Sampler {
+                        sampler,
+                    } => {
+                        image_writes.push(
+                            vk::DescriptorImageInfo::builder()
+                                .sampler(sampler.raw)
+                                .build()
+                        );
+                    }
+                    crate::DescriptorResource::InputAttachment {
+                        view,
+                    } => {
+                        image_writes.push(
+                            vk::DescriptorImageInfo::builder()
+                                .image_view(view.raw)
+                                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                                .build()
+                        );
+                    }
+                }
+            }
 
-                    write.buffer_info(&buffer_writes[index..])
+            let write = match entry.resources[0] {
+                crate::DescriptorResource::Buffer { .. } => {
+                    write.buffer_info(&buffer_writes[buffer_index..])
+                }
+                crate::DescriptorResource::TexelBuffer { .. } => {
+                    write.texel_buffer_view(&texel_buffer_writes[texel_buffer_index..])
+                }
+                crate::DescriptorResource::Image { .. }
+                | crate::DescriptorResource::Sampler { .. }
+                | crate::DescriptorResource::InputAttachment { .. } => {
+                    write.image_info(&image_writes[image_index..])
                 }
             };
 
@@ -180,10 +344,64 @@ impl DescriptorSet {
     }
 }
 
+fn resource_matches(
+    resource: &crate::DescriptorResource,
+    ty: vk::DescriptorType,
+) -> bool {
+    match resource {
+        crate::DescriptorResource::Buffer { .. } => matches!(
+            ty,
+            vk::DescriptorType::UNIFORM_BUFFER
+            | vk::DescriptorType::STORAGE_BUFFER
+            | vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+            | vk::DescriptorType::STORAGE_BUFFER_DYNAMIC
+        ),
+        crate::DescriptorResource::TexelBuffer { .. } => matches!(
+            ty,
+            vk::DescriptorType::UNIFORM_TEXEL_BUFFER
+            | vk::DescriptorType::STORAGE_TEXEL_BUFFER
+        ),
+        crate::DescriptorResource::Image { .. } => matches!(
+            ty,
+            vk::DescriptorType::SAMPLED_IMAGE
+            | vk::DescriptorType::STORAGE_IMAGE
+        ),
+        crate::DescriptorResource::Sampler { .. } => {
+            ty == vk::DescriptorType::SAMPLER
+        }
+        crate::DescriptorResource::InputAttachment { .. } => {
+            ty == vk::DescriptorType::INPUT_ATTACHMENT
+        }
+    }
+}
+
+fn resource_name(resource: &crate::DescriptorResource) -> &'static str {
+    match resource {
+        crate::DescriptorResource::Buffer { .. } => "buffer",
+        crate::DescriptorResource::TexelBuffer { .. } => "texel buffer",
+        crate::DescriptorResource::Image { .. } => "image",
+        crate::DescriptorResource::Sampler { .. } => "sampler",
+        crate::DescriptorResource::InputAttachment { .. } => "input attachment",
+    }
+}
+
 impl Drop for DescriptorSet {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_descriptor_pool(self.pool, None);
-        }
+        let pool = self.pool;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_descriptor_pool(pool, None);
+        });
+    }
+}
+
+impl super::DebugName for DescriptorSetLayout {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::DESCRIPTOR_SET_LAYOUT, self.raw.as_raw())
+    }
+}
+
+impl super::DebugName for DescriptorSet {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::DESCRIPTOR_SET, self.raw.as_raw())
     }
 }
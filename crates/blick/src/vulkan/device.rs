@@ -2,31 +2,58 @@ use super::{Instance, PhysicalDevice, Queue};
 
 use anyhow::Result;
 
-use ash::extensions::khr;
+use ash::extensions::{ext, khr};
 use ash::vk;
 
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 use ash::vk::KhrPortabilitySubsetFn;
 
 use gpu_allocator::AllocatorDebugSettings;
-use gpu_allocator::vulkan::{Allocator, AllocatorCreateDesc};
+use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, Allocator, AllocatorCreateDesc};
 
 use std::collections::HashSet;
 use std::ffi::{CStr, CString};
+use std::num::NonZeroUsize;
 use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
-// TODO:
-const RENDER_PASS_CACHE_SIZE: usize = 16;
-const FRAMEBUFFER_CACHE_SIZE: usize = 16;
 
 pub struct DeviceInner {
     pub(super) raw: ash::Device,
     pub(super) instance: Arc<super::Instance>,
     pub(super) physical_device: PhysicalDevice,
-    pub(super) allocator: Option<Arc<Mutex<Allocator>>>,
+    /// Holds its own clone of the `ash::Device` function table. The clone does
+    /// not own the device handle, `destroy_device` is only ever called from
+    /// `DeviceInner::drop`, after the allocator has been dropped.
+    pub(super) allocator: Option<Mutex<Allocator>>,
     /// TODO: Single queue for everything for now, change this?
     pub(super) universal_queue: Queue,
+    /// Only available if the device supports VK_EXT_extended_dynamic_state
+    pub(super) extended_dynamic_state: Option<ext::ExtendedDynamicState>,
+    /// Only available if the device supports VK_KHR_dynamic_rendering
+    pub(super) dynamic_rendering: Option<khr::DynamicRendering>,
+    /// Only available if the device supports VK_KHR_draw_indirect_count
+    pub(super) draw_indirect_count: Option<khr::DrawIndirectCount>,
+    /// Whether samplerAnisotropy is enabled
+    pub(super) sampler_anisotropy: bool,
+    /// Whether depthBiasClamp is enabled
+    pub(super) depth_bias_clamp: bool,
+    /// Whether wideLines is enabled
+    pub(super) wide_lines: bool,
+    /// Descriptor types whose descriptorBinding*UpdateAfterBind feature is
+    /// enabled
+    pub(super) update_after_bind_types: Vec<vk::DescriptorType>,
+    /// Whether VK_EXT_memory_budget is enabled
+    memory_budget: bool,
+    /// Totals of allocations made through allocate
+    allocated_bytes: AtomicU64,
+    allocation_count: AtomicU64,
+    /// Resources dropped by the user, destroyed once the GPU is done with them
+    deletion_queue: Mutex<super::deletion::DeletionQueue>,
+    /// Image views destroyed through destroy_image_views that framebuffers
+    /// in the cache may still refer to, see FramebufferCache::evict_released_views
+    pub(super) released_views: Mutex<Vec<vk::ImageView>>,
 }
 
 pub struct Device {
@@ -36,6 +63,128 @@ pub struct Device {
     framebuffer_cache: super::framebuffer::FramebufferCache,
 }
 
+impl DeviceInner {
+    /// Queues destruction of a resource until the current frame is released
+    pub(super) fn defer_destroy(
+        &self,
+        destructor: impl FnOnce(&DeviceInner) + Send + 'static,
+    ) {
+        self.deletion_queue.lock().unwrap().push(Box::new(destructor));
+    }
+
+    /// Queues destruction of image views. Cached framebuffers using them are
+    /// evicted before the handles can be reused.
+    pub(super) fn destroy_image_views(&self, views: Vec<vk::ImageView>) {
+        if views.is_empty() {
+            return;
+        }
+        self.released_views.lock().unwrap().extend_from_slice(&views);
+        self.defer_destroy(move |device| {
+            for view in views {
+                unsafe {
+                    device.raw.destroy_image_view(view, None)
+                }
+            }
+        });
+    }
+
+    /// Index of the current frame, see Device::frame_index
+    pub(super) fn frame(&self) -> u64 {
+        self.deletion_queue.lock().unwrap().frame()
+    }
+
+    /// Allocates from the allocator, tracked for Device::memory_report
+    pub(super) fn allocate(
+        &self,
+        desc: &AllocationCreateDesc,
+    ) -> gpu_allocator::Result<Allocation> {
+        let allocation = self.allocator
+            .as_ref()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .allocate(desc)?;
+        self.allocated_bytes.fetch_add(allocation.size(), Ordering::Relaxed);
+        self.allocation_count.fetch_add(1, Ordering::Relaxed);
+        Ok(allocation)
+    }
+
+    pub(super) fn free(&self, allocation: Allocation) -> gpu_allocator::Result<()> {
+        let size = allocation.size();
+        self.allocator
+            .as_ref()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .free(allocation)?;
+        self.allocated_bytes.fetch_sub(size, Ordering::Relaxed);
+        self.allocation_count.fetch_sub(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Drops the clamp if depthBiasClamp isn't enabled
+    pub(super) fn supported_depth_bias(&self, bias: crate::DepthBias) -> crate::DepthBias {
+        if self.depth_bias_clamp {
+            bias
+        } else {
+            crate::DepthBias {
+                clamp: 0.0,
+                ..bias
+            }
+        }
+    }
+
+    pub(super) fn queue(&self, queue: crate::QueueType) -> &super::Queue {
+        match queue {
+            crate::QueueType::Universal => &self.universal_queue,
+        }
+    }
+
+    /// Family indices for a barrier, IGNORED unless ownership is transferred
+    pub(super) fn queue_family_indices(
+        &self,
+        queue_transfer: Option<(crate::QueueType, crate::QueueType)>,
+    ) -> (u32, u32) {
+        match queue_transfer {
+            Some((src_queue, dst_queue)) => (
+                self.queue(src_queue).family.index,
+                self.queue(dst_queue).family.index,
+            ),
+            None => (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED),
+        }
+    }
+
+    /// Sets the debug name of a vulkan object, does nothing if debugging is
+    /// not enabled
+    pub(super) fn set_object_name<T: vk::Handle>(&self, object: T, name: &str) {
+        self.set_object_name_raw(T::TYPE, object.as_raw(), name)
+    }
+
+    pub(super) fn set_object_name_raw(
+        &self,
+        object_type: vk::ObjectType,
+        object_handle: u64,
+        name: &str,
+    ) {
+        if let Some(debug_utils) = self.instance.debug_utils.as_ref() {
+            let name = super::debug_cstring(name);
+            let name_info = vk::DebugUtilsObjectNameInfoEXT::builder()
+                .object_type(object_type)
+                .object_handle(object_handle)
+                .object_name(&name)
+                .build();
+
+            unsafe {
+                // Naming is only a debugging aid, ignore failures
+                let _ = debug_utils.set_debug_utils_object_name(
+                    self.raw.handle(),
+                    &name_info,
+                );
+            }
+        }
+    }
+}
+
 impl Drop for DeviceInner {
     fn drop(&mut self) {
         // TODO: Couldn't this result in a lot of headaches if the device
@@ -45,8 +194,15 @@ impl Drop for DeviceInner {
         // Let device finish any pending work
         unsafe { self.raw.device_wait_idle().unwrap() };
 
-        // Destroy allocator
-        self.allocator.take().unwrap();
+        let destructors = self.deletion_queue.get_mut().unwrap().take_all();
+        for destructor in destructors {
+            destructor(self);
+        }
+
+        // Destroy allocator, this frees all remaining memory blocks so it
+        // has to happen before the device is destroyed. Nothing else holds
+        // a reference to the allocator, resources go through DeviceInner.
+        drop(self.allocator.take());
 
         unsafe {
             self.raw.destroy_device(None);
@@ -55,20 +211,32 @@ impl Drop for DeviceInner {
 }
 
 impl Device {
+    /// presentation: Whether the device will present to a surface, the
+    /// swapchain extension is only required and enabled if so
     pub(crate) fn new(
         instance: &Arc<Instance>,
         physical_device: PhysicalDevice,
+        presentation: bool,
         config: &crate::BackendConfig,
     ) -> Result<Self> {
-        let enabled_extension_names = vec![
-            khr::Swapchain::name().as_ptr(),
-            //vk::KhrDynamicRenderingFn::name().as_ptr(),
+        // Checked before creating anything that would need cleaning up
+        let render_pass_cache_size = NonZeroUsize::new(config.render_pass_cache_size)
+            .ok_or_else(|| anyhow::anyhow!("render_pass_cache_size can't be 0"))?;
+        let framebuffer_cache_size = NonZeroUsize::new(config.framebuffer_cache_size)
+            .ok_or_else(|| anyhow::anyhow!("framebuffer_cache_size can't be 0"))?;
+
+        let mut enabled_extension_names = vec![
             //vk::KhrShaderNonSemanticInfoFn::name().as_ptr(),
             vk::ExtDescriptorIndexingFn::name().as_ptr(),
             vk::KhrBufferDeviceAddressFn::name().as_ptr(),
             #[cfg(any(target_os = "macos", target_os = "ios"))]
             KhrPortabilitySubsetFn::name().as_ptr(),
         ];
+        // Headless instances lack VK_KHR_surface, which the swapchain
+        // extension depends on
+        if presentation {
+            enabled_extension_names.push(khr::Swapchain::name().as_ptr());
+        }
 
         // TODO: For now we just create a single queue using first available graphics 
         //      compatible family
@@ -127,18 +295,54 @@ impl Device {
             }
         }
 
+        // Optional extensions, only enabled if supported
+        let extended_dynamic_state_supported = supported_extensions.contains(
+            ext::ExtendedDynamicState::name().to_str().unwrap()
+        );
+        if extended_dynamic_state_supported {
+            enabled_extension_names.push(ext::ExtendedDynamicState::name().as_ptr());
+        }
+        let dynamic_rendering_supported = supported_extensions.contains(
+            khr::DynamicRendering::name().to_str().unwrap()
+        );
+        if dynamic_rendering_supported {
+            enabled_extension_names.push(khr::DynamicRendering::name().as_ptr());
+        }
+        let draw_indirect_count_supported = supported_extensions.contains(
+            khr::DrawIndirectCount::name().to_str().unwrap()
+        );
+        if draw_indirect_count_supported {
+            enabled_extension_names.push(khr::DrawIndirectCount::name().as_ptr());
+        }
+        let memory_budget = supported_extensions.contains(
+            vk::ExtMemoryBudgetFn::name().to_str().unwrap()
+        );
+        if memory_budget {
+            enabled_extension_names.push(vk::ExtMemoryBudgetFn::name().as_ptr());
+        }
+
         let mut descriptor_indexing
             = vk::PhysicalDeviceDescriptorIndexingFeatures::default();
         let mut buffer_device_address
             = vk::PhysicalDeviceBufferDeviceAddressFeatures::default();
         let mut dynamic_rendering
             = vk::PhysicalDeviceDynamicRenderingFeatures::default();
+        let mut extended_dynamic_state
+            = vk::PhysicalDeviceExtendedDynamicStateFeaturesEXT::default();
 
         let mut features2 = vk::PhysicalDeviceFeatures2::builder()
             .push_next(&mut descriptor_indexing)
-            .push_next(&mut buffer_device_address)
-            .push_next(&mut dynamic_rendering)
-            .build();
+            .push_next(&mut buffer_device_address);
+
+        if dynamic_rendering_supported {
+            features2 = features2.push_next(&mut dynamic_rendering);
+        }
+
+        if extended_dynamic_state_supported {
+            features2 = features2.push_next(&mut extended_dynamic_state);
+        }
+
+        let mut features2 = features2.build();
 
         unsafe {
             // Fills in available features of our device
@@ -149,7 +353,85 @@ impl Device {
                 )
         };
 
-        // TODO: Check that necessary features are available.
+        // The queried structs are passed on to create_device, so every
+        // supported feature in them gets enabled. Features blick relies on
+        // have to be supported.
+        let required_features = [
+            (
+                "bufferDeviceAddress",
+                buffer_device_address.buffer_device_address,
+            ),
+            (
+                "runtimeDescriptorArray",
+                descriptor_indexing.runtime_descriptor_array,
+            ),
+            (
+                "descriptorBindingPartiallyBound",
+                descriptor_indexing.descriptor_binding_partially_bound,
+            ),
+            (
+                "descriptorBindingVariableDescriptorCount",
+                descriptor_indexing.descriptor_binding_variable_descriptor_count,
+            ),
+        ];
+        for (name, supported) in required_features {
+            if supported != vk::TRUE {
+                return Err(anyhow::anyhow!("Feature {} not supported", name));
+            }
+        }
+
+        // Base features are enabled explicitly instead of everything that was
+        // queried, optional ones only if supported
+        let supported_features = features2.features;
+        features2.features = vk::PhysicalDeviceFeatures {
+            sampler_anisotropy: supported_features.sampler_anisotropy,
+            pipeline_statistics_query: supported_features.pipeline_statistics_query,
+            depth_bias_clamp: supported_features.depth_bias_clamp,
+            wide_lines: supported_features.wide_lines,
+            ..Default::default()
+        };
+        let sampler_anisotropy = supported_features.sampler_anisotropy == vk::TRUE;
+        let depth_bias_clamp = supported_features.depth_bias_clamp == vk::TRUE;
+        let wide_lines = supported_features.wide_lines == vk::TRUE;
+        // Enabled along with the rest of descriptor_indexing if supported
+        let update_after_bind_types = [
+            (
+                vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_indexing.descriptor_binding_uniform_buffer_update_after_bind,
+            ),
+            (
+                vk::DescriptorType::SAMPLER,
+                descriptor_indexing.descriptor_binding_sampled_image_update_after_bind,
+            ),
+            (
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_indexing.descriptor_binding_sampled_image_update_after_bind,
+            ),
+            (
+                vk::DescriptorType::SAMPLED_IMAGE,
+                descriptor_indexing.descriptor_binding_sampled_image_update_after_bind,
+            ),
+            (
+                vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_indexing.descriptor_binding_storage_image_update_after_bind,
+            ),
+            (
+                vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_indexing.descriptor_binding_storage_buffer_update_after_bind,
+            ),
+            (
+                vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                descriptor_indexing.descriptor_binding_uniform_texel_buffer_update_after_bind,
+            ),
+            (
+                vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                descriptor_indexing.descriptor_binding_storage_texel_buffer_update_after_bind,
+            ),
+        ]
+            .into_iter()
+            .filter(|(_, supported)| *supported == vk::TRUE)
+            .map(|(ty, _)| ty)
+            .collect::<Vec<_>>();
 
         let device_create_info = vk::DeviceCreateInfo::builder()
             .queue_create_infos(&queue_create_info)
@@ -163,11 +445,10 @@ impl Device {
                 physical_device.raw,
                 &device_create_info,
                 None
-            )
-                .expect("Failed to create logical device")
+            )?
         };
 
-        let allocator = Allocator::new(
+        let allocator = match Allocator::new(
             &AllocatorCreateDesc {
                 instance: instance.raw.clone(),
                 device: device.clone(),
@@ -180,23 +461,61 @@ impl Device {
                 },
                 buffer_device_address: true
             }
-        )
-            .expect("Failed to create allocator");
+        ) {
+            Ok(allocator) => allocator,
+            Err(err) => {
+                // No DeviceInner yet to take care of the cleanup
+                unsafe { device.destroy_device(None) };
+                return Err(anyhow::anyhow!("Failed to create allocator: {}", err));
+            }
+        };
 
         let universal_queue = unsafe {
             device.get_device_queue(universal_queue_family.index, 0)
         };
 
+        let extended_dynamic_state = if extended_dynamic_state_supported
+            && extended_dynamic_state.extended_dynamic_state == vk::TRUE
+        {
+            Some(ext::ExtendedDynamicState::new(&instance.raw, &device))
+        } else {
+            None
+        };
+
+        let dynamic_rendering = if dynamic_rendering_supported
+            && dynamic_rendering.dynamic_rendering == vk::TRUE
+        {
+            Some(khr::DynamicRendering::new(&instance.raw, &device))
+        } else {
+            None
+        };
+
+        // The extension has no feature struct, being enabled is enough
+        let draw_indirect_count = draw_indirect_count_supported
+            .then(|| khr::DrawIndirectCount::new(&instance.raw, &device));
+
         let inner = Arc::new(
             DeviceInner {
                 raw: device,
                 instance: instance.clone(),
                 physical_device,
-                allocator: Some(Arc::new(Mutex::new(allocator))),
+                allocator: Some(Mutex::new(allocator)),
                 universal_queue: Queue {
                     raw: universal_queue,
                     family: universal_queue_family,
                 },
+                extended_dynamic_state,
+                dynamic_rendering,
+                draw_indirect_count,
+                sampler_anisotropy,
+                depth_bias_clamp,
+                wide_lines,
+                update_after_bind_types,
+                memory_budget,
+                allocated_bytes: AtomicU64::new(0),
+                allocation_count: AtomicU64::new(0),
+                deletion_queue: Mutex::new(super::deletion::DeletionQueue::new()),
+                released_views: Mutex::new(Vec::new()),
             }
         );
 
@@ -204,17 +523,160 @@ impl Device {
             Self {
                 inner: inner.clone(),
                 render_pass_cache: super::render_pass::RenderPassCache::new(
-                    RENDER_PASS_CACHE_SIZE,
+                    render_pass_cache_size,
                     &inner,
                 ),
                 framebuffer_cache: super::framebuffer::FramebufferCache::new(
-                    FRAMEBUFFER_CACHE_SIZE,
+                    framebuffer_cache_size,
                     &inner,
                 )
             }
         )
     }
 
+    /// Hits and misses of the render pass and framebuffer caches, for tuning
+    /// the cache sizes in BackendConfig
+    pub fn cache_stats(&self) -> crate::CacheStats {
+        let (render_pass_hits, render_pass_misses) = self.render_pass_cache.stats();
+        let (framebuffer_hits, framebuffer_misses) = self.framebuffer_cache.stats();
+
+        crate::CacheStats {
+            render_pass_hits,
+            render_pass_misses,
+            framebuffer_hits,
+            framebuffer_misses,
+        }
+    }
+
+    pub fn info(&self) -> crate::DeviceInfo {
+        self.inner.physical_device.info()
+    }
+
+    /// Memory allocated for buffers and images, along with the budget and usage
+    /// of each memory heap if VK_EXT_memory_budget is supported. Usage covers
+    /// the whole process, including memory blocks the allocator keeps around
+    /// and other APIs.
+    pub fn memory_report(&self) -> crate::MemoryReport {
+        let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        let mut properties = vk::PhysicalDeviceMemoryProperties2::builder();
+        if self.inner.memory_budget {
+            properties = properties.push_next(&mut budget_properties);
+        }
+        let mut properties = properties.build();
+        unsafe {
+            self.inner.instance.raw.get_physical_device_memory_properties2(
+                self.inner.physical_device.raw,
+                &mut properties,
+            );
+        }
+
+        let memory_properties = properties.memory_properties;
+        let heaps = memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(index, heap)| crate::MemoryHeapReport {
+                size: heap.size,
+                device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                budget: self.inner.memory_budget.then(|| budget_properties.heap_budget[index]),
+                usage: self.inner.memory_budget.then(|| budget_properties.heap_usage[index]),
+            })
+            .collect();
+
+        crate::MemoryReport {
+            allocated_bytes: self.inner.allocated_bytes.load(Ordering::Relaxed),
+            allocation_count: self.inner.allocation_count.load(Ordering::Relaxed),
+            heaps,
+        }
+    }
+
+    /// Whether cull mode, front face and depth state can be set while recording
+    pub fn supports_extended_dynamic_state(&self) -> bool {
+        self.inner.extended_dynamic_state.is_some()
+    }
+
+    /// Whether CommandBuffer::begin_rendering and RenderTarget::Dynamic can
+    /// be used
+    pub fn supports_dynamic_rendering(&self) -> bool {
+        self.inner.dynamic_rendering.is_some()
+    }
+
+    /// Whether RenderPassEncoder::draw_indirect_count and
+    /// draw_indexed_indirect_count can be used
+    pub fn supports_draw_indirect_count(&self) -> bool {
+        self.inner.draw_indirect_count.is_some()
+    }
+
+    /// Whether bindings of type ty can use DescriptorBindingFlags::UPDATE_AFTER_BIND
+    pub fn supports_update_after_bind(&self, ty: crate::DescriptorType) -> bool {
+        self.inner.update_after_bind_types.contains(&ty)
+    }
+
+    /// Highest sample count supported by both color and depth attachments
+    pub fn max_sample_count(&self) -> crate::SampleCountFlags {
+        let limits = &self.inner.physical_device.properties.limits;
+        let counts = limits.framebuffer_color_sample_counts
+            & limits.framebuffer_depth_sample_counts;
+
+        [
+            vk::SampleCountFlags::TYPE_64,
+            vk::SampleCountFlags::TYPE_32,
+            vk::SampleCountFlags::TYPE_16,
+            vk::SampleCountFlags::TYPE_8,
+            vk::SampleCountFlags::TYPE_4,
+            vk::SampleCountFlags::TYPE_2,
+        ]
+            .into_iter()
+            .find(|count| counts.contains(*count))
+            .unwrap_or(vk::SampleCountFlags::TYPE_1)
+    }
+
+    /// 1.0 if the device doesn't support anisotropic filtering, in which case
+    /// SamplerDesc::max_anisotropy is ignored
+    pub fn max_sampler_anisotropy(&self) -> f32 {
+        if !self.inner.sampler_anisotropy {
+            return 1.0;
+        }
+        self.inner.physical_device.properties.limits.max_sampler_anisotropy
+    }
+
+    /// First format in candidates that supports all features with the given
+    /// tiling, e.g. to pick a depth format the device can render to
+    pub fn supported_format(
+        &self,
+        candidates: &[crate::ImageFormat],
+        tiling: crate::ImageTiling,
+        features: crate::FormatFeatureFlags,
+    ) -> Option<crate::ImageFormat> {
+        candidates
+            .iter()
+            .copied()
+            .find(|format| {
+                let properties = unsafe {
+                    self.inner.instance.raw.get_physical_device_format_properties(
+                        self.inner.physical_device.raw,
+                        *format,
+                    )
+                };
+                let supported = match tiling {
+                    vk::ImageTiling::LINEAR => properties.linear_tiling_features,
+                    vk::ImageTiling::OPTIMAL => properties.optimal_tiling_features,
+                    _ => vk::FormatFeatureFlags::empty(),
+                };
+                supported.contains(features)
+            })
+    }
+
+    /// Names the object in validation messages and captures, does nothing if
+    /// debugging is not enabled
+    pub fn set_debug_name<T: super::DebugName>(&self, object: &T, name: &str) {
+        let (object_type, object_handle) = object.debug_handle();
+        self.inner.set_object_name_raw(object_type, object_handle, name);
+    }
+
+    pub fn create_sampler(&self, desc: crate::SamplerDesc) -> Result<crate::Sampler> {
+        super::Sampler::new(&self.inner, desc)
+    }
+
     pub fn create_fence(&self) -> Result<crate::Fence> {
         // TODO: Translate error?
         Ok(super::Fence::new(&self.inner))
@@ -224,12 +686,303 @@ impl Device {
         Ok(super::Semaphore::new(&self.inner))
     }
 
+    pub fn create_event(&self) -> Result<crate::Event> {
+        Ok(super::Event::new(&self.inner))
+    }
+
     pub fn create_buffer(&self, desc: crate::BufferDesc) -> Result<crate::Buffer> {
-        Ok(Arc::new(super::Buffer::new(&self.inner, desc)))
+        Ok(Arc::new(super::Buffer::new(&self.inner, desc)?))
+    }
+
+    /// Ring of staging buffers for per-frame uploads, chunk_size is the size
+    /// of each buffer
+    pub fn create_staging_belt(&self, chunk_size: u64) -> super::StagingBelt {
+        super::StagingBelt::new(&self.inner, chunk_size)
+    }
+
+    /// Creates a buffer with data as initial content. Unless the buffer is
+    /// mappable, data is uploaded through a staging buffer and this blocks
+    /// until the copy has completed.
+    pub fn create_buffer_init(
+        &self,
+        usage: crate::BufferUsage,
+        data: &[u8],
+    ) -> Result<crate::Buffer> {
+        if data.is_empty() {
+            anyhow::bail!("Can't create a buffer from empty data");
+        }
+
+        // Either maps to host visible memory
+        if usage.intersects(crate::BufferUsage::MAP_READ | crate::BufferUsage::MAP_WRITE) {
+            let buffer = self.create_buffer(crate::BufferDesc {
+                size: data.len() as u64,
+                usage,
+                min_alignment: None,
+                memory_location: None,
+                name: None,
+            })?;
+            buffer.write_slice(0, data)?;
+            return Ok(buffer);
+        }
+
+        let buffer = self.create_buffer(crate::BufferDesc {
+            size: data.len() as u64,
+            usage: usage | crate::BufferUsage::TRANSFER_DST,
+            min_alignment: None,
+            memory_location: None,
+            name: None,
+        })?;
+
+        let staging = self.create_buffer(crate::BufferDesc {
+            size: data.len() as u64,
+            usage: crate::BufferUsage::MAP_WRITE | crate::BufferUsage::TRANSFER_SRC,
+            min_alignment: None,
+            memory_location: None,
+            name: Some("staging".to_owned()),
+        })?;
+        staging.write_slice(0, data)?;
+
+        self.run_commands(|command_buffer| {
+            command_buffer.copy_buffer(&staging, 0, &buffer, 0, data.len() as u64);
+        })?;
+        super::buffer::destroy_idle(staging);
+
+        Ok(buffer)
+    }
+
+    /// Creates an image with data as initial content, uploaded through a
+    /// staging buffer. Blocks until the upload has completed, the image is
+    /// left in SHADER_READ_ONLY_OPTIMAL. data holds mip level 0 of all array
+    /// layers, tightly packed, see generate_mipmaps for the other levels.
+    pub fn create_image_init(
+        &self,
+        desc: crate::ImageDesc,
+        data: &[u8],
+    ) -> Result<crate::Image> {
+        let texel_size = super::image::texel_size(desc.format)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported format {:?} for create_image_init", desc.format))?;
+        let expected_size = texel_size as u64
+            * desc.extent.width as u64
+            * desc.extent.height as u64
+            * desc.extent.depth as u64
+            * desc.array_layers as u64;
+        if data.len() as u64 != expected_size {
+            anyhow::bail!(
+                "Image data is {} bytes, expected {} bytes for {:?} of {}x{}x{} with {} layers",
+                data.len(),
+                expected_size,
+                desc.format,
+                desc.extent.width,
+                desc.extent.height,
+                desc.extent.depth,
+                desc.array_layers,
+            );
+        }
+
+        let extent = desc.extent;
+        let image = self.create_image(crate::ImageDesc {
+            usage: desc.usage | crate::ImageUsage::TRANSFER_DST,
+            ..desc
+        })?;
+
+        let staging = self.create_buffer(crate::BufferDesc {
+            size: data.len() as u64,
+            usage: crate::BufferUsage::MAP_WRITE | crate::BufferUsage::TRANSFER_SRC,
+            min_alignment: None,
+            memory_location: None,
+            name: Some("staging".to_owned()),
+        })?;
+        staging.write_slice(0, data)?;
+
+        let aspect_mask = super::image::aspect_mask(image.desc.format);
+        self.run_commands(|command_buffer| {
+            command_buffer.transition(
+                &[],
+                &[],
+                &[crate::ImageBarrier {
+                    image: &image,
+                    src_access_mask: vk::AccessFlags::empty(),
+                    dst_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                    old_layout: vk::ImageLayout::UNDEFINED,
+                    new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    subresource_range: crate::ImageSubresourceRange::all(aspect_mask),
+                    queue_transfer: None,
+                }],
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+            );
+            command_buffer.copy_buffer_to_image(
+                &staging,
+                0,
+                &image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                0,
+                extent,
+            );
+            command_buffer.transition(
+                &[],
+                &[],
+                &[crate::ImageBarrier {
+                    image: &image,
+                    src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                    dst_access_mask: vk::AccessFlags::SHADER_READ,
+                    old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    subresource_range: crate::ImageSubresourceRange::all(aspect_mask),
+                    queue_transfer: None,
+                }],
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            );
+        })?;
+        super::buffer::destroy_idle(staging);
+
+        Ok(image)
+    }
+
+    /// Fills mip levels 1.. of image by repeatedly blitting down from level 0.
+    /// Level 0 has to be in SHADER_READ_ONLY_OPTIMAL, e.g. after
+    /// create_image_init, all levels are left in SHADER_READ_ONLY_OPTIMAL.
+    /// Blocks until done.
+    pub fn generate_mipmaps(&self, image: &crate::Image) -> Result<()> {
+        let mip_levels = image.desc.mip_levels;
+        if mip_levels <= 1 {
+            return Ok(());
+        }
+        if !image.desc.usage.contains(crate::ImageUsage::TRANSFER_SRC | crate::ImageUsage::TRANSFER_DST) {
+            anyhow::bail!("Generating mipmaps requires TRANSFER_SRC and TRANSFER_DST usage");
+        }
+        let format_properties = unsafe {
+            self.inner.instance.raw.get_physical_device_format_properties(
+                self.inner.physical_device.raw,
+                image.desc.format,
+            )
+        };
+        if !format_properties
+            .optimal_tiling_features
+            .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+        {
+            anyhow::bail!("Format {:?} doesn't support linear blits", image.desc.format);
+        }
+
+        let aspect_mask = super::image::aspect_mask(image.desc.format);
+        let barrier = |range, src_access_mask, dst_access_mask, old_layout, new_layout| {
+            crate::ImageBarrier {
+                image,
+                src_access_mask,
+                dst_access_mask,
+                old_layout,
+                new_layout,
+                subresource_range: range,
+                queue_transfer: None,
+            }
+        };
+
+        self.run_commands(|command_buffer| {
+            // Level 0 comes from the upload and may have been sampled since,
+            // reads only need an execution dependency
+            command_buffer.transition(
+                &[],
+                &[],
+                &[barrier(
+                    crate::ImageSubresourceRange::mip_level(aspect_mask, 0),
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                )],
+                vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+            );
+
+            for level in 1..mip_levels {
+                let mut barriers = vec![barrier(
+                    crate::ImageSubresourceRange::mip_level(aspect_mask, level),
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                )];
+                // The other source levels were blitted to
+                if level > 1 {
+                    barriers.push(barrier(
+                        crate::ImageSubresourceRange::mip_level(aspect_mask, level - 1),
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::TRANSFER_READ,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    ));
+                }
+                command_buffer.transition(
+                    &[],
+                    &[],
+                    &barriers,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                );
+                command_buffer.blit_image(
+                    image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    level - 1,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    level,
+                    vk::Filter::LINEAR,
+                );
+            }
+
+            // All levels but the last were used as blit source
+            command_buffer.transition(
+                &[],
+                &[],
+                &[
+                    barrier(
+                        crate::ImageSubresourceRange {
+                            level_count: mip_levels - 1,
+                            ..crate::ImageSubresourceRange::all(aspect_mask)
+                        },
+                        vk::AccessFlags::TRANSFER_READ,
+                        vk::AccessFlags::SHADER_READ,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    ),
+                    barrier(
+                        crate::ImageSubresourceRange::mip_level(aspect_mask, mip_levels - 1),
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::SHADER_READ,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    ),
+                ],
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            );
+        })
+    }
+
+    pub fn create_buffer_view(
+        &self,
+        buffer: &crate::Buffer,
+        desc: crate::BufferViewDesc,
+    ) -> Result<crate::BufferView> {
+        Ok(Arc::new(super::BufferView::new(&self.inner, buffer, desc)?))
     }
 
     pub fn create_image(&self, desc: crate::ImageDesc) -> Result<crate::Image> {
-        Ok(Arc::new(super::Image::new(&self.inner, desc)))
+        Ok(Arc::new(super::Image::new(&self.inner, desc)?))
+    }
+
+    /// Creates an image sharing the memory of other, e.g. for transient render
+    /// targets never used at the same time. Contents of either image are
+    /// undefined after the other has been written, the user has to barrier
+    /// between uses and transition from UNDEFINED when switching images.
+    /// Fails if the memory is too small or of an incompatible type.
+    pub fn create_aliased_image(
+        &self,
+        other: &crate::Image,
+        desc: crate::ImageDesc,
+    ) -> Result<crate::Image> {
+        Ok(Arc::new(super::Image::new_aliased(&self.inner, other, desc)?))
     }
 
     pub fn create_image_view(
@@ -237,14 +990,27 @@ impl Device {
         image: &crate::Image,
         desc: crate::ImageViewDesc
     ) -> Result<crate::ImageView> {
+        image.validate_view(&desc)?;
         Ok(image.view(desc))
     }
 
+    /// Creates multiple views of the same image, e.g. one per array layer
+    pub fn create_image_views(
+        &self,
+        image: &crate::Image,
+        descs: &[crate::ImageViewDesc],
+    ) -> Result<Vec<crate::ImageView>> {
+        for desc in descs {
+            image.validate_view(desc)?;
+        }
+        Ok(image.views(descs))
+    }
+
     pub fn create_descriptor_set_layout(
         &self,
         desc: crate::DescriptorSetLayoutDesc<'_>
     ) -> Result<crate::DescriptorSetLayout> {
-        Ok(super::DescriptorSetLayout::new(&self.inner, desc))
+        super::DescriptorSetLayout::new(&self.inner, desc)
     }
 
     pub fn create_descriptor_set(
@@ -267,18 +1033,14 @@ impl Device {
         &self,
         desc: crate::RenderPassDesc<'_>,
     ) -> Result<crate::RenderPass> {
-        Ok(self.render_pass_cache.get_or_create(
-            desc
-        ))
+        self.render_pass_cache.get_or_create(desc)
     }
 
     pub fn create_framebuffer(
         &self,
         desc: crate::FramebufferDesc<'_>,
     ) -> Result<crate::Framebuffer> {
-        Ok(self.framebuffer_cache.get_or_create(
-            desc
-        ))
+        self.framebuffer_cache.get_or_create(desc)
     }
 
 
@@ -286,21 +1048,90 @@ impl Device {
         &self,
         desc: crate::GraphicsPipelineDesc,
     ) -> Result<crate::GraphicsPipeline> {
-        Ok(Arc::new(super::GraphicsPipeline::new(&self.inner, desc)))
+        Ok(Arc::new(super::GraphicsPipeline::new(&self.inner, desc)?))
     }
 
     pub fn create_compute_pipeline(
         &self,
         desc: crate::ComputePipelineDesc,
     ) -> Result<crate::ComputePipeline> {
-        Ok(Arc::new(super::ComputePipeline::new(&self.inner, desc)))
+        Ok(Arc::new(super::ComputePipeline::new(&self.inner, desc)?))
     }
 
+    /// Compiles the shader and reflects its descriptor set layouts, push
+    /// constants and workgroup size. Specialization constants are ignored.
+    pub fn reflect_shader(
+        &self,
+        desc: &crate::ShaderModuleDesc,
+    ) -> Result<crate::ShaderReflection> {
+        super::shader::reflect_shader(desc)
+    }
+
+
+    pub fn create_query_pool(
+        &self,
+        desc: crate::QueryPoolDesc,
+    ) -> Result<crate::QueryPool> {
+        super::QueryPool::new(&self.inner, desc)
+    }
+
+    /// Blocks until the results are available
+    pub fn get_query_results(
+        &self,
+        pool: &crate::QueryPool,
+        first_query: u32,
+        query_count: u32,
+    ) -> Result<Vec<u64>> {
+        pool.results(first_query, query_count)
+    }
 
-    pub fn create_command_buffer(&self) -> Result<crate::CommandBuffer> {
-        Ok(super::CommandBuffer::new(&self.inner))
+    pub fn create_graphics_pipeline_async(
+        &self,
+        desc: crate::GraphicsPipelineDesc,
+    ) -> Result<crate::PendingPipeline<crate::GraphicsPipeline>> {
+        super::GraphicsPipeline::new_async(&self.inner, desc)
     }
 
+    pub fn create_compute_pipeline_async(
+        &self,
+        desc: crate::ComputePipelineDesc,
+    ) -> Result<crate::PendingPipeline<crate::ComputePipeline>> {
+        super::ComputePipeline::new_async(&self.inner, desc)
+    }
+
+    pub fn create_command_buffer(
+        &self,
+        level: crate::CommandBufferLevel,
+    ) -> Result<crate::CommandBuffer> {
+        Ok(super::CommandBuffer::new(&self.inner, level))
+    }
+
+    /// Records commands into a one-time command buffer, submits it and waits
+    /// for it to complete. Meant for work outside the frame loop, e.g. uploads.
+    pub fn run_commands(
+        &self,
+        f: impl FnOnce(&mut crate::CommandBuffer),
+    ) -> Result<()> {
+        let mut command_buffer = self.create_command_buffer(
+            crate::CommandBufferLevel::PRIMARY
+        )?;
+        let fence = self.create_fence()?;
+
+        command_buffer.begin();
+        f(&mut command_buffer);
+        command_buffer.end();
+
+        self.submit(&[&command_buffer], &[], &[], Some(&fence))?;
+        self.wait(&fence)?;
+
+        // Known idle, no need to hold them until the next released frame
+        command_buffer.destroy_idle();
+        fence.destroy_idle();
+        Ok(())
+    }
+
+    /// Wait semaphores block all commands of the submission, see submit_batch
+    /// for waiting at specific stages or Backend::submit_frame for frames
     pub fn submit(
         &self,
         command_buffers: &[&crate::CommandBuffer],
@@ -317,6 +1148,7 @@ impl Device {
             .iter()
             .map(|sem| sem.raw)
             .collect::<Vec<_>>();
+        let wait_stages = vec![vk::PipelineStageFlags::ALL_COMMANDS; wait_semaphores.len()];
 
         let signal_semaphores = signal_semaphores
             .iter()
@@ -327,11 +1159,13 @@ impl Device {
             .map(|fence| fence.raw)
             .unwrap_or(vk::Fence::null());
 
+        // wait_dst_stage_mask also sets the wait semaphore count, an empty
+        // mask would drop the waits
         let submit_info = vk::SubmitInfo::builder()
             .command_buffers(&command_buffers)
             .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
             .signal_semaphores(&signal_semaphores)
-            .wait_dst_stage_mask(&[])
             .build();
 
         unsafe {
@@ -344,6 +1178,60 @@ impl Device {
                 .expect("Failed to submit command buffer");
         }
 
+        self.inner.instance.check_validation();
+        Ok(())
+    }
+
+    /// Submits several batches with their own synchronization in a single
+    /// queue submission, fence is signaled once all of them have completed
+    pub fn submit_batch(
+        &self,
+        submits: &[crate::SubmitDesc],
+        fence: Option<&crate::Fence>,
+    ) -> Result<()> {
+        // Collected up front as the submit infos point into these
+        let command_buffers = submits
+            .iter()
+            .map(|submit| submit.command_buffers.iter().map(|cb| cb.raw).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let wait_semaphores = submits
+            .iter()
+            .map(|submit| submit.wait_semaphores.iter().map(|(sem, _)| sem.raw).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let wait_stages = submits
+            .iter()
+            .map(|submit| submit.wait_semaphores.iter().map(|(_, stage)| *stage).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let signal_semaphores = submits
+            .iter()
+            .map(|submit| submit.signal_semaphores.iter().map(|sem| sem.raw).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let submit_infos = (0..submits.len())
+            .map(|i| {
+                vk::SubmitInfo::builder()
+                    .command_buffers(&command_buffers[i])
+                    .wait_semaphores(&wait_semaphores[i])
+                    .wait_dst_stage_mask(&wait_stages[i])
+                    .signal_semaphores(&signal_semaphores[i])
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        let fence = fence
+            .map(|fence| fence.raw)
+            .unwrap_or(vk::Fence::null());
+
+        unsafe {
+            self.inner.raw
+                .queue_submit(
+                    self.inner.universal_queue.raw,
+                    &submit_infos,
+                    fence
+                )?;
+        }
+
+        self.inner.instance.check_validation();
         Ok(())
     }
 
@@ -357,13 +1245,65 @@ impl Device {
                 )
                 .expect("Failed to wait for fence");
         }
+        self.inner.instance.check_validation();
         Ok(())
     }
 
+    /// Waits for all queues and destroys all dropped resources, including
+    /// those of frames not yet released. Resources referenced by command
+    /// buffers recorded but not yet submitted have to be kept alive.
     pub fn wait_idle(&self) -> Result<()> {
         unsafe {
             self.inner.raw.device_wait_idle()?
         }
+        self.inner.instance.check_validation();
+
+        let destructors = self.inner.deletion_queue.lock().unwrap().take_all();
+        for destructor in destructors {
+            destructor(&self.inner);
+        }
+        Ok(())
+    }
+
+    /// Number of dropped resources waiting for their frame to be released
+    pub fn pending_destructions(&self) -> usize {
+        self.inner.deletion_queue.lock().unwrap().len()
+    }
+
+    /// Index of the current frame, resources dropped now are destroyed once
+    /// this frame is released
+    pub fn frame_index(&self) -> u64 {
+        self.inner.frame()
+    }
+
+    /// Ends the current frame and returns its index, pass it to release_frame
+    /// once all work submitted during the frame has completed
+    pub fn end_frame(&self) -> u64 {
+        self.inner.deletion_queue.lock().unwrap().end_frame()
+    }
+
+    /// Destroys resources dropped during frame and earlier frames. Call once
+    /// the GPU is done with the frame, e.g. after waiting for its fence.
+    pub fn release_frame(&self, frame: u64) {
+        // Keeps released_views from growing when no framebuffers are created
+        self.framebuffer_cache.evict_released_views();
+
+        // Don't hold the lock while destroying, destructors may drop
+        // resources in turn
+        let destructors = self.inner.deletion_queue.lock().unwrap().take_until(frame);
+        for destructor in destructors {
+            destructor(&self.inner);
+        }
+    }
+
+    /// Waits for all work submitted to the queue, unlike wait_idle other
+    /// queues are not waited for
+    pub fn wait_queue_idle(&self, queue: crate::QueueType) -> Result<()> {
+        let queue = self.inner.queue(queue);
+
+        unsafe {
+            self.inner.raw.queue_wait_idle(queue.raw)?
+        }
         Ok(())
     }
 
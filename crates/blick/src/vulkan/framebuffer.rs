@@ -1,7 +1,10 @@
+use anyhow::{bail, Result};
 use ash::vk;
+use ash::vk::Handle;
 
 use lru::LruCache;
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone, Default, Eq, Hash, PartialEq)]
@@ -19,12 +22,15 @@ struct FramebufferInner {
     device: Arc<super::DeviceInner>,
 }
 
+#[derive(Clone)]
 pub struct Framebuffer {
     inner: Arc<FramebufferInner>,
 }
 
 pub struct FramebufferCache {
     cache: Mutex<LruCache<FramebufferKey, Arc<FramebufferInner>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
     device: Arc<super::DeviceInner>,
 }
 
@@ -34,38 +40,77 @@ impl Framebuffer {
     }
 }
 
+impl super::DebugName for Framebuffer {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::FRAMEBUFFER, self.raw().as_raw())
+    }
+}
+
 impl Drop for FramebufferInner {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_framebuffer(self.raw, None);
-        }
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_framebuffer(raw, None);
+        });
     }
 }
 
 impl FramebufferCache {
     /// device: Device for destroying framebuffers
-    pub fn new(size: usize, device: &Arc<super::DeviceInner>) -> Self {
+    pub fn new(size: NonZeroUsize, device: &Arc<super::DeviceInner>) -> Self {
         Self {
-            cache: Mutex::new(LruCache::new(
-                NonZeroUsize::new(size).unwrap()
-            )),
+            cache: Mutex::new(LruCache::new(size)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
             device: device.clone(),
         }
     }
 
+    /// (hits, misses) of get_or_create
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Evicts framebuffers using views destroyed since the last call, their
+    /// handles may be reused by new views
+    pub fn evict_released_views(&self) {
+        let released = std::mem::take(&mut *self.device.released_views.lock().unwrap());
+        if released.is_empty() {
+            return;
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        let stale = cache
+            .iter()
+            .filter(|(key, _)| key.attachments.iter().any(|view| released.contains(view)))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in stale {
+            cache.pop(&key);
+        }
+    }
+
     pub fn get_or_create(
         &self,
         desc: crate::FramebufferDesc<'_>
-    ) -> Framebuffer {
+    ) -> Result<Framebuffer> {
         let key = FramebufferKey::from(&desc);
-        
-        Framebuffer {
-            inner:
-                self.cache.lock().unwrap().get_or_insert(
-                    key,
-                    || Arc::new(FramebufferInner::new(&self.device, &desc))
-                ).clone(),
+
+        self.evict_released_views();
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(inner) = cache.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Framebuffer { inner: inner.clone() });
         }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let inner = Arc::new(FramebufferInner::new(&self.device, &desc)?);
+        cache.put(key, inner.clone());
+
+        Ok(Framebuffer { inner })
     }
 }
 
@@ -73,7 +118,28 @@ impl FramebufferInner {
     fn new<'a>(
         device: &Arc<super::DeviceInner>,
         desc: &crate::FramebufferDesc<'a>
-    ) -> Self {
+    ) -> Result<Self> {
+        if desc.attachments.len() as u32 != desc.render_pass.num_attachments() {
+            bail!(
+                "Framebuffer has {} attachments, render pass expects {}",
+                desc.attachments.len(),
+                desc.render_pass.num_attachments(),
+            );
+        }
+        for (index, attachment) in desc.attachments.iter().enumerate() {
+            let extent = attachment.image_view.extent;
+            if extent.width < desc.extent.width || extent.height < desc.extent.height {
+                bail!(
+                    "Attachment {} is {}x{}, smaller than the framebuffer of {}x{}",
+                    index,
+                    extent.width,
+                    extent.height,
+                    desc.extent.width,
+                    desc.extent.height,
+                );
+            }
+        }
+
         let attachments = desc.attachments
             .iter()
             .map(|a| a.image_view.raw)
@@ -88,13 +154,13 @@ impl FramebufferInner {
                     .height(desc.extent.height)
                     .layers(1),
                 None
-            ).expect("Failed to create framebuffer")
+            )?
         };
         
-        Self {
+        Ok(Self {
             raw,
             device: device.clone(),
-        }
+        })
     }
 }
 
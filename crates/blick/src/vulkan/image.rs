@@ -1,6 +1,8 @@
 use crate::ImageDesc;
 
+use anyhow::Result;
 use ash::vk;
+use ash::vk::Handle;
 
 use gpu_allocator::MemoryLocation;
 use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, AllocationScheme};
@@ -12,68 +14,136 @@ pub struct Image {
     pub(super) raw: vk::Image,
     pub desc: ImageDesc,
     allocation: Option<Allocation>,
+    /// Image whose memory this image is bound to, see Device::create_aliased_image
+    aliased: Option<crate::Image>,
     device: Arc<super::DeviceInner>,
 
     views: Mutex<HashMap<crate::ImageViewDesc, ImageView>>,
+    /// None unless layout tracking has been enabled with track_layout
+    layouts: Mutex<Option<LayoutTracker>>,
 }
 
 #[derive(Clone, Copy)]
 pub struct ImageView {
     pub(super) raw: vk::ImageView,
+    pub(super) format: vk::Format,
+    /// Extent of the base mip level
+    pub(super) extent: vk::Extent2D,
 }
 
-impl Image {
-    pub(super) fn new(device: &Arc<super::DeviceInner>, desc: ImageDesc) -> Self {
-        let image_create_info = vk::ImageCreateInfo::builder()
-            .image_type(desc.image_type)
-            .format(desc.format)
-            .extent(desc.extent)
-            .usage((&desc.usage).into())
-            .tiling(vk::ImageTiling::OPTIMAL) // TODO: Will this ever change?
-            .flags(vk::ImageCreateFlags::empty())
-            .mip_levels(1)
-            .samples(vk::SampleCountFlags::TYPE_1)
-            .array_layers(1)
-            .build();
+/// View that isn't part of the image's view cache, destroyed when dropped.
+/// Derefs to ImageView so it can be used wherever a cached view can.
+pub struct OwnedImageView {
+    view: ImageView,
+    device: Arc<super::DeviceInner>,
+}
 
-        let image = unsafe {
-            device.raw.create_image(&image_create_info, None)
-                .expect("Failed to create image")
-        };
+impl Image {
+    pub(super) fn new(device: &Arc<super::DeviceInner>, desc: ImageDesc) -> Result<Self> {
+        let image = create_raw(device, &desc)?;
 
         let memory_requirements = unsafe {
             device.raw.get_image_memory_requirements(image)
         };
 
-        let allocation = device
-            .allocator
-            .as_ref()
-            .unwrap()
-            .lock()
-            .unwrap()
-            .allocate(
-                &AllocationCreateDesc {
-                    name: "image",
-                    requirements: memory_requirements,
-                    location: MemoryLocation::GpuOnly,
-                    linear: false,
-                    allocation_scheme: AllocationScheme::GpuAllocatorManaged,
-                }
-            )
-            .expect("Failed to allocate image memory");
+        let allocation = match device.allocate(
+            &AllocationCreateDesc {
+                name: desc.name.as_deref().unwrap_or("image"),
+                requirements: memory_requirements,
+                location: MemoryLocation::GpuOnly,
+                linear: false,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            }
+        ) {
+            Ok(allocation) => allocation,
+            Err(err) => {
+                unsafe { device.raw.destroy_image(image, None) };
+                return Err(super::allocation_error(err));
+            }
+        };
 
-        unsafe {
+        if let Err(err) = unsafe {
             device.raw.bind_image_memory(image, allocation.memory(), allocation.offset())
-                .expect("Failed to bind image memory")
-        };
+        } {
+            device.free(allocation)?;
+            unsafe { device.raw.destroy_image(image, None) };
+            return Err(super::resource_error(err));
+        }
 
-        Self {
+        if let Some(name) = desc.name.as_deref() {
+            device.set_object_name(image, name);
+        }
+
+        Ok(Self {
             raw: image,
             desc,
             allocation: Some(allocation),
+            aliased: None,
             device: device.clone(),
             views: Mutex::new(HashMap::new()),
+            layouts: Mutex::new(None),
+        })
+    }
+
+    /// Creates an image bound to the memory of other, which is kept alive
+    /// until this image is dropped
+    pub(super) fn new_aliased(
+        device: &Arc<super::DeviceInner>,
+        other: &crate::Image,
+        desc: ImageDesc,
+    ) -> Result<Self> {
+        // Aliases of aliases share the memory of the original image
+        let mut owner = other;
+        while let Some(aliased) = owner.aliased.as_ref() {
+            owner = aliased;
         }
+        let allocation = owner.allocation
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Can't alias an image without memory, e.g. a swapchain image"))?;
+
+        let image = create_raw(device, &desc)?;
+
+        let (requirements, owner_requirements) = unsafe {
+            (
+                device.raw.get_image_memory_requirements(image),
+                device.raw.get_image_memory_requirements(owner.raw),
+            )
+        };
+        // Memory type of the allocation is one of the owner's types
+        let compatible = requirements.size <= allocation.size()
+            && allocation.offset() % requirements.alignment == 0
+            && requirements.memory_type_bits & owner_requirements.memory_type_bits
+                == owner_requirements.memory_type_bits;
+        if !compatible {
+            unsafe { device.raw.destroy_image(image, None) };
+            anyhow::bail!(
+                "Image requires {} bytes aligned to {}, incompatible with the {} bytes of aliased memory",
+                requirements.size,
+                requirements.alignment,
+                allocation.size(),
+            );
+        }
+
+        if let Err(err) = unsafe {
+            device.raw.bind_image_memory(image, allocation.memory(), allocation.offset())
+        } {
+            unsafe { device.raw.destroy_image(image, None) };
+            return Err(super::resource_error(err));
+        }
+
+        if let Some(name) = desc.name.as_deref() {
+            device.set_object_name(image, name);
+        }
+
+        Ok(Self {
+            raw: image,
+            desc,
+            allocation: None,
+            aliased: Some(owner.clone()),
+            device: device.clone(),
+            views: Mutex::new(HashMap::new()),
+            layouts: Mutex::new(None),
+        })
     }
     /// Creates a wrapper around a raw image object
     /// No cleanup will be invoked for this type of Image
@@ -86,9 +156,27 @@ impl Image {
             raw,
             desc,
             allocation: None,
+            aliased: None,
             device: device.clone(),
             views: Mutex::new(HashMap::new()),
+            layouts: Mutex::new(None),
+        }
+    }
+
+    pub(super) fn validate_view(&self, desc: &crate::ImageViewDesc) -> Result<()> {
+        if desc.format != self.desc.format && !self.desc.mutable_format {
+            anyhow::bail!(
+                "View format {:?} differs from image format {:?}, requires ImageDesc::mutable_format",
+                desc.format,
+                self.desc.format,
+            );
+        }
+        let cube = desc.view_type == vk::ImageViewType::CUBE
+            || desc.view_type == vk::ImageViewType::CUBE_ARRAY;
+        if cube && !self.desc.cube_compatible {
+            anyhow::bail!("Cube views require ImageDesc::cube_compatible");
         }
+        Ok(())
     }
 
     pub(super) fn view(&self, desc: crate::ImageViewDesc) -> crate::ImageView {
@@ -102,30 +190,255 @@ impl Image {
             view
         }
     }
+
+    /// Creates a view outside of the view cache, for transient views that
+    /// shouldn't live as long as the image, e.g. per mip views. The view must
+    /// not be dropped before the image
+    pub fn create_view_uncached(&self, desc: crate::ImageViewDesc) -> Result<OwnedImageView> {
+        self.validate_view(&desc)?;
+        Ok(OwnedImageView {
+            view: ImageView::new(&self.device, self, desc),
+            device: self.device.clone(),
+        })
+    }
+
+    /// Number of views in the view cache
+    pub fn cached_view_count(&self) -> usize {
+        self.views.lock().unwrap().len()
+    }
+
+    /// Destroys all cached views of this image, cached framebuffers using them
+    /// are evicted. Views previously returned from the cache must not be used
+    /// after this
+    pub fn clear_view_cache(&self) {
+        let views = self.views
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, view)| view.raw)
+            .collect::<Vec<_>>();
+
+        self.device.destroy_image_views(views);
+    }
+
+    /// Enables layout tracking for this image, see CommandBuffer::transition_to.
+    /// All subresources are assumed to be in layout, last accessed with
+    /// access_mask in stage_mask. Calling this again resets the tracked state,
+    /// which is needed after layout changes done by render passes.
+    ///
+    /// State is updated when commands are recorded, so command buffers have
+    /// to be submitted in the order they were recorded in.
+    pub fn track_layout(
+        &self,
+        layout: crate::ImageLayout,
+        access_mask: crate::AccessFlags,
+        stage_mask: crate::PipelineStageFlags,
+    ) {
+        *self.layouts.lock().unwrap() = Some(LayoutTracker::new(
+            self.desc.mip_levels,
+            self.desc.array_layers,
+            SubresourceState {
+                layout,
+                access_mask,
+                stage_mask,
+            },
+        ));
+    }
+
+    /// Last tracked layout of a subresource, None if tracking isn't enabled or
+    /// the subresource is out of range
+    pub fn tracked_layout(
+        &self,
+        mip_level: u32,
+        array_layer: u32,
+    ) -> Option<crate::ImageLayout> {
+        self.layouts
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|tracker| tracker.state(mip_level, array_layer))
+            .map(|state| state.layout)
+    }
+
+    /// Checks old_layout of barrier against the tracked layouts and records the
+    /// new state. Does nothing if tracking isn't enabled.
+    pub(super) fn track_barrier(
+        &self,
+        barrier: &crate::ImageBarrier,
+        stage_mask: vk::PipelineStageFlags,
+    ) {
+        let mut layouts = self.layouts.lock().unwrap();
+        let tracker = match layouts.as_mut() {
+            Some(tracker) => tracker,
+            None => return,
+        };
+
+        let new_state = SubresourceState {
+            layout: barrier.new_layout,
+            access_mask: barrier.dst_access_mask,
+            stage_mask,
+        };
+        for state in tracker.range_mut(&barrier.subresource_range) {
+            // UNDEFINED is always valid, it discards the contents
+            if barrier.old_layout != vk::ImageLayout::UNDEFINED
+                && barrier.old_layout != state.layout
+            {
+                log::warn!(
+                    "Bad transition of image '{}': old_layout is {:?} but image is in {:?}",
+                    self.desc.name.as_deref().unwrap_or("unnamed"),
+                    barrier.old_layout,
+                    state.layout,
+                );
+            }
+            *state = new_state;
+        }
+    }
+
+    /// Builds barriers transitioning the whole image from its tracked state to
+    /// the new state. Returns the barriers and the combined source stages.
+    pub(super) fn tracked_transition(
+        &self,
+        new_layout: vk::ImageLayout,
+        dst_access_mask: vk::AccessFlags,
+        dst_stage_mask: vk::PipelineStageFlags,
+    ) -> (Vec<vk::ImageMemoryBarrier>, vk::PipelineStageFlags) {
+        let mut layouts = self.layouts.lock().unwrap();
+        let tracker = layouts
+            .as_mut()
+            .expect("Layout tracking not enabled, see Image::track_layout");
+
+        let aspect_mask = aspect_mask(self.desc.format);
+        let barrier = |state: &SubresourceState, range: vk::ImageSubresourceRange| {
+            vk::ImageMemoryBarrier::builder()
+                .image(self.raw)
+                .src_access_mask(state.access_mask)
+                .dst_access_mask(dst_access_mask)
+                .old_layout(state.layout)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .subresource_range(range)
+                .build()
+        };
+
+        let first = tracker.states[0];
+        let (barriers, src_stage_mask) = if tracker.states.iter().all(|state| *state == first) {
+            // Common case, a single barrier covers the whole image
+            let range = vk::ImageSubresourceRange::builder()
+                .aspect_mask(aspect_mask)
+                .level_count(vk::REMAINING_MIP_LEVELS)
+                .layer_count(vk::REMAINING_ARRAY_LAYERS)
+                .build();
+            (vec![barrier(&first, range)], first.stage_mask)
+        } else {
+            let mut src_stage_mask = vk::PipelineStageFlags::empty();
+            let barriers = tracker.states
+                .iter()
+                .enumerate()
+                .map(|(index, state)| {
+                    src_stage_mask |= state.stage_mask;
+                    let range = vk::ImageSubresourceRange::builder()
+                        .aspect_mask(aspect_mask)
+                        .base_mip_level(index as u32 % tracker.mip_levels)
+                        .level_count(1)
+                        .base_array_layer(index as u32 / tracker.mip_levels)
+                        .layer_count(1)
+                        .build();
+                    barrier(state, range)
+                })
+                .collect();
+            (barriers, src_stage_mask)
+        };
+
+        tracker.states.fill(SubresourceState {
+            layout: new_layout,
+            access_mask: dst_access_mask,
+            stage_mask: dst_stage_mask,
+        });
+
+        let src_stage_mask = if src_stage_mask.is_empty() {
+            vk::PipelineStageFlags::TOP_OF_PIPE
+        } else {
+            src_stage_mask
+        };
+        (barriers, src_stage_mask)
+    }
+
+    /// Same as view but creates all views under a single lock of the cache
+    pub(super) fn views(&self, descs: &[crate::ImageViewDesc]) -> Vec<crate::ImageView> {
+        let mut views = self.views.lock().unwrap();
+
+        descs
+            .iter()
+            .map(|desc| {
+                *views
+                    .entry(*desc)
+                    .or_insert_with(|| ImageView::new(&self.device, self, *desc))
+            })
+            .collect()
+    }
 }
 
 impl Drop for Image {
     fn drop(&mut self) {
-        for view in self.views.lock().unwrap().values() {
-            unsafe {
-                self.device.raw.destroy_image_view(view.raw, None)
-            }
-        }
+        let raw = self.raw;
+        let views = self.views
+            .get_mut()
+            .unwrap()
+            .values()
+            .map(|view| view.raw)
+            .collect::<Vec<_>>();
+        // Only do cleanup of the image if we actually own it
+        let allocation = self.allocation.take();
+        let aliased = self.aliased.is_some();
 
-        if self.allocation.is_some() {
-            // Only do cleanu if we actually own the image
-            self.device.allocator
-                .as_ref()
-                .unwrap()
-                .lock()
-                .unwrap()
-                .free(self.allocation.take().unwrap())
-                .expect("Failed to free buffer memory");
+        self.device.destroy_image_views(views);
+        self.device.defer_destroy(move |device| {
+            if let Some(allocation) = allocation {
+                device.free(allocation)
+                    .expect("Failed to free image memory");
 
-            unsafe {
-                self.device.raw.destroy_image(self.raw, None);
+                unsafe {
+                    device.raw.destroy_image(raw, None);
+                }
+            } else if aliased {
+                unsafe {
+                    device.raw.destroy_image(raw, None);
+                }
             }
-        }
+        });
+        // The aliased image is released after queueing this one, so its
+        // memory is freed after this image is destroyed
+    }
+}
+
+fn create_flags(desc: &ImageDesc) -> vk::ImageCreateFlags {
+    let mut flags = vk::ImageCreateFlags::empty();
+    if desc.mutable_format {
+        flags |= vk::ImageCreateFlags::MUTABLE_FORMAT;
+    }
+    if desc.cube_compatible {
+        flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
+    }
+    flags
+}
+
+fn create_raw(device: &super::DeviceInner, desc: &ImageDesc) -> Result<vk::Image> {
+    let image_create_info = vk::ImageCreateInfo::builder()
+        .image_type(desc.image_type)
+        .format(desc.format)
+        .extent(desc.extent)
+        .usage((&desc.usage).into())
+        .tiling(vk::ImageTiling::OPTIMAL) // TODO: Will this ever change?
+        .flags(create_flags(desc))
+        .mip_levels(desc.mip_levels)
+        .samples(desc.samples)
+        .array_layers(desc.array_layers)
+        .build();
+
+    unsafe {
+        device.raw.create_image(&image_create_info, None)
+            .map_err(super::resource_error)
     }
 }
 
@@ -150,7 +463,8 @@ impl ImageView {
                 .aspect_mask(desc.aspect_mask)
                 .base_mip_level(desc.base_mip_level)
                 .level_count(desc.level_count)
-                .layer_count(1)
+                .base_array_layer(desc.base_array_layer)
+                .layer_count(desc.layer_count)
                 .build()
             )
             .build();
@@ -160,12 +474,190 @@ impl ImageView {
                 .expect("Failed to create image view")
         };
 
+        let extent = mip_extent(image.desc.extent, desc.base_mip_level);
         Self {
             raw,
+            format: desc.format,
+            extent: vk::Extent2D {
+                width: extent.width,
+                height: extent.height,
+            },
         }
     }
 }
 
+impl std::ops::Deref for OwnedImageView {
+    type Target = ImageView;
+
+    fn deref(&self) -> &ImageView {
+        &self.view
+    }
+}
+
+impl Drop for OwnedImageView {
+    fn drop(&mut self) {
+        self.device.destroy_image_views(vec![self.view.raw]);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct SubresourceState {
+    layout: vk::ImageLayout,
+    access_mask: vk::AccessFlags,
+    stage_mask: vk::PipelineStageFlags,
+}
+
+/// Last known state of each subresource of an image
+struct LayoutTracker {
+    mip_levels: u32,
+    /// Indexed by array_layer * mip_levels + mip_level
+    states: Vec<SubresourceState>,
+}
+
+impl LayoutTracker {
+    fn new(mip_levels: u32, array_layers: u32, state: SubresourceState) -> Self {
+        Self {
+            mip_levels,
+            states: vec![state; (mip_levels * array_layers) as usize],
+        }
+    }
+
+    fn state(&self, mip_level: u32, array_layer: u32) -> Option<&SubresourceState> {
+        if mip_level >= self.mip_levels {
+            return None;
+        }
+        let index = array_layer as usize * self.mip_levels as usize + mip_level as usize;
+        self.states.get(index)
+    }
+
+    fn range_mut<'a>(
+        &'a mut self,
+        range: &crate::ImageSubresourceRange,
+    ) -> impl Iterator<Item = &'a mut SubresourceState> {
+        let mip_levels = self.mip_levels;
+        let array_layers = self.states.len() as u32 / mip_levels;
+
+        let level_count = if range.level_count == vk::REMAINING_MIP_LEVELS {
+            mip_levels - range.base_mip_level
+        } else {
+            range.level_count
+        };
+        let layer_count = if range.layer_count == vk::REMAINING_ARRAY_LAYERS {
+            array_layers - range.base_array_layer
+        } else {
+            range.layer_count
+        };
+        let levels = range.base_mip_level..range.base_mip_level + level_count;
+        let layers = range.base_array_layer..range.base_array_layer + layer_count;
+
+        self.states
+            .iter_mut()
+            .enumerate()
+            .filter(move |(index, _)| {
+                levels.contains(&(*index as u32 % mip_levels))
+                    && layers.contains(&(*index as u32 / mip_levels))
+            })
+            .map(|(_, state)| state)
+    }
+}
+
+/// Extent of a mip level of an image with the given extent
+pub(super) fn mip_extent(extent: vk::Extent3D, mip_level: u32) -> vk::Extent3D {
+    vk::Extent3D {
+        width: (extent.width >> mip_level).max(1),
+        height: (extent.height >> mip_level).max(1),
+        depth: (extent.depth >> mip_level).max(1),
+    }
+}
+
+/// Size in bytes of a texel, None for formats without fixed size texels
+/// such as block compressed formats
+pub(super) fn texel_size(format: vk::Format) -> Option<u32> {
+    let size = match format {
+        vk::Format::R8_UNORM
+        | vk::Format::R8_SNORM
+        | vk::Format::R8_UINT
+        | vk::Format::R8_SINT
+        | vk::Format::R8_SRGB => 1,
+        vk::Format::R8G8_UNORM
+        | vk::Format::R8G8_SNORM
+        | vk::Format::R8G8_UINT
+        | vk::Format::R8G8_SINT
+        | vk::Format::R8G8_SRGB
+        | vk::Format::R16_UNORM
+        | vk::Format::R16_SNORM
+        | vk::Format::R16_UINT
+        | vk::Format::R16_SINT
+        | vk::Format::R16_SFLOAT => 2,
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SNORM
+        | vk::Format::R8G8B8A8_UINT
+        | vk::Format::R8G8B8A8_SINT
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::A2B10G10R10_UNORM_PACK32
+        | vk::Format::B10G11R11_UFLOAT_PACK32
+        | vk::Format::R16G16_UNORM
+        | vk::Format::R16G16_SFLOAT
+        | vk::Format::R32_UINT
+        | vk::Format::R32_SINT
+        | vk::Format::R32_SFLOAT => 4,
+        vk::Format::R16G16B16A16_UNORM
+        | vk::Format::R16G16B16A16_SFLOAT
+        | vk::Format::R32G32_UINT
+        | vk::Format::R32G32_SINT
+        | vk::Format::R32G32_SFLOAT => 8,
+        vk::Format::R32G32B32_UINT
+        | vk::Format::R32G32B32_SINT
+        | vk::Format::R32G32B32_SFLOAT => 12,
+        vk::Format::R32G32B32A32_UINT
+        | vk::Format::R32G32B32A32_SINT
+        | vk::Format::R32G32B32A32_SFLOAT => 16,
+        _ => return None,
+    };
+    Some(size)
+}
+
+/// Aspects covered by a whole image of the format
+pub(super) fn aspect_mask(format: vk::Format) -> vk::ImageAspectFlags {
+    match format {
+        vk::Format::D16_UNORM
+        | vk::Format::X8_D24_UNORM_PACK32
+        | vk::Format::D32_SFLOAT => vk::ImageAspectFlags::DEPTH,
+        vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+        vk::Format::D16_UNORM_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT_S8_UINT => {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        }
+        _ => vk::ImageAspectFlags::COLOR,
+    }
+}
+
+impl super::DebugName for Image {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::IMAGE, self.raw.as_raw())
+    }
+}
+
+impl super::DebugName for ImageView {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::IMAGE_VIEW, self.raw.as_raw())
+    }
+}
+
+impl From<&crate::ImageSubresourceRange> for vk::ImageSubresourceRange {
+    fn from(range: &crate::ImageSubresourceRange) -> Self {
+        vk::ImageSubresourceRange::builder()
+            .aspect_mask(range.aspect_mask)
+            .base_mip_level(range.base_mip_level)
+            .level_count(range.level_count)
+            .base_array_layer(range.base_array_layer)
+            .layer_count(range.layer_count)
+            .build()
+    }
+}
 
 impl From<&crate::ImageUsage> for vk::ImageUsageFlags {
     fn from(usage: &crate::ImageUsage) -> Self {
@@ -173,15 +665,20 @@ impl From<&crate::ImageUsage> for vk::ImageUsageFlags {
 
         if usage.contains(crate::ImageUsage::TRANSFER_SRC) {
             flags |= vk::ImageUsageFlags::TRANSFER_SRC;
-        } else if usage.contains(crate::ImageUsage::TRANSFER_DST) {
+        }
+        if usage.contains(crate::ImageUsage::TRANSFER_DST) {
             flags |= vk::ImageUsageFlags::TRANSFER_DST;
-        } else if usage.contains(crate::ImageUsage::SAMPLED) {
+        }
+        if usage.contains(crate::ImageUsage::SAMPLED) {
             flags |= vk::ImageUsageFlags::SAMPLED;
-        } else if usage.contains(crate::ImageUsage::STORAGE) {
+        }
+        if usage.contains(crate::ImageUsage::STORAGE) {
             flags |= vk::ImageUsageFlags::STORAGE;
-        } else if usage.contains(crate::ImageUsage::COLOR_ATTACHMENT) {
+        }
+        if usage.contains(crate::ImageUsage::COLOR_ATTACHMENT) {
             flags |= vk::ImageUsageFlags::COLOR_ATTACHMENT;
-        } else if usage.contains(crate::ImageUsage::DEPTH_STENCIL_ATTACHMENT) {
+        }
+        if usage.contains(crate::ImageUsage::DEPTH_STENCIL_ATTACHMENT) {
             flags |= vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT;
         }
 
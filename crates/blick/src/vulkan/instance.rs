@@ -7,9 +7,12 @@ use ash::vk::{
     KhrGetPhysicalDeviceProperties2Fn, KhrPortabilityEnumerationFn,
 };
 
+use std::any::Any;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_void};
 use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub struct Instance {
     pub(super) entry: ash::Entry,
@@ -17,6 +20,36 @@ pub struct Instance {
 
     pub(super) debug_utils: Option<ext::DebugUtils>,
     debug_messenger: Option<vk::DebugUtilsMessengerEXT>,
+    /// Boxed to get a stable pointer for p_user_data, has to outlive
+    /// debug_messenger
+    debug_callback_data: Box<DebugCallbackData>,
+}
+
+/// Passed to vulkan_debug_utils_callback through p_user_data
+struct DebugCallbackData {
+    callback: Option<crate::ValidationCallback>,
+    break_on_error: bool,
+    /// Set together with pending_panic, checked without locking
+    has_pending_panic: AtomicBool,
+    /// Panics can't unwind out of the callback, they are raised by the next
+    /// call to Instance::check_validation instead. Only the first is kept.
+    pending_panic: Mutex<Option<PendingPanic>>,
+}
+
+enum PendingPanic {
+    ValidationError(String),
+    /// Panic of the user callback
+    Callback(Box<dyn Any + Send>),
+}
+
+impl DebugCallbackData {
+    fn set_pending_panic(&self, pending: PendingPanic) {
+        let mut pending_panic = self.pending_panic.lock().unwrap();
+        if pending_panic.is_none() {
+            *pending_panic = Some(pending);
+            self.has_pending_panic.store(true, Ordering::Release);
+        }
+    }
 }
 
 pub struct PhysicalDevice {
@@ -27,12 +60,62 @@ pub struct PhysicalDevice {
     pub(super) queue_families: Vec<super::QueueFamily>,
 }
 
+impl PhysicalDevice {
+    pub fn name(&self) -> String {
+        super::vk_to_string(&self.properties.device_name)
+    }
+    pub fn device_type(&self) -> crate::PhysicalDeviceType {
+        self.properties.device_type
+    }
+    pub fn vendor_id(&self) -> u32 {
+        self.properties.vendor_id
+    }
+    pub fn device_id(&self) -> u32 {
+        self.properties.device_id
+    }
+    pub fn info(&self) -> crate::DeviceInfo {
+        let limits = &self.properties.limits;
+
+        crate::DeviceInfo {
+            name: self.name(),
+            device_type: self.properties.device_type,
+            vendor_id: self.properties.vendor_id,
+            device_id: self.properties.device_id,
+            api_version: (
+                vk::api_version_major(self.properties.api_version),
+                vk::api_version_minor(self.properties.api_version),
+                vk::api_version_patch(self.properties.api_version),
+            ),
+            driver_version: self.properties.driver_version,
+            limits: crate::DeviceLimits {
+                max_image_dimension_1d: limits.max_image_dimension1_d,
+                max_image_dimension_2d: limits.max_image_dimension2_d,
+                max_image_dimension_3d: limits.max_image_dimension3_d,
+                max_image_dimension_cube: limits.max_image_dimension_cube,
+                max_image_array_layers: limits.max_image_array_layers,
+                max_push_constants_size: limits.max_push_constants_size,
+                max_bound_descriptor_sets: limits.max_bound_descriptor_sets,
+                max_uniform_buffer_range: limits.max_uniform_buffer_range,
+                max_storage_buffer_range: limits.max_storage_buffer_range,
+                max_compute_work_group_count: limits.max_compute_work_group_count,
+                max_compute_work_group_size: limits.max_compute_work_group_size,
+                max_compute_work_group_invocations: limits.max_compute_work_group_invocations,
+                max_color_attachments: limits.max_color_attachments,
+                min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment,
+                min_storage_buffer_offset_alignment: limits.min_storage_buffer_offset_alignment,
+                timestamp_period: limits.timestamp_period,
+            },
+        }
+    }
+}
+
 
 impl Instance {
     pub fn new(
         required_extensions: &'static [*const c_char],
-        debugging: bool,
+        config: &mut crate::BackendConfig,
     ) -> Result<Self> {
+        let debugging = config.debugging;
         let entry = unsafe { ash::Entry::load()? };
 
         let mut extension_names = required_extensions.to_vec();
@@ -50,12 +133,33 @@ impl Instance {
             layer_names.push(CString::new("VK_LAYER_KHRONOS_validation").unwrap());
         }
 
+        let validation_features = if debugging {
+            enabled_validation_features(config.validation_features)
+        } else {
+            Vec::new()
+        };
+        if !validation_features.is_empty() {
+            extension_names.push(vk::ExtValidationFeaturesFn::name().as_ptr());
+        }
+        let mut validation_features_info = vk::ValidationFeaturesEXT::builder()
+            .enabled_validation_features(&validation_features);
+
         let layer_names = layer_names
             .iter()
             .map(|layer| layer.as_ptr())
             .collect::<Vec<_>>();
 
+        let app_name = CString::new(config.app_name.as_str())?;
+        let engine_name = CString::new(config.engine_name.as_str())?;
+        let make_version = |(major, minor, patch)| {
+            vk::make_api_version(0, major, minor, patch)
+        };
+
         let application_info = vk::ApplicationInfo::builder()
+            .application_name(&app_name)
+            .application_version(make_version(config.app_version))
+            .engine_name(&engine_name)
+            .engine_version(make_version(config.engine_version))
             .api_version(vk::make_api_version(0, 1, 2, 0))
             .build();
 
@@ -65,19 +169,33 @@ impl Instance {
             vk::InstanceCreateFlags::default()
         };
 
-        let instance_create_info = vk::InstanceCreateInfo::builder()
+        let mut instance_create_info = vk::InstanceCreateInfo::builder()
             .application_info(&application_info)
             .enabled_layer_names(&layer_names)
             .enabled_extension_names(&extension_names)
-            .flags(create_flags)
-            .build();
+            .flags(create_flags);
+        if !validation_features.is_empty() {
+            instance_create_info = instance_create_info
+                .push_next(&mut validation_features_info);
+        }
 
         let instance = unsafe {
             entry.create_instance(&instance_create_info, None)?
         };
 
+        let debug_callback_data = Box::new(DebugCallbackData {
+            callback: config.validation_callback.take(),
+            break_on_error: config.break_on_validation_error,
+            has_pending_panic: AtomicBool::new(false),
+            pending_panic: Mutex::new(None),
+        });
         let (debug_utils, debug_messenger) = if debugging {
-            let (l, m) = setup_debug_utils(&entry, &instance);
+            let (l, m) = setup_debug_utils(
+                &entry,
+                &instance,
+                config.validation_severity,
+                &debug_callback_data,
+            );
             (Some(l), Some(m))
         } else {
             (None, None)
@@ -89,10 +207,29 @@ impl Instance {
                 raw: instance,
                 debug_utils,
                 debug_messenger,
+                debug_callback_data,
             },
         )
     }
 
+    /// Raises a panic deferred by the debug callback, i.e. a validation error
+    /// with BackendConfig::break_on_validation_error or a panicking
+    /// validation callback. Called by Device on submits and waits.
+    pub(super) fn check_validation(&self) {
+        let data = &self.debug_callback_data;
+        if !data.has_pending_panic.load(Ordering::Acquire) {
+            return;
+        }
+        data.has_pending_panic.store(false, Ordering::Release);
+        match data.pending_panic.lock().unwrap().take() {
+            Some(PendingPanic::ValidationError(message)) => {
+                panic!("Validation error: {}", message);
+            },
+            Some(PendingPanic::Callback(payload)) => std::panic::resume_unwind(payload),
+            None => {},
+        }
+    }
+
     pub fn enumerate_physical_devices(&self) -> Result<Vec<PhysicalDevice>> {
         Ok(unsafe {
             self.raw.enumerate_physical_devices()?
@@ -140,12 +277,32 @@ impl Drop for Instance {
     }
 }
 
+fn enabled_validation_features(
+    features: crate::ValidationFeatures,
+) -> Vec<vk::ValidationFeatureEnableEXT> {
+    use crate::ValidationFeatures;
+
+    let mut enabled = Vec::new();
+    if features.contains(ValidationFeatures::GPU_ASSISTED) {
+        enabled.push(vk::ValidationFeatureEnableEXT::GPU_ASSISTED);
+        // Lets the layer use a descriptor set slot of its own
+        enabled.push(vk::ValidationFeatureEnableEXT::GPU_ASSISTED_RESERVE_BINDING_SLOT);
+    }
+    if features.contains(ValidationFeatures::SYNCHRONIZATION) {
+        enabled.push(vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION);
+    }
+    if features.contains(ValidationFeatures::BEST_PRACTICES) {
+        enabled.push(vk::ValidationFeatureEnableEXT::BEST_PRACTICES);
+    }
+    enabled
+}
+
 /// Callback function used in Debug Utils.
 unsafe extern "system" fn vulkan_debug_utils_callback(
     message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
     message_type: vk::DebugUtilsMessageTypeFlagsEXT,
     p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-    _p_user_data: *mut c_void,
+    p_user_data: *mut c_void,
 ) -> vk::Bool32 {
     let severity = match message_severity {
         vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => "[Verbose]",
@@ -160,19 +317,44 @@ unsafe extern "system" fn vulkan_debug_utils_callback(
         vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION => "[Validation]",
         _ => "[Unknown]",
     };
-    let message = CStr::from_ptr((*p_callback_data).p_message);
-    log::debug!("[Debug]{}{}{:?}", severity, types, message);
+    let message = CStr::from_ptr((*p_callback_data).p_message).to_string_lossy();
+    let level = match message_severity {
+        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => log::Level::Error,
+        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => log::Level::Warn,
+        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => log::Level::Debug,
+        _ => log::Level::Trace,
+    };
+    log::log!(level, "{}{}{}", severity, types, message);
+
+    // Unwinding through the driver would abort, panics are deferred to
+    // Instance::check_validation
+    let data = &*(p_user_data as *const DebugCallbackData);
+    if let Some(callback) = &data.callback {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            callback(message_severity, &message)
+        }));
+        if let Err(payload) = result {
+            data.set_pending_panic(PendingPanic::Callback(payload));
+        }
+    }
+    if data.break_on_error
+        && message_severity == vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
+    {
+        data.set_pending_panic(PendingPanic::ValidationError(message.into_owned()));
+    }
 
     vk::FALSE
 }
 
-pub fn setup_debug_utils(
+fn setup_debug_utils(
     entry: &ash::Entry,
     instance: &ash::Instance,
+    severity: crate::ValidationSeverity,
+    data: &DebugCallbackData,
 ) -> (ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT) {
     let debug_utils_loader = ash::extensions::ext::DebugUtils::new(entry, instance);
 
-    let messenger_ci = populate_debug_messenger_create_info();
+    let messenger_ci = populate_debug_messenger_create_info(severity, data);
 
     let utils_messenger = unsafe {
         debug_utils_loader
@@ -183,20 +365,20 @@ pub fn setup_debug_utils(
     (debug_utils_loader, utils_messenger)
 }
 
-pub fn populate_debug_messenger_create_info() -> vk::DebugUtilsMessengerCreateInfoEXT {
+fn populate_debug_messenger_create_info(
+    severity: crate::ValidationSeverity,
+    data: &DebugCallbackData,
+) -> vk::DebugUtilsMessengerCreateInfoEXT {
     vk::DebugUtilsMessengerCreateInfoEXT {
         s_type: vk::StructureType::DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
         p_next: ptr::null(),
         flags: vk::DebugUtilsMessengerCreateFlagsEXT::empty(),
-        message_severity: vk::DebugUtilsMessageSeverityFlagsEXT::WARNING |
-            // vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE |
-            vk::DebugUtilsMessageSeverityFlagsEXT::INFO |
-            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+        message_severity: severity,
         message_type: vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
             | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
             | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
         pfn_user_callback: Some(vulkan_debug_utils_callback),
-        p_user_data: ptr::null_mut(),
+        p_user_data: data as *const DebugCallbackData as *mut c_void,
     }
 }
 
@@ -1,36 +1,45 @@
 mod backend;
 mod buffer;
 mod command;
+mod deletion;
 mod descriptor;
 mod device;
 mod framebuffer;
 mod image;
 mod instance;
+mod query;
 mod render_pass;
+mod renderdoc;
+mod sampler;
 mod shader;
+mod staging;
 mod surface;
 mod swapchain;
 mod sync;
 
-pub use backend::Backend;
-pub use buffer::Buffer;
-pub use command::{CommandBuffer, ComputePassEncoder, RenderPassEncoder};
+pub use backend::{Backend, Frame, SurfaceContext, SwapchainRecreatedCallback};
+pub use buffer::{Buffer, BufferView};
+pub use command::{CommandBuffer, ComputePassEncoder, DebugScopeGuard, RenderPassEncoder};
 pub use descriptor::{DescriptorSet, DescriptorSetLayout};
 pub use device::{Device, DeviceInner};
 pub use framebuffer::Framebuffer;
-pub use image::{Image, ImageView};
+pub use image::{Image, ImageView, OwnedImageView};
 pub use instance::Instance;
 pub use instance::PhysicalDevice;
+pub use query::QueryPool;
 pub use render_pass::RenderPass;
-pub use shader::{ComputePipeline, GraphicsPipeline};
+pub use sampler::Sampler;
+pub use shader::{ComputePipeline, GraphicsPipeline, PendingPipeline};
+pub use staging::{StagingBelt, StagingSlice};
 pub use surface::Surface;
 pub use swapchain::{Swapchain, SwapchainDesc};
-pub use sync::{Fence, Semaphore};
+pub use sync::{Event, Fence, Semaphore};
 
 use ash::vk;
 
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
+use std::sync::Arc;
 
 
 #[derive(Copy, Clone)]
@@ -44,9 +53,43 @@ pub struct Queue {
     pub family: QueueFamily,
 }
 
+/// Objects that can be given a debug name through Device::set_debug_name
+pub trait DebugName {
+    fn debug_handle(&self) -> (vk::ObjectType, u64);
+}
+
+impl<T: DebugName> DebugName for Arc<T> {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (**self).debug_handle()
+    }
+}
+
 /// Converts a C string (as provided by vulkan) to a Rust string.
 pub fn vk_to_string(raw_string : &[c_char]) -> String {
     unsafe {
         CStr::from_ptr(raw_string.as_ptr())
     }.to_str().expect("Failed to convert string").to_owned()
 }
+
+/// Translates out of memory errors to ResourceError so callers can react
+pub(super) fn resource_error(result: vk::Result) -> anyhow::Error {
+    match result {
+        vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => crate::ResourceError::OutOfDeviceMemory.into(),
+        vk::Result::ERROR_OUT_OF_HOST_MEMORY => crate::ResourceError::OutOfHostMemory.into(),
+        err => err.into(),
+    }
+}
+
+pub(super) fn allocation_error(err: gpu_allocator::AllocationError) -> anyhow::Error {
+    match err {
+        gpu_allocator::AllocationError::OutOfMemory => {
+            crate::ResourceError::OutOfDeviceMemory.into()
+        },
+        err => err.into(),
+    }
+}
+
+/// For debug names and labels, drops interior NULs rather than failing
+pub(super) fn debug_cstring(name: &str) -> CString {
+    CString::new(name.replace('\0', "")).unwrap()
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use ash::vk;
+use ash::vk::Handle;
+
+use std::sync::Arc;
+
+pub struct QueryPool {
+    pub(super) raw: vk::QueryPool,
+    ty: crate::QueryType,
+    count: u32,
+    device: Arc<super::DeviceInner>,
+}
+
+impl QueryPool {
+    pub(super) fn new(
+        device: &Arc<super::DeviceInner>,
+        desc: crate::QueryPoolDesc,
+    ) -> Result<Self> {
+        let (query_type, pipeline_statistics) = match desc.ty {
+            crate::QueryType::Occlusion => (
+                vk::QueryType::OCCLUSION,
+                vk::QueryPipelineStatisticFlags::empty(),
+            ),
+            crate::QueryType::PipelineStatistics(flags) => (
+                vk::QueryType::PIPELINE_STATISTICS,
+                flags,
+            ),
+        };
+
+        let create_info = vk::QueryPoolCreateInfo::builder()
+            .query_type(query_type)
+            .query_count(desc.count)
+            .pipeline_statistics(pipeline_statistics)
+            .build();
+
+        let raw = unsafe {
+            device.raw.create_query_pool(&create_info, None)?
+        };
+
+        Ok(Self {
+            raw,
+            ty: desc.ty,
+            count: desc.count,
+            device: device.clone(),
+        })
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Number of u64 values written per query
+    pub fn values_per_query(&self) -> usize {
+        match self.ty {
+            crate::QueryType::Occlusion => 1,
+            crate::QueryType::PipelineStatistics(flags) => {
+                flags.as_raw().count_ones() as usize
+            },
+        }
+    }
+
+    /// Waits for the queries to be available, returns values_per_query values
+    /// for each query. Pipeline statistics are ordered by their flag bits.
+    pub(super) fn results(&self, first_query: u32, query_count: u32) -> Result<Vec<u64>> {
+        let in_range = first_query
+            .checked_add(query_count)
+            .is_some_and(|end| end <= self.count);
+        if !in_range {
+            anyhow::bail!(
+                "{} queries at {} out of range for pool of {} queries",
+                query_count,
+                first_query,
+                self.count,
+            );
+        }
+
+        let stride = self.values_per_query();
+        let mut data = vec![0u64; stride * query_count as usize];
+
+        unsafe {
+            // ash's wrapper derives the stride from the element type, which
+            // doesn't work for a variable number of statistics
+            (self.device.raw.fp_v1_0().get_query_pool_results)(
+                self.device.raw.handle(),
+                self.raw,
+                first_query,
+                query_count,
+                std::mem::size_of_val(data.as_slice()),
+                data.as_mut_ptr().cast(),
+                (stride * std::mem::size_of::<u64>()) as vk::DeviceSize,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+            )
+                .result()?;
+        }
+
+        Ok(data)
+    }
+}
+
+impl Drop for QueryPool {
+    fn drop(&mut self) {
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_query_pool(raw, None);
+        });
+    }
+}
+
+impl super::DebugName for QueryPool {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::QUERY_POOL, self.raw.as_raw())
+    }
+}
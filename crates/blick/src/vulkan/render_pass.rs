@@ -1,29 +1,58 @@
-use crate::ColorAttachmentDesc;
+use crate::{ColorAttachmentDesc, DepthStencilAttachmentDesc, SubpassDependency};
 
+use anyhow::{anyhow, bail, Result};
 use ash::vk;
+use ash::vk::Handle;
 
 use lru::LruCache;
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 
+#[derive(Clone, Default, Eq, Hash, PartialEq)]
+pub struct SubpassKey {
+    pub color_attachments: Vec<Option<u32>>,
+    pub input_attachments: Vec<Option<u32>>,
+    pub resolve_attachments: Vec<Option<u32>>,
+    pub depth_stencil_attachment: bool,
+}
+
 #[derive(Clone, Default, Eq, Hash, PartialEq)]
 pub struct RenderPassKey {
     pub color_attachments: Vec<Option<ColorAttachmentDesc>>,
+    pub samples: vk::SampleCountFlags,
+    pub resolve_attachments: Vec<Option<ColorAttachmentDesc>>,
+    pub subpasses: Vec<SubpassKey>,
+    pub dependencies: Vec<SubpassDependency>,
+    pub depth_stencil_attachment: Option<DepthStencilAttachmentDesc>,
 }
 
 struct RenderPassInner {
     raw: vk::RenderPass,
     num_attachments: u32,
+    samples: vk::SampleCountFlags,
+    /// Per subpass, includes unused attachment slots
+    color_attachment_counts: Vec<u32>,
+    /// Index of the depth stencil attachment
+    depth_stencil_attachment: Option<u32>,
+    /// Index of the attachment of each slot in RenderPassDesc::color_attachments
+    color_attachment_slots: Vec<Option<u32>>,
+    /// Dependencies with src_subpass == dst_subpass, allowing barriers within
+    /// that subpass
+    self_dependencies: Vec<crate::SubpassDependency>,
     device: Arc<super::DeviceInner>,
 }
 
 
 pub struct RenderPassCache {
     cache: Mutex<LruCache<RenderPassKey, Arc<RenderPassInner>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
     device: Arc<super::DeviceInner>,
 }
 
+#[derive(Clone)]
 pub struct RenderPass {
     inner: Arc<RenderPassInner>,
 }
@@ -35,100 +64,314 @@ impl RenderPass {
     pub fn num_attachments(&self) -> u32 {
         self.inner.num_attachments
     }
+    pub fn samples(&self) -> vk::SampleCountFlags {
+        self.inner.samples
+    }
+    pub fn subpass_count(&self) -> u32 {
+        self.inner.color_attachment_counts.len() as u32
+    }
+    /// None if the render pass has no such subpass
+    pub fn color_attachment_count(&self, subpass: u32) -> Option<u32> {
+        self.inner.color_attachment_counts.get(subpass as usize).copied()
+    }
+    /// Index of the depth stencil attachment among the framebuffer attachments
+    pub fn depth_stencil_attachment(&self) -> Option<u32> {
+        self.inner.depth_stencil_attachment
+    }
+    /// Index among the framebuffer attachments of a slot in
+    /// RenderPassDesc::color_attachments, None for empty slots
+    pub fn color_attachment_index(&self, slot: usize) -> Option<u32> {
+        self.inner.color_attachment_slots.get(slot).copied().flatten()
+    }
+    /// Self-dependencies of the subpass, see RenderPassEncoder::barrier
+    pub fn self_dependencies(
+        &self,
+        subpass: u32,
+    ) -> impl Iterator<Item = &crate::SubpassDependency> {
+        self.inner.self_dependencies
+            .iter()
+            .filter(move |dependency| dependency.src_subpass == subpass)
+    }
+}
+
+impl super::DebugName for RenderPass {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::RENDER_PASS, self.raw().as_raw())
+    }
 }
 
 impl RenderPassCache {
     /// device: Device for destroying render passes
-    pub fn new(size: usize, device: &Arc<super::DeviceInner>) -> Self {
+    pub fn new(size: NonZeroUsize, device: &Arc<super::DeviceInner>) -> Self {
         Self {
-            cache: Mutex::new(LruCache::new(
-                NonZeroUsize::new(size).unwrap()
-            )),
+            cache: Mutex::new(LruCache::new(size)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
             device: device.clone(),
         }
     }
 
+    /// (hits, misses) of get_or_create
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
     pub fn get_or_create(
         &self,
         desc: crate::RenderPassDesc<'_>
-    ) -> RenderPass {
+    ) -> Result<RenderPass> {
         let key = RenderPassKey::from(&desc);
         
-        RenderPass {
-            inner:
-                self.cache.lock().unwrap().get_or_insert(
-                    key,
-                    || Arc::new(RenderPassInner::new(&self.device, desc))
-                ).clone()
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(inner) = cache.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(RenderPass { inner: inner.clone() });
         }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let inner = Arc::new(RenderPassInner::new(&self.device, desc)?);
+        cache.put(key, inner.clone());
+
+        Ok(RenderPass { inner })
     }
 }
 
+/// Attachment references of a subpass
+struct SubpassRefs {
+    color: Vec<vk::AttachmentReference>,
+    depth_stencil: Option<vk::AttachmentReference>,
+    input: Vec<vk::AttachmentReference>,
+    resolve: Vec<vk::AttachmentReference>,
+}
+
 impl RenderPassInner {
-    fn new(device: &Arc<super::DeviceInner>, desc: crate::RenderPassDesc<'_>) -> Self {
+    fn new(device: &Arc<super::DeviceInner>, desc: crate::RenderPassDesc<'_>) -> Result<Self> {
         let mut attachments = Vec::new();
-        let mut color_refs = Vec::new();
-
-        for color_attachment in desc.color_attachments.iter() {
-            if let Some(color_attachment) = color_attachment {
-                attachments.push(
-                    vk::AttachmentDescription::builder()
-                        .format(color_attachment.format)
-                        // TODO:
-                        .samples(vk::SampleCountFlags::TYPE_1)
-                        .load_op(vk::AttachmentLoadOp::CLEAR)
-                        .store_op(vk::AttachmentStoreOp::STORE)
-                        // OK since we clear the image anyway, have to change if we don't
-                        .initial_layout(vk::ImageLayout::UNDEFINED)
-                        .final_layout(color_attachment.layout)
-                        .build()
-                );
-                
-                color_refs.push(
-                    vk::AttachmentReference::builder()
-                        .attachment(attachments.len() as u32 - 1)
-                        .layout(color_attachment.layout)
-                        .build()
-                );
-            } else {
-                color_refs.push(
-                    vk::AttachmentReference::builder()
-                        .attachment(vk::ATTACHMENT_UNUSED)
-                        .layout(vk::ImageLayout::UNDEFINED)
-                        .build()
-                );
+        // Map slots in desc.color_attachments and desc.resolve_attachments to
+        // indices in attachments
+        let slots = attachment_slots(desc.color_attachments, 0);
+        for color_attachment in desc.color_attachments.iter().flatten() {
+            attachments.push(
+                vk::AttachmentDescription::builder()
+                    .format(color_attachment.format)
+                    .samples(desc.samples)
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    // OK since we clear the image anyway, have to change if we don't
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .final_layout(color_attachment.layout)
+                    .build()
+            );
+        }
+
+        let resolve_slots = attachment_slots(
+            desc.resolve_attachments,
+            attachments.len() as u32,
+        );
+        for resolve_attachment in desc.resolve_attachments.iter().flatten() {
+            attachments.push(
+                vk::AttachmentDescription::builder()
+                    .format(resolve_attachment.format)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    // Fully overwritten by the resolve
+                    .load_op(vk::AttachmentLoadOp::DONT_CARE)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .final_layout(resolve_attachment.layout)
+                    .build()
+            );
+        }
+
+        let depth_stencil_slot = desc.depth_stencil_attachment.map(|attachment| {
+            attachments.push(
+                vk::AttachmentDescription::builder()
+                    .format(attachment.format)
+                    .samples(desc.samples)
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .stencil_load_op(vk::AttachmentLoadOp::CLEAR)
+                    .stencil_store_op(vk::AttachmentStoreOp::STORE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .final_layout(attachment.layout)
+                    .build()
+            );
+            attachments.len() as u32 - 1
+        });
+
+        let attachment_ref = |attachment: Option<u32>, layout: vk::ImageLayout| {
+            match attachment {
+                Some(attachment) => vk::AttachmentReference::builder()
+                    .attachment(attachment)
+                    .layout(layout)
+                    .build(),
+                None => vk::AttachmentReference::builder()
+                    .attachment(vk::ATTACHMENT_UNUSED)
+                    .layout(vk::ImageLayout::UNDEFINED)
+                    .build(),
+            }
+        };
+        let refs_to = |slots: &[Option<u32>], indices: &[Option<u32>], layout| {
+            indices
+                .iter()
+                .map(|index| match index {
+                    Some(index) => match slots.get(*index as usize) {
+                        Some(slot) => Ok(attachment_ref(*slot, layout)),
+                        None => Err(anyhow!("Attachment index {} out of range", index)),
+                    },
+                    None => Ok(attachment_ref(None, layout)),
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        let depth_stencil_ref = depth_stencil_slot.map(|slot| attachment_ref(
+            Some(slot),
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        ));
+
+        let refs = if desc.subpasses.is_empty() {
+            vec![SubpassRefs {
+                color: desc.color_attachments
+                    .iter()
+                    .zip(slots.iter())
+                    .map(|(attachment, slot)| attachment_ref(
+                        *slot,
+                        attachment.map_or(vk::ImageLayout::UNDEFINED, |a| a.layout)
+                    ))
+                    .collect(),
+                depth_stencil: depth_stencil_ref,
+                input: Vec::new(),
+                resolve: desc.resolve_attachments
+                    .iter()
+                    .zip(resolve_slots.iter())
+                    .map(|(attachment, slot)| attachment_ref(
+                        *slot,
+                        attachment.map_or(vk::ImageLayout::UNDEFINED, |a| a.layout)
+                    ))
+                    .collect(),
+            }]
+        } else {
+            if depth_stencil_ref.is_none()
+                && desc.subpasses.iter().any(|subpass| subpass.depth_stencil_attachment)
+            {
+                bail!("Subpass uses a depth stencil attachment the render pass doesn't have");
             }
+
+            desc.subpasses
+                .iter()
+                .map(|subpass| Ok(SubpassRefs {
+                    color: refs_to(
+                        &slots,
+                        subpass.color_attachments,
+                        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+                    )?,
+                    depth_stencil: depth_stencil_ref
+                        .filter(|_| subpass.depth_stencil_attachment),
+                    input: refs_to(
+                        &slots,
+                        subpass.input_attachments,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+                    )?,
+                    resolve: refs_to(
+                        &resolve_slots,
+                        subpass.resolve_attachments,
+                        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+                    )?,
+                }))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        if refs.iter().any(|refs| {
+            !refs.resolve.is_empty() && refs.resolve.len() != refs.color.len()
+        }) {
+            bail!("Resolve attachments must match the number of color attachments");
         }
 
-        let subpass = vk::SubpassDescription::builder()
-            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-            .color_attachments(&color_refs)
-            .build();
+        let subpasses = refs
+            .iter()
+            .map(|refs| {
+                let mut subpass = vk::SubpassDescription::builder()
+                    .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+                    .color_attachments(&refs.color)
+                    .input_attachments(&refs.input);
+                // Shares count with color attachments, leave null if unused
+                if !refs.resolve.is_empty() {
+                    subpass = subpass.resolve_attachments(&refs.resolve);
+                }
+                if let Some(depth_stencil) = &refs.depth_stencil {
+                    subpass = subpass.depth_stencil_attachment(depth_stencil);
+                }
+                subpass.build()
+            })
+            .collect::<Vec<_>>();
+
+        let dependencies = desc.dependencies
+            .iter()
+            .map(|dependency| {
+                vk::SubpassDependency::builder()
+                    .src_subpass(dependency.src_subpass)
+                    .dst_subpass(dependency.dst_subpass)
+                    .src_stage_mask(dependency.src_stage_mask)
+                    .dst_stage_mask(dependency.dst_stage_mask)
+                    .src_access_mask(dependency.src_access_mask)
+                    .dst_access_mask(dependency.dst_access_mask)
+                    .dependency_flags(dependency.dependency_flags)
+                    .build()
+            })
+            .collect::<Vec<_>>();
 
         let render_pass_create_info = vk::RenderPassCreateInfo::builder()
             .attachments(&attachments)
-            .subpasses(&[subpass])
+            .subpasses(&subpasses)
+            .dependencies(&dependencies)
             .build();
 
         let raw = unsafe {
             device.raw
-                .create_render_pass(&render_pass_create_info, None)
-                .expect("Failed to create render pass")
+                .create_render_pass(&render_pass_create_info, None)?
         };
 
-        Self {
+        Ok(Self {
             raw,
             num_attachments: attachments.len() as u32,
+            samples: desc.samples,
+            color_attachment_counts: refs
+                .iter()
+                .map(|refs| refs.color.len() as u32)
+                .collect(),
+            depth_stencil_attachment: depth_stencil_slot,
+            color_attachment_slots: slots,
+            self_dependencies: desc.dependencies
+                .iter()
+                .filter(|dependency| dependency.src_subpass == dependency.dst_subpass)
+                .copied()
+                .collect(),
             device: device.clone(),
-        }
+        })
     }
 }
 
+/// Maps slots of an attachment list to indices of their attachment
+/// descriptions, starting at first_index. Empty slots get no description.
+fn attachment_slots<T>(slots: &[Option<T>], first_index: u32) -> Vec<Option<u32>> {
+    let mut index = first_index;
+    slots
+        .iter()
+        .map(|slot| slot.as_ref().map(|_| {
+            index += 1;
+            index - 1
+        }))
+        .collect()
+}
+
 impl Drop for RenderPassInner {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_render_pass(self.raw, None);
-        }
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_render_pass(raw, None);
+        });
     }
 }
 
@@ -143,8 +386,35 @@ impl<'a> From<&crate::RenderPassDesc<'a>> for RenderPassKey {
             .for_each(|attachment| {
                 key.color_attachments.push(*attachment);
             });
+
+        key.samples = desc.samples;
+        key.resolve_attachments = desc.resolve_attachments.to_vec();
+        key.subpasses = desc.subpasses
+            .iter()
+            .map(|subpass| SubpassKey {
+                color_attachments: subpass.color_attachments.to_vec(),
+                input_attachments: subpass.input_attachments.to_vec(),
+                resolve_attachments: subpass.resolve_attachments.to_vec(),
+                depth_stencil_attachment: subpass.depth_stencil_attachment,
+            })
+            .collect();
+        key.dependencies = desc.dependencies.to_vec();
+        key.depth_stencil_attachment = desc.depth_stencil_attachment;
         
         key
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::attachment_slots;
+
+    #[test]
+    fn attachment_slots_skip_empty() {
+        assert_eq!(attachment_slots(&[None, Some(())], 0), [None, Some(0)]);
+        assert_eq!(attachment_slots(&[Some(()), None, Some(())], 0), [Some(0), None, Some(1)]);
+        assert_eq!(attachment_slots(&[Some(()), Some(())], 0), [Some(0), Some(1)]);
+        // Resolve attachments follow the color attachments
+        assert_eq!(attachment_slots(&[Some(()), None, Some(())], 2), [Some(2), None, Some(3)]);
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::Result;
+
+use std::os::raw::{c_int, c_void};
+
+/// eRENDERDOC_API_Version_1_1_2
+const API_VERSION: c_int = 10102;
+
+type GetApiFn = unsafe extern "C" fn(version: c_int, out_api: *mut *mut c_void) -> c_int;
+type FrameCaptureFn = unsafe extern "C" fn(device: *mut c_void, window: *mut c_void);
+type EndFrameCaptureFn = unsafe extern "C" fn(device: *mut c_void, window: *mut c_void) -> u32;
+
+/// Leading part of RENDERDOC_API_1_1_2, functions not used are left opaque
+#[repr(C)]
+struct Api {
+    _unused: [*const c_void; 19],
+    start_frame_capture: FrameCaptureFn,
+    _is_frame_capturing: *const c_void,
+    end_frame_capture: EndFrameCaptureFn,
+}
+
+/// RenderDoc in-application API, only available when the application was
+/// launched from or injected by RenderDoc
+pub(super) struct RenderDoc {
+    api: *const Api,
+    /// Keeps the module referenced while the api is in use
+    _library: libloading::Library,
+}
+
+impl RenderDoc {
+    /// Returns None if RenderDoc isn't loaded into the process, it is never
+    /// loaded by us since it has to hook Vulkan before the instance is created
+    pub(super) fn new() -> Option<Self> {
+        let library = match load_library() {
+            Ok(library) => library,
+            Err(_) => return None,
+        };
+
+        let mut api = std::ptr::null_mut();
+        let result = unsafe {
+            let get_api = library.get::<GetApiFn>(b"RENDERDOC_GetAPI\0").ok()?;
+            get_api(API_VERSION, &mut api)
+        };
+        if result != 1 || api.is_null() {
+            log::warn!("RenderDoc is loaded but doesn't support API version 1.1.2");
+            return None;
+        }
+
+        log::info!("RenderDoc attached, captures can be triggered");
+        Some(Self {
+            api: api as *const Api,
+            _library: library,
+        })
+    }
+
+    /// Null device and window capture whatever is rendered next
+    pub(super) fn start_frame_capture(&self) {
+        unsafe {
+            ((*self.api).start_frame_capture)(std::ptr::null_mut(), std::ptr::null_mut());
+        }
+    }
+
+    pub(super) fn end_frame_capture(&self) {
+        let result = unsafe {
+            ((*self.api).end_frame_capture)(std::ptr::null_mut(), std::ptr::null_mut())
+        };
+        if result != 1 {
+            log::warn!("RenderDoc failed to capture the frame");
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn load_library() -> Result<libloading::Library> {
+    use libloading::os::unix::{Library, RTLD_NOW};
+    /// Only succeed if the library already is loaded
+    const RTLD_NOLOAD: c_int = 0x4;
+
+    let name = if cfg!(target_os = "android") {
+        "libVkLayer_GLES_RenderDoc.so"
+    } else {
+        "librenderdoc.so"
+    };
+    Ok(unsafe { Library::open(Some(name), RTLD_NOW | RTLD_NOLOAD)? }.into())
+}
+
+#[cfg(windows)]
+fn load_library() -> Result<libloading::Library> {
+    use libloading::os::windows::Library;
+
+    Ok(Library::open_already_loaded("renderdoc.dll")?.into())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+fn load_library() -> Result<libloading::Library> {
+    anyhow::bail!("RenderDoc isn't supported on this platform")
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use ash::vk;
+use ash::vk::Handle;
+use std::sync::Arc;
+
+pub struct Sampler {
+    pub(super) raw: vk::Sampler,
+    max_anisotropy: Option<f32>,
+    device: Arc<super::DeviceInner>,
+}
+
+impl Sampler {
+    pub(super) fn new(
+        device: &Arc<super::DeviceInner>,
+        desc: crate::SamplerDesc,
+    ) -> Result<Self> {
+        // Anisotropy is ignored if not supported, clamped to what the device
+        // supports otherwise
+        let max_anisotropy = desc.max_anisotropy
+            .filter(|_| device.sampler_anisotropy)
+            .map(|max_anisotropy| max_anisotropy.min(
+                device.physical_device.properties.limits.max_sampler_anisotropy
+            ));
+
+        let sampler_create_info = vk::SamplerCreateInfo::builder()
+            .mag_filter(desc.mag_filter)
+            .min_filter(desc.min_filter)
+            .mipmap_mode(desc.mipmap_mode)
+            .address_mode_u(desc.address_mode)
+            .address_mode_v(desc.address_mode)
+            .address_mode_w(desc.address_mode)
+            .anisotropy_enable(max_anisotropy.is_some())
+            .max_anisotropy(max_anisotropy.unwrap_or(1.0))
+            .min_lod(0.0)
+            .max_lod(vk::LOD_CLAMP_NONE)
+            .build();
+
+        let raw = unsafe {
+            device.raw.create_sampler(&sampler_create_info, None)
+                .map_err(super::resource_error)?
+        };
+
+        Ok(Self {
+            raw,
+            max_anisotropy,
+            device: device.clone(),
+        })
+    }
+
+    /// Anisotropy the sampler was created with after clamping, None if
+    /// anisotropic filtering is disabled
+    pub fn max_anisotropy(&self) -> Option<f32> {
+        self.max_anisotropy
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_sampler(raw, None);
+        });
+    }
+}
+
+impl super::DebugName for Sampler {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::SAMPLER, self.raw.as_raw())
+    }
+}
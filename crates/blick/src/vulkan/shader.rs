@@ -1,5 +1,6 @@
 use anyhow::Result;
 use ash::vk;
+use ash::vk::Handle;
 
 use std::ffi::CString;
 use std::sync::Arc;
@@ -7,6 +8,24 @@ use std::sync::Arc;
 pub struct GraphicsPipeline {
     pub(super) raw: vk::Pipeline,
     pub(super) pipeline_layout: vk::PipelineLayout,
+    pub(super) push_constant_ranges: Vec<vk::PushConstantRange>,
+    /// Defaults, reapplied on bind if the state is dynamic
+    pub(super) cull_mode: vk::CullModeFlags,
+    pub(super) front_face: vk::FrontFace,
+    pub(super) depth_test_enable: bool,
+    pub(super) depth_write_enable: bool,
+    pub(super) depth_compare_op: vk::CompareOp,
+    /// Depth bias is dynamic if enabled
+    pub(super) depth_bias: Option<crate::DepthBias>,
+    pub(super) dynamic_states: crate::DynamicStates,
+    /// Front and back stencil references, reapplied if STENCIL_REFERENCE is dynamic
+    pub(super) stencil_reference: (u32, u32),
+    pub(super) subpass: u32,
+    pub(super) samples: vk::SampleCountFlags,
+    /// Number of blend attachment states, has to match the subpass
+    pub(super) color_attachment_count: u32,
+    /// Created for dynamic rendering rather than a render pass
+    pub(super) dynamic_rendering: bool,
     device: Arc<super::DeviceInner>,
 }
 
@@ -14,40 +33,311 @@ pub struct ComputePipeline {
     pub(super) raw: vk::Pipeline,
     #[allow(dead_code)]
     pub(super) pipeline_layout: vk::PipelineLayout,
+    pub(super) push_constant_ranges: Vec<vk::PushConstantRange>,
     device: Arc<super::DeviceInner>,
 }
 
 
+/// Pipeline being created on a worker thread
+pub struct PendingPipeline<T> {
+    handle: std::thread::JoinHandle<Result<T>>,
+}
+
+impl<T> PendingPipeline<T> {
+    pub fn is_ready(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Blocks until the pipeline is created
+    pub fn wait(self) -> Result<T> {
+        match self.handle.join() {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::anyhow!("Pipeline creation panicked")),
+        }
+    }
+}
+
+/// Specialization constants packed into a data blob with map entries
+#[derive(Clone, Default)]
+struct Specialization {
+    map_entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+
+impl Specialization {
+    fn new(constants: &[(u32, crate::SpecValue)]) -> Self {
+        let mut specialization = Self::default();
+        for (constant_id, value) in constants {
+            let bytes = match *value {
+                // Booleans are VkBool32
+                crate::SpecValue::Bool(value) => (value as u32).to_ne_bytes(),
+                crate::SpecValue::U32(value) => value.to_ne_bytes(),
+                crate::SpecValue::I32(value) => value.to_ne_bytes(),
+                crate::SpecValue::F32(value) => value.to_ne_bytes(),
+            };
+            specialization.map_entries.push(vk::SpecializationMapEntry {
+                constant_id: *constant_id,
+                offset: specialization.data.len() as u32,
+                size: bytes.len(),
+            });
+            specialization.data.extend_from_slice(&bytes);
+        }
+        specialization
+    }
+
+    /// None if there are no constants to specialize
+    fn info(&self) -> Option<vk::SpecializationInfo> {
+        if self.map_entries.is_empty() {
+            return None;
+        }
+        Some(
+            vk::SpecializationInfo::builder()
+                .map_entries(&self.map_entries)
+                .data(&self.data)
+                .build()
+        )
+    }
+}
+
+/// Shader compiled to spirv, ready for pipeline creation
+struct CompiledShader {
+    stage: vk::ShaderStageFlags,
+    spirv: Vec<u8>,
+    specialization: Specialization,
+}
+
+impl CompiledShader {
+    fn new(desc: &crate::ShaderModuleDesc) -> Result<Self> {
+        Ok(Self {
+            stage: desc.stage,
+            spirv: compile_shader(desc)?,
+            specialization: Specialization::new(desc.specialization),
+        })
+    }
+}
+
+/// Owned copy of a ShaderModuleDesc, for passing to worker threads
+struct ShaderModuleSource {
+    source: String,
+    stage: vk::ShaderStageFlags,
+    specialization: Specialization,
+}
+
+impl ShaderModuleSource {
+    fn new(desc: &crate::ShaderModuleDesc) -> Self {
+        match desc.source {
+            crate::ShaderSource::Hlsl(src) => Self {
+                source: src.to_owned(),
+                stage: desc.stage,
+                specialization: Specialization::new(desc.specialization),
+            },
+        }
+    }
+
+    fn compile(self) -> Result<CompiledShader> {
+        let spirv = compile_shader(&crate::ShaderModuleDesc {
+            source: crate::ShaderSource::Hlsl(&self.source),
+            stage: self.stage,
+            specialization: &[],
+        })?;
+
+        Ok(CompiledShader {
+            stage: self.stage,
+            spirv,
+            specialization: self.specialization,
+        })
+    }
+}
+
+/// Owned copy of crate::RenderTarget that can be passed to a worker thread
+enum PipelineTarget {
+    RenderPass(crate::RenderPass),
+    Dynamic {
+        color_formats: Vec<vk::Format>,
+        depth_format: Option<vk::Format>,
+    },
+}
+
+impl PipelineTarget {
+    fn new(
+        device: &super::DeviceInner,
+        target: &crate::RenderTarget,
+        subpass: u32,
+    ) -> Result<Self> {
+        Ok(match target {
+            crate::RenderTarget::RenderPass(pass) => {
+                if pass.color_attachment_count(subpass).is_none() {
+                    anyhow::bail!(
+                        "Subpass {} out of range, render pass has {} subpasses",
+                        subpass,
+                        pass.subpass_count(),
+                    );
+                }
+                Self::RenderPass((*pass).clone())
+            }
+            crate::RenderTarget::Dynamic { color_formats, depth_format } => {
+                if device.dynamic_rendering.is_none() {
+                    anyhow::bail!("VK_KHR_dynamic_rendering not supported");
+                }
+                if subpass != 0 {
+                    anyhow::bail!("Dynamic rendering has no subpasses, subpass has to be 0");
+                }
+                Self::Dynamic {
+                    color_formats: color_formats.to_vec(),
+                    depth_format: *depth_format,
+                }
+            }
+        })
+    }
+}
+
+/// Fixed function state of a GraphicsPipelineDesc
+#[derive(Clone, Copy)]
+struct PipelineState {
+    subpass: u32,
+    samples: vk::SampleCountFlags,
+    stencil: crate::StencilState,
+    depth_bias: Option<crate::DepthBias>,
+    blend: Option<crate::BlendState>,
+    dynamic_states: crate::DynamicStates,
+}
+
+impl PipelineState {
+    fn new(desc: &crate::GraphicsPipelineDesc) -> Self {
+        Self {
+            subpass: desc.subpass,
+            samples: desc.samples,
+            stencil: desc.stencil,
+            depth_bias: desc.depth_bias,
+            blend: desc.blend,
+            dynamic_states: desc.dynamic_states,
+        }
+    }
+}
+
 impl GraphicsPipeline {
     pub(super) fn new(
         device: &Arc<super::DeviceInner>,
         desc: crate::GraphicsPipelineDesc,
-    ) -> Self {
-        let entry_name = CString::new("main").unwrap();
-
-        let shader_stage_create_infos = desc.shader_modules
+    ) -> Result<Self> {
+        let shaders = desc.shader_modules
             .iter()
-            .map(|module_desc| {
-                let shader_module = create_shader_module(
-                    device,
-                    module_desc,
-                )
-                    .expect("Failed to create shader module");
+            .map(CompiledShader::new)
+            .collect::<Result<Vec<_>>>()?;
+        let target = PipelineTarget::new(device, &desc.target, desc.subpass)?;
 
-                vk::PipelineShaderStageCreateInfo::builder()
-                    .stage(module_desc.stage)
-                    .module(shader_module)
-                    .name(&entry_name)
-                    .build()
-            })
+        let layout = create_pipeline_layout(
+            device,
+            desc.descriptor_set_layouts,
+            desc.push_constant_ranges
+        )?;
+
+        Self::from_spirv(
+            device,
+            &shaders,
+            layout,
+            &target,
+            &PipelineState::new(&desc),
+        )
+    }
+
+    /// Compiles shaders and creates the pipeline on a worker thread. The
+    /// pipeline layout is created up front, so the descriptor set layouts
+    /// don't need to outlive this call.
+    pub(super) fn new_async(
+        device: &Arc<super::DeviceInner>,
+        desc: crate::GraphicsPipelineDesc,
+    ) -> Result<PendingPipeline<crate::GraphicsPipeline>> {
+        let sources = desc.shader_modules
+            .iter()
+            .map(ShaderModuleSource::new)
             .collect::<Vec<_>>();
+        let target = PipelineTarget::new(device, &desc.target, desc.subpass)?;
 
-        let pipeline_layout = create_pipeline_layout(
+        let layout = create_pipeline_layout(
             device,
             desc.descriptor_set_layouts,
             desc.push_constant_ranges
-        )
-            .expect("Failed to create pipeline layout");
+        )?;
+
+        let state = PipelineState::new(&desc);
+        let device = device.clone();
+
+        let handle = std::thread::spawn(move || {
+            let shaders = match sources
+                .into_iter()
+                .map(ShaderModuleSource::compile)
+                .collect::<Result<Vec<_>>>()
+            {
+                Ok(shaders) => shaders,
+                Err(err) => {
+                    layout.destroy(&device);
+                    return Err(err);
+                }
+            };
+
+            Self::from_spirv(
+                &device,
+                &shaders,
+                layout,
+                &target,
+                &state,
+            ).map(Arc::new)
+        });
+
+        Ok(PendingPipeline { handle })
+    }
+
+    /// Takes ownership of the pipeline layout, which is destroyed on failure
+    fn from_spirv(
+        device: &Arc<super::DeviceInner>,
+        shaders: &[CompiledShader],
+        layout: PipelineLayout,
+        target: &PipelineTarget,
+        state: &PipelineState,
+    ) -> Result<Self> {
+        let PipelineState {
+            subpass,
+            samples,
+            stencil,
+            depth_bias,
+            blend,
+            dynamic_states: optional_dynamic_states,
+        } = *state;
+        let entry_name = CString::new("main").unwrap();
+
+        let specialization_infos = shaders
+            .iter()
+            .map(|shader| shader.specialization.info())
+            .collect::<Vec<_>>();
+
+        let mut shader_modules = Vec::with_capacity(shaders.len());
+        for shader in shaders {
+            match create_shader_module(device, &shader.spirv) {
+                Ok(shader_module) => shader_modules.push(shader_module),
+                Err(err) => {
+                    destroy_shader_modules(device, &shader_modules);
+                    layout.destroy(device);
+                    return Err(err);
+                }
+            }
+        }
+
+        let shader_stage_create_infos = shaders
+            .iter()
+            .zip(shader_modules.iter())
+            .zip(specialization_infos.iter())
+            .map(|((shader, &shader_module), specialization_info)| {
+                let mut create_info = vk::PipelineShaderStageCreateInfo::builder()
+                    .stage(shader.stage)
+                    .module(shader_module)
+                    .name(&entry_name);
+                if let Some(specialization_info) = specialization_info {
+                    create_info = create_info.specialization_info(specialization_info);
+                }
+                create_info.build()
+            })
+            .collect::<Vec<_>>();
 
         // We only do bindless vertex buffers
         let vertex_input_state_create_info = vk::PipelineVertexInputStateCreateInfo::builder()
@@ -60,6 +350,38 @@ impl GraphicsPipeline {
             .build();
 
         // TODO: Allow changing of state parameters
+        let cull_mode = vk::CullModeFlags::BACK;
+        let front_face = vk::FrontFace::CLOCKWISE;
+        let depth_test_enable = false;
+        let depth_write_enable = false;
+        let depth_compare_op = vk::CompareOp::LESS_OR_EQUAL;
+        let depth_bias = depth_bias.map(|bias| device.supported_depth_bias(bias));
+
+        let mut dynamic_states = vec![
+            vk::DynamicState::VIEWPORT,
+            vk::DynamicState::SCISSOR,
+        ];
+        if device.extended_dynamic_state.is_some() {
+            dynamic_states.extend_from_slice(&[
+                vk::DynamicState::CULL_MODE_EXT,
+                vk::DynamicState::FRONT_FACE_EXT,
+                vk::DynamicState::DEPTH_TEST_ENABLE_EXT,
+                vk::DynamicState::DEPTH_WRITE_ENABLE_EXT,
+                vk::DynamicState::DEPTH_COMPARE_OP_EXT,
+            ]);
+        }
+        if depth_bias.is_some() {
+            dynamic_states.push(vk::DynamicState::DEPTH_BIAS);
+        }
+        for (flag, state) in [
+            (crate::DynamicStates::BLEND_CONSTANTS, vk::DynamicState::BLEND_CONSTANTS),
+            (crate::DynamicStates::LINE_WIDTH, vk::DynamicState::LINE_WIDTH),
+            (crate::DynamicStates::STENCIL_REFERENCE, vk::DynamicState::STENCIL_REFERENCE),
+        ] {
+            if optional_dynamic_states.contains(flag) {
+                dynamic_states.push(state);
+            }
+        }
 
         let viewport_state_create_info = vk::PipelineViewportStateCreateInfo::builder()
             .viewport_count(1)
@@ -67,60 +389,90 @@ impl GraphicsPipeline {
             .build();
     
         let rasterization_state_create_info = vk::PipelineRasterizationStateCreateInfo::builder()
-            .cull_mode(vk::CullModeFlags::BACK)
-            .front_face(vk::FrontFace::CLOCKWISE)
+            .cull_mode(cull_mode)
+            .front_face(front_face)
             .polygon_mode(vk::PolygonMode::FILL)
             .rasterizer_discard_enable(false)
             .line_width(1.0)
-            .depth_bias_clamp(0.0)
-            .depth_bias_constant_factor(0.0)
-            .depth_bias_enable(false)
-            .depth_bias_slope_factor(0.0)
+            // Values are set dynamically
+            .depth_bias_enable(depth_bias.is_some())
             .build();
     
         let multisample_state_create_info = vk::PipelineMultisampleStateCreateInfo::builder()
-            .rasterization_samples(vk::SampleCountFlags::TYPE_1)
+            .rasterization_samples(samples)
             .sample_shading_enable(false)
             .min_sample_shading(0.0)
             .build();
     
-        let stencil_state = vk::StencilOpState::builder()
-            .fail_op(vk::StencilOp::KEEP)
-            .pass_op(vk::StencilOp::KEEP)
-            .depth_fail_op(vk::StencilOp::KEEP)
-            .compare_op(vk::CompareOp::ALWAYS)
-            .build();
-    
         let depth_stencil_state_create_info = vk::PipelineDepthStencilStateCreateInfo::builder()
-            .depth_test_enable(false)
-            .depth_write_enable(false)
-            .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
-            .front(stencil_state)
-            .back(stencil_state)
+            .depth_test_enable(depth_test_enable)
+            .depth_write_enable(depth_write_enable)
+            .depth_compare_op(depth_compare_op)
+            .stencil_test_enable(stencil.test_enable)
+            .front((&stencil.front).into())
+            .back((&stencil.back).into())
             .max_depth_bounds(1.0)
             .build();
 
-        let color_blend_state_create_infos = (0..desc.render_pass.num_attachments())
+        let color_attachment_count = match target {
+            // Subpass was checked by PipelineTarget::new
+            PipelineTarget::RenderPass(pass) => pass.color_attachment_count(subpass).unwrap_or(0),
+            PipelineTarget::Dynamic { color_formats, .. } => color_formats.len() as u32,
+        };
+        let color_blend_state_create_infos = (0..color_attachment_count)
             .map(|_| {
-                vk::PipelineColorBlendAttachmentState::builder()
-                    .blend_enable(false)
-                    .color_write_mask(vk::ColorComponentFlags::RGBA)
-                    .src_color_blend_factor(vk::BlendFactor::ONE)
-                    .dst_color_blend_factor(vk::BlendFactor::ZERO)
-                    .color_blend_op(vk::BlendOp::ADD)
-                    .src_alpha_blend_factor(vk::BlendFactor::ONE)
-                    .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
-                    .alpha_blend_op(vk::BlendOp::ADD)
-                    .build()
+                let builder = vk::PipelineColorBlendAttachmentState::builder()
+                    .color_write_mask(vk::ColorComponentFlags::RGBA);
+                match blend {
+                    Some(blend) => builder
+                        .blend_enable(true)
+                        .src_color_blend_factor(blend.src_color_factor)
+                        .dst_color_blend_factor(blend.dst_color_factor)
+                        .color_blend_op(blend.color_op)
+                        .src_alpha_blend_factor(blend.src_alpha_factor)
+                        .dst_alpha_blend_factor(blend.dst_alpha_factor)
+                        .alpha_blend_op(blend.alpha_op),
+                    None => builder
+                        .blend_enable(false)
+                        .src_color_blend_factor(vk::BlendFactor::ONE)
+                        .dst_color_blend_factor(vk::BlendFactor::ZERO)
+                        .color_blend_op(vk::BlendOp::ADD)
+                        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                        .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+                        .alpha_blend_op(vk::BlendOp::ADD),
+                }
+                .build()
             })
             .collect::<Vec<_>>();
 
 
-        // let mut pipeline_rendering_create_info = vk::PipelineRenderingCreateInfo::builder()
-        //     .color_attachment_formats(&[vk::Format::B8G8R8A8_SRGB]) // TODO
-        //     .build();
+        let (color_formats, depth_format) = match target {
+            PipelineTarget::RenderPass(_) => (&[][..], None),
+            PipelineTarget::Dynamic { color_formats, depth_format } => {
+                (&color_formats[..], *depth_format)
+            }
+        };
+        // Formats of aspects the attachment doesn't have have to be UNDEFINED
+        let aspect_format = |aspect| match depth_format {
+            Some(format) if super::image::aspect_mask(format).contains(aspect) => format,
+            _ => vk::Format::UNDEFINED,
+        };
+        let stencil_format = aspect_format(vk::ImageAspectFlags::STENCIL);
+        let depth_format = aspect_format(vk::ImageAspectFlags::DEPTH);
+        let mut pipeline_rendering_create_info = vk::PipelineRenderingCreateInfo::builder()
+            .color_attachment_formats(color_formats)
+            .depth_attachment_format(depth_format)
+            .stencil_attachment_format(stencil_format)
+            .build();
+
+        let color_blend_state_create_info = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&color_blend_state_create_infos)
+            .build();
+        let dynamic_state_create_info = vk::PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(&dynamic_states)
+            .build();
 
-        let graphics_pipeline_create_info = vk::GraphicsPipelineCreateInfo::builder()
+        let mut graphics_pipeline_create_info = vk::GraphicsPipelineCreateInfo::builder()
             .stages(&shader_stage_create_infos)
             .vertex_input_state(&vertex_input_state_create_info)
             .input_assembly_state(&vertex_input_assembly_state_create_info)
@@ -128,18 +480,22 @@ impl GraphicsPipeline {
             .rasterization_state(&rasterization_state_create_info)
             .multisample_state(&multisample_state_create_info)
             .depth_stencil_state(&depth_stencil_state_create_info)
-            .color_blend_state(&vk::PipelineColorBlendStateCreateInfo::builder()
-                .attachments(&color_blend_state_create_infos)
-                .build())
-            .dynamic_state(&vk::PipelineDynamicStateCreateInfo::builder()
-                .dynamic_states(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR])
-                .build())
-            .layout(pipeline_layout)
-            //.push_next(&mut pipeline_rendering_create_info)
-            .render_pass(desc.render_pass.raw())
-            .build();
+            .color_blend_state(&color_blend_state_create_info)
+            .dynamic_state(&dynamic_state_create_info)
+            .layout(layout.raw)
+            .subpass(subpass);
+
+        graphics_pipeline_create_info = match target {
+            PipelineTarget::RenderPass(pass) => {
+                graphics_pipeline_create_info.render_pass(pass.raw())
+            }
+            PipelineTarget::Dynamic { .. } => {
+                graphics_pipeline_create_info.push_next(&mut pipeline_rendering_create_info)
+            }
+        };
+        let graphics_pipeline_create_info = graphics_pipeline_create_info.build();
 
-        let raw = unsafe {
+        let result = unsafe {
             device.raw
                 .create_graphics_pipelines(
                     // TODO: ?
@@ -147,30 +503,47 @@ impl GraphicsPipeline {
                     &[graphics_pipeline_create_info],
                     None
                 )
-                .expect("Failed to create graphics pipeline")
-        }[0];
+        };
 
-        // Pipeline is complete, now we can cleanup shader modules
-        shader_stage_create_infos
-            .iter()
-            .for_each(|info| unsafe {
-                device.raw.destroy_shader_module(info.module, None)
-            });
+        // Pipeline is complete (or failed), now we can cleanup shader modules
+        destroy_shader_modules(device, &shader_modules);
 
-        Self {
+        let raw = match result {
+            Ok(pipelines) => pipelines[0],
+            Err((_, err)) => {
+                layout.destroy(device);
+                return Err(err.into());
+            }
+        };
+
+        Ok(Self {
             raw,
-            pipeline_layout,
+            pipeline_layout: layout.raw,
+            push_constant_ranges: layout.push_constant_ranges,
+            cull_mode,
+            front_face,
+            depth_test_enable,
+            depth_write_enable,
+            depth_compare_op,
+            depth_bias,
+            dynamic_states: optional_dynamic_states,
+            stencil_reference: (stencil.front.reference, stencil.back.reference),
+            subpass,
+            samples,
+            color_attachment_count,
+            dynamic_rendering: matches!(target, PipelineTarget::Dynamic { .. }),
             device: device.clone(),
-        }
+        })
     }
 }
 
 impl Drop for GraphicsPipeline {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.raw.destroy_pipeline(self.raw, None);
-        }
+        let (raw, pipeline_layout) = (self.raw, self.pipeline_layout);
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_pipeline_layout(pipeline_layout, None);
+            device.raw.destroy_pipeline(raw, None);
+        });
     }
 }
 
@@ -178,70 +551,134 @@ impl ComputePipeline {
     pub fn new(
         device: &Arc<super::DeviceInner>,
         desc: crate::ComputePipelineDesc,
-    ) -> Self {
-        let entry_name = CString::new("main").unwrap();
+    ) -> Result<Self> {
+        let shader = CompiledShader::new(&desc.shader_module)?;
 
-        let shader_module = create_shader_module(
+        let layout = create_pipeline_layout(
             device,
-            &desc.shader_module,
-        )
-            .expect("Failed to create shader module");
+            desc.descriptor_set_layouts,
+            desc.push_constant_ranges
+        )?;
 
-        let shader_stage_create_info = vk::PipelineShaderStageCreateInfo::builder()
-            .stage(vk::ShaderStageFlags::COMPUTE)
-            .module(shader_module)
-            .name(&entry_name)
-            .build();
+        Self::from_spirv(device, &shader, layout)
+    }
+
+    /// See GraphicsPipeline::new_async
+    pub(super) fn new_async(
+        device: &Arc<super::DeviceInner>,
+        desc: crate::ComputePipelineDesc,
+    ) -> Result<PendingPipeline<crate::ComputePipeline>> {
+        let source = ShaderModuleSource::new(&desc.shader_module);
 
-        let pipeline_layout = create_pipeline_layout(
+        let layout = create_pipeline_layout(
             device,
             desc.descriptor_set_layouts,
             desc.push_constant_ranges
-        )
-            .expect("Failed to create pipeline layout");
+        )?;
+
+        let device = device.clone();
+
+        let handle = std::thread::spawn(move || {
+            let shader = match source.compile() {
+                Ok(shader) => shader,
+                Err(err) => {
+                    layout.destroy(&device);
+                    return Err(err);
+                }
+            };
+
+            Self::from_spirv(&device, &shader, layout).map(Arc::new)
+        });
+
+        Ok(PendingPipeline { handle })
+    }
+
+    /// Takes ownership of the pipeline layout, which is destroyed on failure
+    fn from_spirv(
+        device: &Arc<super::DeviceInner>,
+        shader: &CompiledShader,
+        layout: PipelineLayout,
+    ) -> Result<Self> {
+        let entry_name = CString::new("main").unwrap();
+
+        let shader_module = match create_shader_module(device, &shader.spirv) {
+            Ok(shader_module) => shader_module,
+            Err(err) => {
+                layout.destroy(device);
+                return Err(err);
+            }
+        };
+
+        let specialization_info = shader.specialization.info();
+
+        let mut shader_stage_create_info = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(shader_module)
+            .name(&entry_name);
+        if let Some(specialization_info) = &specialization_info {
+            shader_stage_create_info = shader_stage_create_info
+                .specialization_info(specialization_info);
+        }
+        let shader_stage_create_info = shader_stage_create_info.build();
 
         let compute_pipeline_create_info = vk::ComputePipelineCreateInfo::builder()
-            .layout(pipeline_layout)
+            .layout(layout.raw)
             .stage(shader_stage_create_info)
             .build();
 
-        let raw = unsafe {
+        let result = unsafe {
             device.raw
                 .create_compute_pipelines(
                     vk::PipelineCache::null(),
                     &[compute_pipeline_create_info],
                     None
                 )
-                .expect("Failed to create compute pipeline")
-        }[0];
+        };
 
-        unsafe {
-            device.raw.destroy_shader_module(shader_module, None)
+        destroy_shader_modules(device, &[shader_module]);
+
+        let raw = match result {
+            Ok(pipelines) => pipelines[0],
+            Err((_, err)) => {
+                layout.destroy(device);
+                return Err(err.into());
+            }
         };
 
-        Self {
+        Ok(Self {
             raw,
-            pipeline_layout,
+            pipeline_layout: layout.raw,
+            push_constant_ranges: layout.push_constant_ranges,
             device: device.clone(),
-        }
+        })
     }
 }
 
 
 impl Drop for ComputePipeline {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.raw.destroy_pipeline(self.raw, None);
-        }
+        let (raw, pipeline_layout) = (self.raw, self.pipeline_layout);
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_pipeline_layout(pipeline_layout, None);
+            device.raw.destroy_pipeline(raw, None);
+        });
     }
 }
 
-fn create_shader_module(
-    device: &Arc<super::DeviceInner>,
-    desc: &crate::ShaderModuleDesc
-) -> Result<vk::ShaderModule> {
+impl super::DebugName for GraphicsPipeline {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::PIPELINE, self.raw.as_raw())
+    }
+}
+
+impl super::DebugName for ComputePipeline {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::PIPELINE, self.raw.as_raw())
+    }
+}
 
+/// Compiles the shader source to spirv
+fn compile_shader(desc: &crate::ShaderModuleDesc) -> Result<Vec<u8>> {
     let spirv = match desc.source {
         crate::ShaderSource::Hlsl(src) => {
             let target_profile = match desc.stage {
@@ -260,12 +697,80 @@ fn create_shader_module(
         }
     };
 
+    Ok(spirv)
+}
+
+/// Compiles the shader and reflects the resources it uses
+pub(super) fn reflect_shader(desc: &crate::ShaderModuleDesc) -> Result<crate::ShaderReflection> {
+    let spirv = compile_shader(desc)?;
+    let reflection = rspirv_reflect::Reflection::new_from_spirv(&spirv)
+        .map_err(reflect_error)?;
+
+    let descriptor_sets = reflection
+        .get_descriptor_sets()
+        .map_err(reflect_error)?
+        .into_iter()
+        .map(|(set, bindings)| {
+            let entries = bindings
+                .into_iter()
+                .map(|(binding, info)| {
+                    let (count, flags) = match info.binding_count {
+                        rspirv_reflect::BindingCount::One => {
+                            (1, vk::DescriptorBindingFlags::empty())
+                        },
+                        rspirv_reflect::BindingCount::StaticSized(count) => {
+                            (count as u32, vk::DescriptorBindingFlags::empty())
+                        },
+                        rspirv_reflect::BindingCount::Unbounded => (
+                            0,
+                            vk::DescriptorBindingFlags::PARTIALLY_BOUND
+                                | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT,
+                        ),
+                    };
+                    crate::DescriptorSetLayoutEntry {
+                        binding,
+                        stage_flags: desc.stage,
+                        ty: vk::DescriptorType::from_raw(info.ty.0 as i32),
+                        count,
+                        flags,
+                    }
+                })
+                .collect();
+            (set, entries)
+        })
+        .collect();
+
+    let push_constant_range = reflection
+        .get_push_constant_range()
+        .map_err(reflect_error)?
+        .map(|range| crate::PushConstantRange {
+            stage_flags: desc.stage,
+            offset: range.offset,
+            size: range.size,
+        });
+
+    Ok(crate::ShaderReflection {
+        descriptor_sets,
+        push_constant_range,
+        local_size: reflection.get_compute_group_size(),
+    })
+}
+
+/// ReflectError isn't Sync so it can't be converted to anyhow::Error directly
+fn reflect_error(err: rspirv_reflect::ReflectError) -> anyhow::Error {
+    anyhow::anyhow!("Shader reflection failed: {}", err)
+}
+
+fn create_shader_module(
+    device: &Arc<super::DeviceInner>,
+    spirv: &[u8],
+) -> Result<vk::ShaderModule> {
     // Builder requires conversion Vec<u8> -> &[u32] (and then back to ptr)
     let create_info = vk::ShaderModuleCreateInfo {
         s_type: vk::StructureType::SHADER_MODULE_CREATE_INFO,
         p_next: std::ptr::null(),
         flags: vk::ShaderModuleCreateFlags::empty(),
-        code_size: spirv.len() as usize,
+        code_size: spirv.len(),
         p_code: spirv.as_ptr() as *const u32,
     };
 
@@ -276,6 +781,14 @@ fn create_shader_module(
     Ok(module)
 }
 
+fn destroy_shader_modules(device: &super::DeviceInner, shader_modules: &[vk::ShaderModule]) {
+    for &shader_module in shader_modules {
+        unsafe {
+            device.raw.destroy_shader_module(shader_module, None)
+        };
+    }
+}
+
 /// Compiles hlsl shader to spirv
 /// This will probably be moved to some asset system later
 fn compile_hlsl(
@@ -302,11 +815,40 @@ fn compile_hlsl(
     Ok(spirv)
 }
 
+/// Pipeline layout and the push constant ranges it was created with
+struct PipelineLayout {
+    raw: vk::PipelineLayout,
+    push_constant_ranges: Vec<vk::PushConstantRange>,
+}
+
+impl PipelineLayout {
+    /// For failed pipeline creation, the layout is otherwise owned by the pipeline
+    fn destroy(&self, device: &super::DeviceInner) {
+        unsafe {
+            device.raw.destroy_pipeline_layout(self.raw, None)
+        };
+    }
+}
+
 fn create_pipeline_layout(
     device: &Arc<super::DeviceInner>,
     descriptor_set_layouts:  &[&crate::DescriptorSetLayout],
     push_constant_ranges:  &[crate::PushConstantRange],
-) -> Result<vk::PipelineLayout> {
+) -> Result<PipelineLayout> {
+    let max_size = device.physical_device.properties.limits.max_push_constants_size;
+    for range in push_constant_ranges {
+        let in_bounds = range.offset.checked_add(range.size)
+            .is_some_and(|end| end <= max_size);
+        if !in_bounds {
+            anyhow::bail!(
+                "Push constant range {}+{} exceeds maxPushConstantsSize of {} bytes",
+                range.offset,
+                range.size,
+                max_size,
+            );
+        }
+    }
+
     let descriptor_set_layouts = descriptor_set_layouts
         .iter()
         .map(|layout| layout.raw)
@@ -326,7 +868,10 @@ fn create_pipeline_layout(
         device.raw
             .create_pipeline_layout(&pipeline_layout_create_info, None)
     } {
-        Ok(layout) => Ok(layout),
+        Ok(raw) => Ok(PipelineLayout {
+            raw,
+            push_constant_ranges,
+        }),
         Err(e) => Err(e.into()),
     }
 }
@@ -340,3 +885,17 @@ impl From<&crate::PushConstantRange> for vk::PushConstantRange {
         }
     }
 }
+
+impl From<&crate::StencilFaceState> for vk::StencilOpState {
+    fn from(state: &crate::StencilFaceState) -> Self {
+        vk::StencilOpState {
+            fail_op: state.fail_op,
+            pass_op: state.pass_op,
+            depth_fail_op: state.depth_fail_op,
+            compare_op: state.compare_op,
+            compare_mask: state.compare_mask,
+            write_mask: state.write_mask,
+            reference: state.reference,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Part of a staging chunk handed out by StagingBelt::allocate, write to it
+/// through buffer.write_slice or buffer.mapped_ptr and copy from it with
+/// CommandBuffer::copy_buffer
+pub struct StagingSlice {
+    pub buffer: crate::Buffer,
+    /// Offset in bytes into buffer
+    pub offset: u64,
+    pub size: u64,
+}
+
+struct Chunk {
+    buffer: crate::Buffer,
+    /// Next free byte
+    offset: u64,
+    /// Last frame the chunk was allocated from
+    frame: u64,
+}
+
+/// Suballocates uploads from a few large persistently mapped buffers instead
+/// of creating a staging buffer per upload. Chunks used during a frame are
+/// reused once that frame has been released, see recall.
+pub struct StagingBelt {
+    chunk_size: u64,
+    /// Chunk currently allocated from
+    active: Option<Chunk>,
+    /// Full chunks waiting for their frame to be released
+    in_flight: VecDeque<Chunk>,
+    free: Vec<Chunk>,
+    device: Arc<super::DeviceInner>,
+}
+
+impl StagingBelt {
+    pub(super) fn new(device: &Arc<super::DeviceInner>, chunk_size: u64) -> Self {
+        Self {
+            chunk_size,
+            active: None,
+            in_flight: VecDeque::new(),
+            free: Vec::new(),
+            device: device.clone(),
+        }
+    }
+
+    /// Allocates size bytes, alignment has to be a power of two. Allocations
+    /// larger than the chunk size get a chunk of their own.
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Result<StagingSlice> {
+        debug_assert!(
+            alignment.is_power_of_two(),
+            "allocate: alignment {} is not a power of two",
+            alignment,
+        );
+        let frame = self.device.frame();
+        let align = |offset: u64| (offset + alignment - 1) & !(alignment - 1);
+
+        let fits = self.active.as_ref().is_some_and(|chunk| {
+            align(chunk.offset) + size <= chunk.buffer.size()
+        });
+        if !fits {
+            if let Some(chunk) = self.active.take() {
+                self.in_flight.push_back(chunk);
+            }
+            self.active = Some(self.take_chunk(size)?);
+        }
+
+        let chunk = self.active.as_mut().unwrap();
+        let offset = align(chunk.offset);
+        chunk.offset = offset + size;
+        chunk.frame = frame;
+
+        Ok(StagingSlice {
+            buffer: chunk.buffer.clone(),
+            offset,
+            size,
+        })
+    }
+
+    /// Writes data to a staging allocation and records a copy of it to dst
+    pub fn write_buffer(
+        &mut self,
+        command_buffer: &mut crate::CommandBuffer,
+        dst: &crate::Buffer,
+        dst_offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        let slice = self.allocate(data.len() as u64, 4)?;
+        slice.buffer.write_slice(slice.offset as usize, data)?;
+        command_buffer.copy_buffer(&slice.buffer, slice.offset, dst, dst_offset, slice.size);
+        Ok(())
+    }
+
+    /// Makes chunks last used during frame or earlier available again, call
+    /// together with Device::release_frame
+    pub fn recall(&mut self, frame: u64) {
+        while self.in_flight.front().is_some_and(|chunk| chunk.frame <= frame) {
+            let mut chunk = self.in_flight.pop_front().unwrap();
+            // Oversized chunks are dropped rather than kept around
+            if chunk.buffer.size() == self.chunk_size {
+                chunk.offset = 0;
+                self.free.push(chunk);
+            }
+        }
+    }
+
+    /// Total size of all chunks owned by the belt
+    pub fn allocated_size(&self) -> u64 {
+        self.active
+            .iter()
+            .chain(self.in_flight.iter())
+            .chain(self.free.iter())
+            .map(|chunk| chunk.buffer.size())
+            .sum()
+    }
+
+    fn take_chunk(&mut self, size: u64) -> Result<Chunk> {
+        if size <= self.chunk_size {
+            if let Some(chunk) = self.free.pop() {
+                return Ok(chunk);
+            }
+        }
+
+        let buffer = Arc::new(super::Buffer::new(
+            &self.device,
+            crate::BufferDesc {
+                size: size.max(self.chunk_size),
+                usage: crate::BufferUsage::MAP_WRITE | crate::BufferUsage::TRANSFER_SRC,
+                min_alignment: None,
+                memory_location: None,
+                name: Some("staging belt".to_owned()),
+            },
+        )?);
+        Ok(Chunk {
+            buffer,
+            offset: 0,
+            frame: 0,
+        })
+    }
+}
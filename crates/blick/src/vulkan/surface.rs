@@ -6,7 +6,6 @@ use ash::extensions::khr;
 use ash::vk;
 
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
-use winit::window::Window;
 
 pub struct Surface {
     pub(super) raw : vk::SurfaceKHR,
@@ -14,9 +13,10 @@ pub struct Surface {
 }
 
 impl Surface {
-    pub fn new(
+    /// window: Any window providing raw handles, e.g. a winit Window
+    pub fn new<W: HasRawWindowHandle + HasRawDisplayHandle>(
         instance: &Instance,
-        window: &Window
+        window: &W
     ) -> Result<Self> {
         let surface = unsafe {
             ash_window::create_surface(
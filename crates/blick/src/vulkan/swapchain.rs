@@ -4,8 +4,45 @@ use ash::vk;
 
 use std::sync::Arc;
 
+#[derive(Debug)]
 pub enum SwapchainError {
     Outdated,
+    SurfaceLost,
+    DeviceLost,
+    Other(vk::Result),
+}
+
+impl From<vk::Result> for SwapchainError {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_OUT_OF_DATE_KHR => SwapchainError::Outdated,
+            vk::Result::ERROR_SURFACE_LOST_KHR => SwapchainError::SurfaceLost,
+            vk::Result::ERROR_DEVICE_LOST => SwapchainError::DeviceLost,
+            err => SwapchainError::Other(err),
+        }
+    }
+}
+
+impl From<SwapchainError> for crate::BeginFrameError {
+    fn from(err: SwapchainError) -> Self {
+        match err {
+            SwapchainError::Outdated => crate::BeginFrameError::OutdatedSwapchain,
+            SwapchainError::SurfaceLost => crate::BeginFrameError::SurfaceLost,
+            SwapchainError::DeviceLost => crate::BeginFrameError::DeviceLost,
+            SwapchainError::Other(err) => crate::BeginFrameError::Other(err),
+        }
+    }
+}
+
+impl From<SwapchainError> for crate::EndFrameError {
+    fn from(err: SwapchainError) -> Self {
+        match err {
+            SwapchainError::Outdated => crate::EndFrameError::OutdatedSwapchain,
+            SwapchainError::SurfaceLost => crate::EndFrameError::SurfaceLost,
+            SwapchainError::DeviceLost => crate::EndFrameError::DeviceLost,
+            SwapchainError::Other(err) => crate::EndFrameError::Other(err),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -15,12 +52,25 @@ pub struct SwapchainDesc {
     pub extent: crate::Extent2d,
     pub image_count: u32,
     pub present_mode: vk::PresentModeKHR,
+    /// Always includes COLOR_ATTACHMENT, TRANSFER_SRC and TRANSFER_DST if the
+    /// surface supports them
+    pub usage: crate::ImageUsage,
+    /// OPAQUE if supported, otherwise the first supported mode
+    pub composite_alpha: vk::CompositeAlphaFlagsKHR,
+    /// IDENTITY if supported, otherwise the current transform of the surface
+    pub pre_transform: vk::SurfaceTransformFlagsKHR,
 }
 
 #[derive(Clone)]
 pub struct SwapchainImage {
     pub index: u32,
+    /// Shared with the swapchain for its whole lifetime, views created on it are
+    /// cached and reused across frames until the swapchain is recreated
     pub image: crate::Image,
+    /// Layout of the image when acquired, PRESENT_SRC_KHR if swapchain images
+    /// are transitioned on creation, otherwise UNDEFINED. Either is valid as
+    /// old_layout for the first barrier of a frame.
+    pub layout: vk::ImageLayout,
 }
 
 pub struct Swapchain {
@@ -32,6 +82,10 @@ pub struct Swapchain {
 
 
 impl Swapchain {
+    pub fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
     /// Any options provided in desc are expected to be supported by our device
     /// TODO: Add compatibility checks?
     pub fn new(
@@ -39,7 +93,7 @@ impl Swapchain {
         surface: &super::Surface,
         desc: &super::SwapchainDesc,
         old_swapchain: Option<&Self>,
-    ) -> Self {
+    ) -> Result<Self, SwapchainError> {
         let old_swapchain = match old_swapchain {
             Some(old_swapchain) => old_swapchain.raw,
             None => vk::SwapchainKHR::null(),
@@ -53,11 +107,10 @@ impl Swapchain {
             .image_array_layers(1)
             .image_color_space(desc.color_space)
             .image_extent(desc.extent)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_usage((&desc.usage).into())
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
-            // TODO: pre_transform, my guess this is mostly for mobile
-            .pre_transform(vk::SurfaceTransformFlagsKHR::IDENTITY)
-            .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+            .pre_transform(desc.pre_transform)
+            .composite_alpha(desc.composite_alpha)
             .present_mode(desc.present_mode)
             .clipped(true)
             .old_swapchain(old_swapchain)
@@ -66,14 +119,15 @@ impl Swapchain {
 
         let loader = khr::Swapchain::new(&device.instance.raw, &device.raw);
         let raw = unsafe {
-            loader.create_swapchain(&swapchain_create_info, None)
-                .expect("Failed to create swapchain")
+            loader.create_swapchain(&swapchain_create_info, None)?
         };
 
-        let images = unsafe {
-            loader
-                .get_swapchain_images(raw)
-                .expect("Failed to get swapchain images")
+        let images = match unsafe { loader.get_swapchain_images(raw) } {
+            Ok(images) => images,
+            Err(err) => {
+                unsafe { loader.destroy_swapchain(raw, None) };
+                return Err(err.into());
+            }
         };
 
         let images = images
@@ -91,41 +145,53 @@ impl Swapchain {
                             depth: 1,
                         },
                         image_type: vk::ImageType::TYPE_2D,
-                        usage: crate::ImageUsage::COLOR_ATTACHMENT,
+                        mip_levels: 1,
+                        array_layers: 1,
+                        samples: vk::SampleCountFlags::TYPE_1,
+                        usage: desc.usage,
+                        mutable_format: false,
+                        cube_compatible: false,
+                        name: None,
                     },
                 );
 
                 SwapchainImage {
                     index: index as u32,
                     image: Arc::new(image),
+                    layout: vk::ImageLayout::UNDEFINED,
                 }
             })
             .collect::<Vec<_>>();
 
-        Self {
+        Ok(Self {
             raw,
             loader,
             images,
+        })
+    }
+
+    pub(super) fn images(&self) -> &[SwapchainImage] {
+        &self.images
+    }
+
+    /// Sets the layout all images are in when acquired
+    pub(super) fn set_image_layout(&mut self, layout: vk::ImageLayout) {
+        for image in self.images.iter_mut() {
+            image.layout = layout;
         }
     }
 
     pub(super) fn acquire_next_image(
         &self,
         semaphore: &super::Semaphore,
-    ) -> Result<SwapchainImage> {
+    ) -> Result<SwapchainImage, SwapchainError> {
         let (index, _) = unsafe {
-            match self.loader.acquire_next_image(
+            self.loader.acquire_next_image(
                 self.raw,
                 u64::MAX,
                 semaphore.raw,
                 vk::Fence::null(),
-            ) {
-                Ok((index, is_suboptimal)) => (index, is_suboptimal),
-                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                    return Err(anyhow::anyhow!("Swapchain out of date"))
-                }
-                Err(err) => panic!("Failed to acquire next image: {:?}", err),
-            }
+            )?
         };
 
         // TODO: Handle suboptimal
@@ -138,23 +204,16 @@ impl Swapchain {
         queue: &super::Queue,
         image: &SwapchainImage,
         render_finished: &super::Semaphore,
-    ) -> Result<(), SwapchainError> {
+    ) -> Result<bool, SwapchainError> {
         let present_info = vk::PresentInfoKHR::builder()
             .wait_semaphores(&[render_finished.raw])
             .swapchains(&[self.raw])
             .image_indices(&[image.index])
             .build();
         unsafe {
-            match self.loader.queue_present(queue.raw, &present_info) {
-                Ok(_) => {
-                    // TODO: Handle suboptimal?
-                    Ok(())
-                },
-                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                    Err(SwapchainError::Outdated)
-                },
-                Err(err) => panic!("Failed to present image: {:?}", err),
-            }
+            self.loader
+                .queue_present(queue.raw, &present_info)
+                .map_err(SwapchainError::from)
         }
     }
 }
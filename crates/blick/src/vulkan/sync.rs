@@ -1,4 +1,5 @@
 use ash::vk;
+use ash::vk::Handle;
 use std::sync::Arc;
 
 pub struct Fence {
@@ -23,11 +24,27 @@ impl Fence {
         }
     }
 }
+impl Fence {
+    /// Destroys the fence right away, for transient fences known to be
+    /// signaled
+    pub(super) fn destroy_idle(mut self) {
+        unsafe {
+            self.device.raw.destroy_fence(self.raw, None);
+        }
+        self.raw = vk::Fence::null();
+    }
+}
+
 impl Drop for Fence {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_fence(self.raw, None)
+        if self.raw == vk::Fence::null() {
+            return;
         }
+
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_fence(raw, None);
+        });
     }
 }
 
@@ -56,8 +73,60 @@ impl Semaphore {
 
 impl Drop for Semaphore {
     fn drop(&mut self) {
-        unsafe {
-            self.device.raw.destroy_semaphore(self.raw, None)
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_semaphore(raw, None);
+        });
+    }
+}
+
+/// GPU-side signal for finer grained sync within a queue than full barriers
+pub struct Event {
+    pub(super) raw: vk::Event,
+    device: Arc<super::DeviceInner>,
+}
+
+impl Event {
+    pub(super) fn new(device: &Arc<super::DeviceInner>) -> Self {
+        let event_create_info = vk::EventCreateInfo::builder()
+            .flags(vk::EventCreateFlags::empty())
+            .build();
+
+        let raw = unsafe {
+            device.raw.create_event(&event_create_info, None)
+                .expect("Failed to create event")
+        };
+
+        Self {
+            raw,
+            device: device.clone(),
         }
     }
 }
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        let raw = self.raw;
+        self.device.defer_destroy(move |device| unsafe {
+            device.raw.destroy_event(raw, None);
+        });
+    }
+}
+
+impl super::DebugName for Fence {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::FENCE, self.raw.as_raw())
+    }
+}
+
+impl super::DebugName for Event {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::EVENT, self.raw.as_raw())
+    }
+}
+
+impl super::DebugName for Semaphore {
+    fn debug_handle(&self) -> (vk::ObjectType, u64) {
+        (vk::ObjectType::SEMAPHORE, self.raw.as_raw())
+    }
+}
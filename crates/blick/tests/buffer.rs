@@ -0,0 +1,126 @@
+mod common;
+
+#[test]
+fn create_buffer_init_uploads_data() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let data = (0..64u8).collect::<Vec<_>>();
+
+    // Uploaded through a staging buffer
+    let buffer = device.create_buffer_init(
+        blick::BufferUsage::STORAGE | blick::BufferUsage::TRANSFER_SRC,
+        &data,
+    ).unwrap();
+    assert_eq!(buffer.size(), data.len() as u64);
+    let readback = common::readback_buffer(device, buffer.size());
+    device.run_commands(|command_buffer| {
+        command_buffer.copy_buffer(&buffer, 0, &readback, 0, buffer.size());
+    }).unwrap();
+    assert_eq!(readback.read_slice::<u8>(0, data.len()).unwrap(), data);
+
+    // Written directly
+    for usage in [blick::BufferUsage::MAP_WRITE, blick::BufferUsage::MAP_READ] {
+        let buffer = device.create_buffer_init(usage, &data).unwrap();
+        assert_eq!(buffer.read_slice::<u8>(0, data.len()).unwrap(), data);
+    }
+}
+
+#[test]
+fn create_buffer_init_with_empty_data_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    assert!(device.create_buffer_init(blick::BufferUsage::STORAGE, &[]).is_err());
+    assert!(device.create_buffer_init(blick::BufferUsage::MAP_WRITE, &[]).is_err());
+}
+
+/// Size is the requested size, not the possibly larger allocation
+#[test]
+fn size_is_requested_size() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let buffer = device.create_buffer(blick::BufferDesc {
+        size: 1024,
+        usage: blick::BufferUsage::STORAGE | blick::BufferUsage::MAP_WRITE,
+        min_alignment: None,
+        memory_location: None,
+        name: None,
+    }).unwrap();
+    assert_eq!(buffer.size(), 1024);
+    assert_eq!(buffer.read_slice::<u8>(0, 1024).unwrap().len(), 1024);
+    assert!(buffer.read_slice::<u8>(0, 1025).is_err());
+}
+
+/// Offsets and counts that overflow the byte range are out of bounds rather
+/// than wrapping around
+#[test]
+fn overflowing_range_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let buffer = device.create_buffer_init(blick::BufferUsage::MAP_WRITE, &[0u8; 64]).unwrap();
+    assert!(buffer.read_slice::<u32>(usize::MAX / 2, 1).is_err());
+    assert!(buffer.read_slice::<u32>(1, usize::MAX / 4).is_err());
+    assert!(buffer.write_slice::<u8>(usize::MAX, &[0]).is_err());
+}
+
+#[test]
+fn name_with_interior_nul() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    device.create_buffer(blick::BufferDesc {
+        size: 16,
+        usage: blick::BufferUsage::STORAGE,
+        min_alignment: None,
+        memory_location: None,
+        name: Some("vertex\0buffer".to_owned()),
+    }).unwrap();
+}
+
+/// Small buffers are suballocated, without the override they would likely
+/// land on a less aligned offset
+#[test]
+fn min_alignment_is_respected() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let buffers = (0..8)
+        .map(|_| device.create_buffer(blick::BufferDesc {
+            size: 4,
+            usage: blick::BufferUsage::STORAGE | blick::BufferUsage::SHADER_DEVICE_ADDRESS,
+            min_alignment: Some(256),
+            memory_location: None,
+            name: None,
+        }).unwrap())
+        .collect::<Vec<_>>();
+    for buffer in &buffers {
+        assert_eq!(buffer.device_address() % 256, 0);
+    }
+}
+
+/// Allocations larger than any heap fail with an error callers can react to
+#[test]
+fn out_of_memory_is_an_error() {
+    // Exceeding the heap size is also a validation error
+    let Ok(backend) = blick::Backend::new_headless(blick::BackendConfig {
+        break_on_validation_error: false,
+        ..common::config()
+    }) else { return };
+    let device = backend.device();
+
+    let result = device.create_buffer(blick::BufferDesc {
+        size: 1 << 44,
+        usage: blick::BufferUsage::STORAGE,
+        min_alignment: None,
+        memory_location: None,
+        name: None,
+    });
+    let err = result.err().expect("Allocating 16 TiB succeeded");
+    assert!(matches!(
+        err.downcast_ref::<blick::ResourceError>(),
+        Some(blick::ResourceError::OutOfDeviceMemory)
+    ), "{}", err);
+}
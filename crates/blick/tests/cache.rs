@@ -0,0 +1,107 @@
+mod common;
+
+fn render_pass_desc(
+    color_attachments: &[Option<blick::ColorAttachmentDesc>],
+) -> blick::RenderPassDesc<'_> {
+    blick::RenderPassDesc {
+        color_attachments,
+        samples: blick::SampleCountFlags::TYPE_1,
+        resolve_attachments: &[],
+        subpasses: &[],
+        dependencies: &[],
+        depth_stencil_attachment: None,
+    }
+}
+
+fn color(format: blick::ImageFormat) -> [Option<blick::ColorAttachmentDesc>; 1] {
+    [Some(blick::ColorAttachmentDesc {
+        format,
+        layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    })]
+}
+
+#[test]
+fn zero_cache_size_is_an_error() {
+    // Only meaningful if creating a backend works at all
+    if common::backend().is_none() {
+        return;
+    }
+
+    let mut config = common::config();
+    config.render_pass_cache_size = 0;
+    assert!(blick::Backend::new_headless(config).is_err());
+
+    let mut config = common::config();
+    config.framebuffer_cache_size = 0;
+    assert!(blick::Backend::new_headless(config).is_err());
+}
+
+/// A cache of size 1 only keeps the most recent render pass
+#[test]
+fn render_pass_cache_evicts_least_recently_used() {
+    if common::backend().is_none() {
+        return;
+    }
+
+    let mut config = common::config();
+    config.render_pass_cache_size = 1;
+    let backend = blick::Backend::new_headless(config).unwrap();
+    let device = backend.device();
+
+    let a = color(blick::ImageFormat::R8G8B8A8_UNORM);
+    let b = color(blick::ImageFormat::R32_SFLOAT);
+
+    device.create_render_pass(render_pass_desc(&a)).unwrap();
+    device.create_render_pass(render_pass_desc(&a)).unwrap();
+    let stats = device.cache_stats();
+    assert_eq!((stats.render_pass_hits, stats.render_pass_misses), (1, 1));
+
+    // b evicts a, so a misses again
+    device.create_render_pass(render_pass_desc(&b)).unwrap();
+    device.create_render_pass(render_pass_desc(&a)).unwrap();
+    let stats = device.cache_stats();
+    assert_eq!((stats.render_pass_hits, stats.render_pass_misses), (1, 3));
+}
+
+/// Every frame uses more framebuffers than the cache holds and is left in
+/// flight, evicted framebuffers must live until their command buffer is done
+#[test]
+fn framebuffers_evicted_while_in_flight() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let extent = blick::Extent2d {
+        width: 4,
+        height: 4,
+    };
+    let render_pass = common::render_pass(device, None);
+    let count = blick::DEFAULT_CACHE_SIZE + 4;
+
+    let mut frames = Vec::new();
+    for _ in 0..4 {
+        let mut command_buffer = device
+            .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+            .unwrap();
+        command_buffer.begin();
+        for _ in 0..count {
+            let image = common::color_target(device, extent);
+            let view = common::view(device, &image, blick::ImageAspectFlags::COLOR);
+            let framebuffer = common::framebuffer(device, &render_pass, &[&view], extent);
+            command_buffer.begin_render_pass(&render_pass, &framebuffer, &extent.into());
+        }
+        command_buffer.end();
+
+        let fence = device.create_fence().unwrap();
+        device.submit(&[&command_buffer], &[], &[], Some(&fence)).unwrap();
+        frames.push((fence, device.end_frame()));
+    }
+
+    let stats = device.cache_stats();
+    assert_eq!(stats.framebuffer_misses, 4 * count as u64);
+
+    // Validation errors panic here
+    for (fence, frame) in frames {
+        device.wait(&fence).unwrap();
+        device.release_frame(frame);
+    }
+}
@@ -0,0 +1,315 @@
+#![allow(dead_code)]
+
+/// Headless backend with validation enabled. None if there is no Vulkan
+/// device, in which case the calling test is skipped.
+pub fn backend() -> Option<blick::Backend> {
+    match blick::Backend::new_headless(config()) {
+        Ok(backend) => Some(backend),
+        Err(err) => {
+            eprintln!("Skipping test, no Vulkan device: {}", err);
+            None
+        }
+    }
+}
+
+pub fn config() -> blick::BackendConfig {
+    blick::BackendConfig {
+        debugging: true,
+        device_selection: blick::DeviceSelection::default(),
+        transition_swapchain_images: false,
+        app_name: "blick tests".to_owned(),
+        app_version: (0, 1, 0),
+        engine_name: "blick".to_owned(),
+        engine_version: (0, 1, 0),
+        render_pass_cache_size: blick::DEFAULT_CACHE_SIZE,
+        framebuffer_cache_size: blick::DEFAULT_CACHE_SIZE,
+        renderdoc: false,
+        validation_severity: blick::DEFAULT_VALIDATION_SEVERITY,
+        validation_callback: None,
+        break_on_validation_error: true,
+        validation_features: blick::ValidationFeatures::empty(),
+    }
+}
+
+pub fn storage_buffer(device: &blick::Device, size: u64) -> blick::Buffer {
+    device.create_buffer(blick::BufferDesc {
+        size,
+        usage: blick::BufferUsage::STORAGE
+            | blick::BufferUsage::TRANSFER_SRC
+            | blick::BufferUsage::TRANSFER_DST,
+        min_alignment: None,
+        memory_location: None,
+        name: None,
+    }).unwrap()
+}
+
+/// Host readable storage buffer, for results written by shaders
+pub fn readback_buffer(device: &blick::Device, size: u64) -> blick::Buffer {
+    device.create_buffer(blick::BufferDesc {
+        size,
+        usage: blick::BufferUsage::STORAGE
+            | blick::BufferUsage::TRANSFER_DST
+            | blick::BufferUsage::MAP_READ,
+        min_alignment: None,
+        memory_location: None,
+        name: None,
+    }).unwrap()
+}
+
+/// Layout with count storage buffers at bindings 0..count
+pub fn storage_layout(device: &blick::Device, count: u32) -> blick::DescriptorSetLayout {
+    let entries = (0..count)
+        .map(|binding| blick::DescriptorSetLayoutEntry {
+            binding,
+            stage_flags: blick::ShaderStageFlags::COMPUTE,
+            ty: blick::DescriptorType::STORAGE_BUFFER,
+            count: 1,
+            flags: blick::DescriptorBindingFlags::empty(),
+        })
+        .collect::<Vec<_>>();
+    device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &entries,
+        }
+    ).unwrap()
+}
+
+pub fn compute_pipeline(
+    device: &blick::Device,
+    hlsl: &str,
+    layout: &blick::DescriptorSetLayout,
+) -> blick::ComputePipeline {
+    device.create_compute_pipeline(
+        blick::ComputePipelineDesc {
+            shader_module: blick::ShaderModuleDesc {
+                source: blick::ShaderSource::Hlsl(hlsl),
+                stage: blick::ShaderStageFlags::COMPUTE,
+                specialization: &[],
+            },
+            descriptor_set_layouts: &[layout],
+            push_constant_ranges: &[],
+        }
+    ).unwrap()
+}
+
+/// Copies mip_level of a 2D color image into host memory, the image has to
+/// be in layout and is left in it
+pub fn read_image(
+    device: &blick::Device,
+    image: &blick::Image,
+    layout: blick::ImageLayout,
+    mip_level: u32,
+) -> Vec<u8> {
+    let extent = blick::Extent3d {
+        width: (image.desc.extent.width >> mip_level).max(1),
+        height: (image.desc.extent.height >> mip_level).max(1),
+        depth: 1,
+    };
+    let buffer = readback_buffer(device, 4 * extent.width as u64 * extent.height as u64);
+
+    let barrier = |old_layout, new_layout| blick::ImageBarrier {
+        image,
+        src_access_mask: blick::AccessFlags::MEMORY_WRITE,
+        dst_access_mask: blick::AccessFlags::MEMORY_READ,
+        old_layout,
+        new_layout,
+        subresource_range: blick::ImageSubresourceRange::mip_level(
+            blick::ImageAspectFlags::COLOR,
+            mip_level,
+        ),
+        queue_transfer: None,
+    };
+    device.run_commands(|command_buffer| {
+        command_buffer.transition(
+            &[],
+            &[],
+            &[barrier(layout, blick::ImageLayout::TRANSFER_SRC_OPTIMAL)],
+            blick::PipelineStageFlags::ALL_COMMANDS,
+            blick::PipelineStageFlags::TRANSFER,
+        );
+        command_buffer.copy_image_to_buffer(
+            image,
+            blick::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            mip_level,
+            extent,
+            &buffer,
+            0,
+        );
+        command_buffer.transition(
+            &[],
+            &[],
+            &[barrier(blick::ImageLayout::TRANSFER_SRC_OPTIMAL, layout)],
+            blick::PipelineStageFlags::TRANSFER,
+            blick::PipelineStageFlags::ALL_COMMANDS,
+        );
+    }).unwrap();
+
+    buffer.read_slice(0, buffer.size() as usize).unwrap()
+}
+
+pub const COLOR_FORMAT: blick::ImageFormat = blick::ImageFormat::R8G8B8A8_UNORM;
+
+/// Single triangle covering the whole target at depth 0
+pub const FULLSCREEN_VS: &str = include_str!("../../../../assets/shaders/fullscreen_vs.hlsl");
+
+/// Outputs the color pushed at offset 0
+pub const SOLID_PS: &str = r#"
+struct PushConstants {
+    float4 color;
+};
+
+[[vk::push_constant]] PushConstants push_constants;
+
+float4 main() : SV_TARGET0 {
+    return push_constants.color;
+}
+"#;
+
+/// Renderable and readable with read_image
+pub fn color_target(device: &blick::Device, extent: blick::Extent2d) -> blick::Image {
+    image_2d(
+        device,
+        COLOR_FORMAT,
+        extent,
+        blick::ImageUsage::COLOR_ATTACHMENT | blick::ImageUsage::TRANSFER_SRC,
+    )
+}
+
+pub fn image_2d(
+    device: &blick::Device,
+    format: blick::ImageFormat,
+    extent: blick::Extent2d,
+    usage: blick::ImageUsage,
+) -> blick::Image {
+    device.create_image(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format,
+            extent: blick::Extent3d {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage,
+            mutable_format: false,
+            cube_compatible: false,
+            name: None,
+        }
+    ).unwrap()
+}
+
+/// View of the first mip level and layer
+pub fn view(
+    device: &blick::Device,
+    image: &blick::Image,
+    aspect_mask: blick::ImageAspectFlags,
+) -> blick::ImageView {
+    device.create_image_view(
+        image,
+        blick::ImageViewDesc {
+            view_type: blick::ImageViewType::TYPE_2D,
+            aspect_mask,
+            format: image.desc.format,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    ).unwrap()
+}
+
+/// Pipeline drawing vs with SOLID_PS, configure adjusts the fixed function
+/// state
+pub fn solid_pipeline(
+    device: &blick::Device,
+    target: blick::RenderTarget,
+    vs: &str,
+    configure: impl FnOnce(&mut blick::GraphicsPipelineDesc),
+) -> anyhow::Result<blick::GraphicsPipeline> {
+    let mut desc = blick::GraphicsPipelineDesc {
+        shader_modules: &[
+            blick::ShaderModuleDesc {
+                source: blick::ShaderSource::Hlsl(vs),
+                stage: blick::ShaderStageFlags::VERTEX,
+                specialization: &[],
+            },
+            blick::ShaderModuleDesc {
+                source: blick::ShaderSource::Hlsl(SOLID_PS),
+                stage: blick::ShaderStageFlags::FRAGMENT,
+                specialization: &[],
+            },
+        ],
+        descriptor_set_layouts: &[],
+        push_constant_ranges: &[
+            blick::PushConstantRange {
+                stage_flags: blick::ShaderStageFlags::FRAGMENT,
+                offset: 0,
+                size: 16,
+            },
+        ],
+        target,
+        subpass: 0,
+        samples: blick::SampleCountFlags::TYPE_1,
+        stencil: blick::StencilState::default(),
+        depth_bias: None,
+        blend: None,
+        dynamic_states: blick::DynamicStates::empty(),
+    };
+    configure(&mut desc);
+    device.create_graphics_pipeline(desc)
+}
+
+/// Single subpass with one color attachment and an optional depth stencil
+/// attachment, both left in their attachment layouts
+pub fn render_pass(
+    device: &blick::Device,
+    depth_format: Option<blick::ImageFormat>,
+) -> blick::RenderPass {
+    device.create_render_pass(
+        blick::RenderPassDesc {
+            color_attachments: &[
+                Some(blick::ColorAttachmentDesc {
+                    format: COLOR_FORMAT,
+                    layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                }),
+            ],
+            samples: blick::SampleCountFlags::TYPE_1,
+            resolve_attachments: &[],
+            subpasses: &[],
+            dependencies: &[],
+            depth_stencil_attachment: depth_format.map(|format| {
+                blick::DepthStencilAttachmentDesc {
+                    format,
+                    layout: blick::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                }
+            }),
+        }
+    ).unwrap()
+}
+
+pub fn texel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+    let offset = 4 * (y * width + x) as usize;
+    pixels[offset..offset + 4].try_into().unwrap()
+}
+
+pub fn framebuffer(
+    device: &blick::Device,
+    render_pass: &blick::RenderPass,
+    views: &[&blick::ImageView],
+    extent: blick::Extent2d,
+) -> blick::Framebuffer {
+    let attachments = views
+        .iter()
+        .map(|&image_view| blick::Attachment { image_view })
+        .collect::<Vec<_>>();
+    device.create_framebuffer(
+        blick::FramebufferDesc {
+            render_pass,
+            attachments: &attachments,
+            extent,
+        }
+    ).unwrap()
+}
@@ -0,0 +1,21 @@
+mod common;
+
+/// Unbalanced labels are reported by the validation layer on submit, raised
+/// as a panic when run_commands waits
+#[test]
+fn debug_scopes_are_balanced() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let src = common::storage_buffer(device, 64);
+    let dst = common::storage_buffer(device, 64);
+    device.run_commands(|command_buffer| {
+        let mut outer = command_buffer.debug_scope("outer");
+        {
+            let mut inner = outer.debug_scope("inner");
+            inner.insert_debug_label("copy");
+            inner.copy_buffer(&src, 0, &dst, 0, 64);
+        }
+        outer.insert_debug_label("after inner");
+    }).unwrap();
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::collections::VecDeque;
+
+const FRAMES_IN_FLIGHT: usize = 2;
+
+/// Drops every resource of a frame right after submitting it, while up to
+/// FRAMES_IN_FLIGHT frames are still executing. Validation errors about
+/// objects destroyed while in use fail the test.
+#[test]
+fn drop_resources_while_in_flight() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let mut in_flight = VecDeque::new();
+    for _ in 0..16 {
+        let mut command_buffer = device
+            .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+            .unwrap();
+        let src = common::storage_buffer(device, 1 << 20);
+        let dst = common::storage_buffer(device, 1 << 20);
+        let layout = device.create_descriptor_set_layout(
+            blick::DescriptorSetLayoutDesc {
+                entries: &[
+                    blick::DescriptorSetLayoutEntry {
+                        binding: 0,
+                        stage_flags: blick::ShaderStageFlags::COMPUTE,
+                        ty: blick::DescriptorType::STORAGE_BUFFER,
+                        count: 1,
+                        flags: blick::DescriptorBindingFlags::empty(),
+                    },
+                ],
+            }
+        ).unwrap();
+        let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+        let semaphore = device.create_semaphore().unwrap();
+        let fence = device.create_fence().unwrap();
+
+        command_buffer.begin();
+        command_buffer.copy_buffer(&src, 0, &dst, 0, src.size());
+        command_buffer.end();
+        device.submit(&[&command_buffer], &[], &[&semaphore], Some(&fence)).unwrap();
+
+        // Nothing waits for the submission before these are dropped
+        drop((command_buffer, src, dst, layout, descriptor_set, semaphore));
+
+        in_flight.push_back((device.end_frame(), fence));
+        if in_flight.len() > FRAMES_IN_FLIGHT {
+            let (frame, fence) = in_flight.pop_front().unwrap();
+            device.wait(&fence).unwrap();
+            device.release_frame(frame);
+        }
+    }
+
+    device.wait_idle().unwrap();
+    for (frame, _) in in_flight {
+        device.release_frame(frame);
+    }
+}
+
+/// Transient objects of run_commands and the upload helpers are known idle
+/// once they return, nothing should pile up without a frame loop
+#[test]
+fn run_commands_does_not_defer() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let pending = device.pending_destructions();
+    for _ in 0..64 {
+        device.run_commands(|_| {}).unwrap();
+    }
+    assert_eq!(device.pending_destructions(), pending);
+
+    for _ in 0..64 {
+        let buffer = device
+            .create_buffer_init(blick::BufferUsage::STORAGE, &[0u8; 256])
+            .unwrap();
+        drop(buffer);
+    }
+    // Only the dropped buffers themselves are deferred, not their staging
+    assert_eq!(device.pending_destructions(), pending + 64);
+
+    device.wait_idle().unwrap();
+    assert_eq!(device.pending_destructions(), 0);
+}
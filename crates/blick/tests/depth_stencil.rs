@@ -0,0 +1,178 @@
+mod common;
+
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 16,
+    height: 16,
+};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// Color target and a depth attachment of format, with a render pass and
+/// framebuffer for them
+struct Target {
+    color: blick::Image,
+    /// Kept alive for the framebuffer
+    _depth: blick::Image,
+    render_pass: blick::RenderPass,
+    framebuffer: blick::Framebuffer,
+}
+
+fn target(device: &blick::Device, depth_format: blick::ImageFormat) -> Target {
+    let color = common::color_target(device, EXTENT);
+    let depth = common::image_2d(
+        device,
+        depth_format,
+        EXTENT,
+        blick::ImageUsage::DEPTH_STENCIL_ATTACHMENT,
+    );
+    let aspect_mask = match depth_format {
+        blick::ImageFormat::D16_UNORM | blick::ImageFormat::D32_SFLOAT => {
+            blick::ImageAspectFlags::DEPTH
+        }
+        _ => blick::ImageAspectFlags::DEPTH | blick::ImageAspectFlags::STENCIL,
+    };
+    let color_view = common::view(device, &color, blick::ImageAspectFlags::COLOR);
+    let depth_view = common::view(device, &depth, aspect_mask);
+    let render_pass = common::render_pass(device, Some(depth_format));
+    let framebuffer = common::framebuffer(
+        device,
+        &render_pass,
+        &[&color_view, &depth_view],
+        EXTENT,
+    );
+
+    Target {
+        color,
+        _depth: depth,
+        render_pass,
+        framebuffer,
+    }
+}
+
+/// The first draw writes reference 1 to the left half, the second only passes
+/// where the stencil equals 1
+#[test]
+fn stencil_masks_second_draw() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    // Vulkan requires one of these to be usable as depth stencil attachment
+    let format = device.supported_format(
+        &[
+            blick::ImageFormat::D24_UNORM_S8_UINT,
+            blick::ImageFormat::D32_SFLOAT_S8_UINT,
+        ],
+        blick::ImageTiling::OPTIMAL,
+        blick::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+    ).unwrap();
+    let target = target(device, format);
+
+    let stencil = |face| blick::StencilState {
+        test_enable: true,
+        front: face,
+        back: face,
+    };
+    let write = common::solid_pipeline(
+        device,
+        blick::RenderTarget::RenderPass(&target.render_pass),
+        common::FULLSCREEN_VS,
+        |desc| desc.stencil = stencil(blick::StencilFaceState {
+            pass_op: blick::StencilOp::REPLACE,
+            reference: 1,
+            ..Default::default()
+        }),
+    ).unwrap();
+    let test = common::solid_pipeline(
+        device,
+        blick::RenderTarget::RenderPass(&target.render_pass),
+        common::FULLSCREEN_VS,
+        |desc| desc.stencil = stencil(blick::StencilFaceState {
+            compare_op: blick::CompareOp::EQUAL,
+            reference: 1,
+            ..Default::default()
+        }),
+    ).unwrap();
+
+    let render_area = blick::Rect::from(EXTENT);
+    let half = EXTENT.width / 2;
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_render_pass(&target.render_pass, &target.framebuffer, &render_area)
+            .bind_pipeline(&write)
+            .set_viewport_and_scissor(&render_area)
+            .set_scissor(&blick::Rect::new(0, 0, half, EXTENT.height))
+            .push_constants_typed(0, &[1.0f32, 0.0, 0.0, 1.0])
+            .draw(3, 1, 0, 0)
+            .bind_pipeline(&test)
+            .set_scissor(&render_area)
+            .push_constants_typed(0, &[1.0f32; 4])
+            .draw(3, 1, 0, 0);
+    }).unwrap();
+
+    let pixels = common::read_image(
+        device,
+        &target.color,
+        blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        0,
+    );
+    assert_eq!(common::texel(&pixels, EXTENT.width, 0, 8), WHITE);
+    assert_eq!(common::texel(&pixels, EXTENT.width, half - 1, 8), WHITE);
+    assert_eq!(common::texel(&pixels, EXTENT.width, half, 8), BLACK);
+}
+
+/// Both draws are at depth 0, only the constant bias of the second lets it
+/// pass GREATER. Depth testing is enabled through extended dynamic state, so
+/// without it the test only checks that the pipeline builds and draws.
+#[test]
+fn depth_bias_offsets_depth() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    // D16_UNORM makes the constant factor a multiple of 1 / 65535
+    let target = target(device, blick::ImageFormat::D16_UNORM);
+    let pipeline = common::solid_pipeline(
+        device,
+        blick::RenderTarget::RenderPass(&target.render_pass),
+        common::FULLSCREEN_VS,
+        |desc| desc.depth_bias = Some(blick::DepthBias {
+            constant_factor: 0.0,
+            clamp: 0.0,
+            slope_factor: 2.0,
+        }),
+    ).unwrap();
+
+    let test_depth = device.supports_extended_dynamic_state();
+    let render_area = blick::Rect::from(EXTENT);
+    device.run_commands(|command_buffer| {
+        let pass = command_buffer
+            .begin_render_pass(&target.render_pass, &target.framebuffer, &render_area)
+            .bind_pipeline(&pipeline)
+            .set_viewport_and_scissor(&render_area);
+        let pass = if test_depth {
+            pass.set_depth_test_enable(true)
+                .set_depth_write_enable(true)
+                .set_depth_compare_op(blick::CompareOp::ALWAYS)
+                .push_constants_typed(0, &[1.0f32, 0.0, 0.0, 1.0])
+                .draw(3, 1, 0, 0)
+                .set_depth_compare_op(blick::CompareOp::GREATER)
+        } else {
+            pass
+        };
+        pass
+            .set_depth_bias(&blick::DepthBias {
+                constant_factor: 16.0,
+                clamp: 0.0,
+                slope_factor: 2.0,
+            })
+            .push_constants_typed(0, &[1.0f32; 4])
+            .draw(3, 1, 0, 0);
+    }).unwrap();
+
+    let pixels = common::read_image(
+        device,
+        &target.color,
+        blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        0,
+    );
+    assert_eq!(common::texel(&pixels, EXTENT.width, 8, 8), WHITE);
+}
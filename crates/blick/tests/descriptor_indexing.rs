@@ -0,0 +1,222 @@
+mod common;
+
+const COUNT: u32 = 64;
+const ELEMENT: u32 = 37;
+
+const SHADER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> buffers[64];
+[[vk::binding(1, 0)]] RWStructuredBuffer<uint> result;
+
+[numthreads(1, 1, 1)]
+void main() {
+    result[0] = buffers[37][0];
+}
+"#;
+
+/// Fills an array binding of 64 buffers, element 37 is written after the
+/// set has been bound
+#[test]
+fn read_element_of_buffer_array() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    if !device.supports_update_after_bind(blick::DescriptorType::STORAGE_BUFFER) {
+        eprintln!("Skipping test, UPDATE_AFTER_BIND not supported for storage buffers");
+        return;
+    }
+
+    let layout = device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::STORAGE_BUFFER,
+                    count: COUNT,
+                    flags: blick::DescriptorBindingFlags::PARTIALLY_BOUND
+                        | blick::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+                },
+                blick::DescriptorSetLayoutEntry {
+                    binding: 1,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::STORAGE_BUFFER,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+            ],
+        }
+    ).unwrap();
+
+    let buffers = (0..COUNT)
+        .map(|i| {
+            let buffer = device.create_buffer(blick::BufferDesc {
+                size: 4,
+                usage: blick::BufferUsage::STORAGE | blick::BufferUsage::MAP_WRITE,
+                min_alignment: None,
+                memory_location: None,
+                name: None,
+            }).unwrap();
+            buffer.write_slice(0, &[i * 10]).unwrap();
+            buffer
+        })
+        .collect::<Vec<_>>();
+    let result = common::readback_buffer(device, 4);
+
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    let resources = buffers
+        .iter()
+        .map(blick::DescriptorResource::whole_buffer)
+        .collect::<Vec<_>>();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &resources[..ELEMENT as usize],
+            },
+            blick::Descriptor {
+                binding: 0,
+                array_element: ELEMENT + 1,
+                resources: &resources[ELEMENT as usize + 1..],
+            },
+            blick::Descriptor {
+                binding: 1,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&result)],
+            },
+        ],
+    ).unwrap();
+
+    let pipeline = common::compute_pipeline(device, SHADER, &layout);
+
+    device.run_commands(|command_buffer| {
+        let pass = command_buffer.begin_compute_pass()
+            .bind_pipeline(&pipeline)
+            .bind_descriptor_set(0, &descriptor_set);
+
+        device.update_descriptor_set(
+            &descriptor_set,
+            &[
+                blick::Descriptor {
+                    binding: 0,
+                    array_element: ELEMENT,
+                    resources: &resources[ELEMENT as usize..ELEMENT as usize + 1],
+                },
+            ],
+        ).unwrap();
+
+        pass.dispatch(1, 1, 1);
+    }).unwrap();
+
+    assert_eq!(result.read_slice::<u32>(0, 1).unwrap(), [ELEMENT * 10]);
+}
+
+#[test]
+fn update_after_bind_requires_device_support() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    // Input attachments can never be updated after bind
+    let layout = device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::FRAGMENT,
+                    ty: blick::DescriptorType::INPUT_ATTACHMENT,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+                },
+            ],
+        }
+    );
+    assert!(layout.is_err());
+}
+
+const RUNTIME_ARRAY_SHADER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> result;
+[[vk::binding(1, 0)]] RWStructuredBuffer<uint> buffers[];
+
+[numthreads(1, 1, 1)]
+void main() {
+    result[0] = buffers[5][0];
+}
+"#;
+
+/// An unbounded array in the shader needs runtimeDescriptorArray to be
+/// enabled on the device, validation would fail the pipeline otherwise
+#[test]
+fn runtime_descriptor_array_is_enabled() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let layout = device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::STORAGE_BUFFER,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+                blick::DescriptorSetLayoutEntry {
+                    binding: 1,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::STORAGE_BUFFER,
+                    count: 8,
+                    flags: blick::DescriptorBindingFlags::PARTIALLY_BOUND
+                        | blick::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT,
+                },
+            ],
+        }
+    ).unwrap();
+
+    let buffers = (0..8u32)
+        .map(|i| {
+            let buffer = device.create_buffer(blick::BufferDesc {
+                size: 4,
+                usage: blick::BufferUsage::STORAGE | blick::BufferUsage::MAP_WRITE,
+                min_alignment: None,
+                memory_location: None,
+                name: None,
+            }).unwrap();
+            buffer.write_slice(0, &[i + 100]).unwrap();
+            buffer
+        })
+        .collect::<Vec<_>>();
+    let result = common::readback_buffer(device, 4);
+
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    let resources = buffers
+        .iter()
+        .map(blick::DescriptorResource::whole_buffer)
+        .collect::<Vec<_>>();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&result)],
+            },
+            blick::Descriptor {
+                binding: 1,
+                array_element: 0,
+                resources: &resources,
+            },
+        ],
+    ).unwrap();
+
+    let pipeline = common::compute_pipeline(device, RUNTIME_ARRAY_SHADER, &layout);
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&pipeline)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+    }).unwrap();
+
+    assert_eq!(result.read_slice::<u32>(0, 1).unwrap(), [105]);
+}
@@ -0,0 +1,201 @@
+mod common;
+
+fn layout(device: &blick::Device, ty: blick::DescriptorType) -> blick::DescriptorSetLayout {
+    device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+            ],
+        }
+    ).unwrap()
+}
+
+fn write(
+    device: &blick::Device,
+    descriptor_set: &blick::DescriptorSet,
+    resource: blick::DescriptorResource<'_>,
+) -> anyhow::Result<()> {
+    device.update_descriptor_set(
+        descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[resource],
+            },
+        ],
+    )
+}
+
+#[test]
+fn buffer_for_image_binding_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let buffer = common::storage_buffer(device, 256);
+    let layout = layout(device, blick::DescriptorType::STORAGE_IMAGE);
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+
+    let err = write(device, &descriptor_set, blick::DescriptorResource::whole_buffer(&buffer))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("STORAGE_IMAGE"), "{}", err);
+    assert!(err.contains("buffer resource"), "{}", err);
+}
+
+#[test]
+fn buffer_range_must_fit_buffer() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let buffer = common::storage_buffer(device, 256);
+    let layout = layout(device, blick::DescriptorType::STORAGE_BUFFER);
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+
+    let in_bounds = blick::DescriptorResource::buffer_range(&buffer, 128, 64);
+    assert!(write(device, &descriptor_set, in_bounds).is_ok());
+
+    let overrun = blick::DescriptorResource::buffer_range(&buffer, 224, 64);
+    assert!(write(device, &descriptor_set, overrun).is_err());
+
+    let overflow = blick::DescriptorResource::buffer_range(&buffer, 128, u64::MAX - 64);
+    assert!(write(device, &descriptor_set, overflow).is_err());
+}
+
+const IMAGE_ARRAY_SHADER: &str = r#"
+[[vk::binding(0, 0)]] Texture2D<float4> images[8];
+[[vk::binding(1, 0)]] RWStructuredBuffer<float4> result;
+
+[numthreads(3, 1, 1)]
+void main(uint thread_id : SV_DispatchThreadID) {
+    result[thread_id] = images[thread_id + 2].Load(int3(0, 0, 0));
+}
+"#;
+
+/// Elements 2..5 of an image array are written by a single descriptor
+#[test]
+fn write_image_array_range() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let layout = device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::SAMPLED_IMAGE,
+                    count: 8,
+                    flags: blick::DescriptorBindingFlags::PARTIALLY_BOUND,
+                },
+                blick::DescriptorSetLayoutEntry {
+                    binding: 1,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::STORAGE_BUFFER,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+            ],
+        }
+    ).unwrap();
+
+    let texels: [[u8; 4]; 3] = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+    let images = texels
+        .iter()
+        .map(|texel| device.create_image_init(
+            blick::ImageDesc {
+                image_type: blick::ImageType::TYPE_2D,
+                format: common::COLOR_FORMAT,
+                extent: blick::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth: 1,
+                },
+                mip_levels: 1,
+                array_layers: 1,
+                samples: blick::SampleCountFlags::TYPE_1,
+                usage: blick::ImageUsage::SAMPLED,
+                mutable_format: false,
+                cube_compatible: false,
+                name: None,
+            },
+            texel,
+        ).unwrap())
+        .collect::<Vec<_>>();
+    let views = images
+        .iter()
+        .map(|image| common::view(device, image, blick::ImageAspectFlags::COLOR))
+        .collect::<Vec<_>>();
+    let resources = views
+        .iter()
+        .map(|view| blick::DescriptorResource::Image {
+            view,
+            layout: blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        })
+        .collect::<Vec<_>>();
+    let result = common::readback_buffer(device, 3 * 16);
+
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 2,
+                resources: &resources,
+            },
+            blick::Descriptor {
+                binding: 1,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&result)],
+            },
+        ],
+    ).unwrap();
+
+    let pipeline = common::compute_pipeline(device, IMAGE_ARRAY_SHADER, &layout);
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&pipeline)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+    }).unwrap();
+
+    let expected = texels
+        .iter()
+        .flatten()
+        .map(|&value| value as f32 / 255.0)
+        .collect::<Vec<_>>();
+    assert_eq!(result.read_slice::<f32>(0, 12).unwrap(), expected);
+}
+
+#[test]
+fn array_range_past_binding_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let buffer = common::storage_buffer(device, 256);
+    let layout = layout(device, blick::DescriptorType::STORAGE_BUFFER);
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+
+    let result = device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[
+                    blick::DescriptorResource::whole_buffer(&buffer),
+                    blick::DescriptorResource::whole_buffer(&buffer),
+                ],
+            },
+        ],
+    );
+    assert!(result.is_err());
+}
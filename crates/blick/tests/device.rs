@@ -0,0 +1,75 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Collects the messages logged by blick
+struct Logger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.messages.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger {
+    messages: Mutex::new(Vec::new()),
+};
+
+/// The allocator holds its own clone of the ash Device, it must be dropped
+/// with its allocations before the device is destroyed. Leaked memory or
+/// objects are reported by the validation layer on vkDestroyDevice.
+#[test]
+fn devices_created_and_dropped_in_a_loop() {
+    let errors = Arc::new(AtomicUsize::new(0));
+
+    for _ in 0..16 {
+        let backend = blick::Backend::new_headless(blick::BackendConfig {
+            break_on_validation_error: false,
+            validation_callback: Some(Box::new({
+                let errors = errors.clone();
+                move |severity, message| {
+                    if severity == blick::ValidationSeverity::ERROR {
+                        eprintln!("{}", message);
+                        errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            })),
+            ..common::config()
+        });
+        let Ok(backend) = backend else { return };
+        let device = backend.device();
+
+        let _buffer = common::storage_buffer(device, 1024);
+        let _image = common::color_target(device, blick::Extent2d { width: 4, height: 4 });
+        device.wait_idle().unwrap();
+    }
+
+    assert_eq!(errors.load(Ordering::Relaxed), 0);
+}
+
+/// The logger is global, other tests in this binary select the same device
+#[test]
+fn info_name_matches_logged_device() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+
+    let Some(backend) = common::backend() else { return };
+    let info = backend.device_info();
+
+    let messages = LOGGER.messages.lock().unwrap();
+    let logged = messages
+        .iter()
+        .find_map(|message| message.strip_prefix("Using device: "))
+        .expect("Selected device wasn't logged");
+    assert_eq!(logged, info.name);
+    assert!(info.limits.max_image_dimension_2d >= 4096);
+}
@@ -0,0 +1,130 @@
+mod common;
+
+const SHADER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> data;
+
+[numthreads(8, 8, 1)]
+void main(uint3 group_id : SV_GroupID, uint3 group_thread_id : SV_GroupThreadID) {
+    if (all(group_thread_id == 0)) {
+        InterlockedAdd(data[0], 1);
+        InterlockedMax(data[1], group_id.x + 1);
+        InterlockedMax(data[2], group_id.y + 1);
+    }
+}
+"#;
+
+/// 100 isn't a multiple of 8, the partial groups at the edges are dispatched
+/// as well
+#[test]
+fn dispatch_for_rounds_up() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let layout = common::storage_layout(device, 1);
+    let pipeline = common::compute_pipeline(device, SHADER, &layout);
+
+    let data = device.create_buffer_init(
+        blick::BufferUsage::STORAGE | blick::BufferUsage::MAP_READ,
+        &[0u8; 12],
+    ).unwrap();
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&data)],
+            },
+        ],
+    ).unwrap();
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&pipeline)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch_for(
+                blick::Extent3d {
+                    width: 100,
+                    height: 100,
+                    depth: 1,
+                },
+                (8, 8, 1),
+            );
+    }).unwrap();
+
+    assert_eq!(data.read_slice::<u32>(0, 3).unwrap(), [13 * 13, 13, 13]);
+}
+
+const WRITE_SHADER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> data;
+
+[numthreads(64, 1, 1)]
+void main(uint thread_id : SV_DispatchThreadID) {
+    data[thread_id] = thread_id;
+}
+"#;
+
+const DOUBLE_SHADER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> data;
+[[vk::binding(1, 0)]] RWStructuredBuffer<uint> doubled;
+
+[numthreads(64, 1, 1)]
+void main(uint thread_id : SV_DispatchThreadID) {
+    // Reads a value written by another invocation of the first dispatch
+    doubled[thread_id] = 2 * data[63 - thread_id];
+}
+"#;
+
+/// The second dispatch reads what the first one wrote, ordered by a global
+/// memory barrier rather than one per buffer
+#[test]
+fn memory_barrier_between_dispatches() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let layout = common::storage_layout(device, 2);
+    let write = common::compute_pipeline(device, WRITE_SHADER, &layout);
+    let double = common::compute_pipeline(device, DOUBLE_SHADER, &layout);
+
+    let data = common::storage_buffer(device, 64 * 4);
+    let doubled = common::readback_buffer(device, 64 * 4);
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&data)],
+            },
+            blick::Descriptor {
+                binding: 1,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&doubled)],
+            },
+        ],
+    ).unwrap();
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&write)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+        command_buffer.transition(
+            &[blick::MemoryBarrier {
+                src_access_mask: blick::AccessFlags::SHADER_WRITE,
+                dst_access_mask: blick::AccessFlags::SHADER_READ,
+            }],
+            &[],
+            &[],
+            blick::PipelineStageFlags::COMPUTE_SHADER,
+            blick::PipelineStageFlags::COMPUTE_SHADER,
+        );
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&double)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+    }).unwrap();
+
+    let expected = (0..64u32).rev().map(|value| 2 * value).collect::<Vec<_>>();
+    assert_eq!(doubled.read_slice::<u32>(0, 64).unwrap(), expected);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 16,
+    height: 16,
+};
+
+const OBJECT_PS: &str = r#"
+struct Object {
+    float4 color;
+};
+[[vk::binding(0, 0)]] ConstantBuffer<Object> object;
+
+float4 main() : SV_Target {
+    return object.color;
+}
+"#;
+
+/// Left and right half are drawn with the same descriptor set, the dynamic
+/// offset selects the color of each draw
+#[test]
+fn draw_objects_at_different_offsets() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let layout = device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::FRAGMENT,
+                    ty: blick::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+            ],
+        }
+    ).unwrap();
+
+    let stride = device.info().limits.min_uniform_buffer_offset_alignment.max(16);
+    let objects = device.create_buffer(blick::BufferDesc {
+        size: 2 * stride,
+        usage: blick::BufferUsage::UNIFORM | blick::BufferUsage::MAP_WRITE,
+        min_alignment: None,
+        memory_location: None,
+        name: None,
+    }).unwrap();
+    objects.write_slice(0, &[1.0f32, 0.0, 0.0, 1.0]).unwrap();
+    objects.write_slice(stride as usize, &[0.0f32, 1.0, 0.0, 1.0]).unwrap();
+
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::buffer_range(&objects, 0, 16)],
+            },
+        ],
+    ).unwrap();
+
+    let render_pass = common::render_pass(device, None);
+    let pipeline = device.create_graphics_pipeline(
+        blick::GraphicsPipelineDesc {
+            shader_modules: &[
+                blick::ShaderModuleDesc {
+                    source: blick::ShaderSource::Hlsl(common::FULLSCREEN_VS),
+                    stage: blick::ShaderStageFlags::VERTEX,
+                    specialization: &[],
+                },
+                blick::ShaderModuleDesc {
+                    source: blick::ShaderSource::Hlsl(OBJECT_PS),
+                    stage: blick::ShaderStageFlags::FRAGMENT,
+                    specialization: &[],
+                },
+            ],
+            descriptor_set_layouts: &[&layout],
+            push_constant_ranges: &[],
+            target: blick::RenderTarget::RenderPass(&render_pass),
+            subpass: 0,
+            samples: blick::SampleCountFlags::TYPE_1,
+            stencil: blick::StencilState::default(),
+            depth_bias: None,
+            blend: None,
+            dynamic_states: blick::DynamicStates::empty(),
+        }
+    ).unwrap();
+
+    let color = common::color_target(device, EXTENT);
+    let view = common::view(device, &color, blick::ImageAspectFlags::COLOR);
+    let framebuffer = common::framebuffer(device, &render_pass, &[&view], EXTENT);
+    let render_area = blick::Rect::from(EXTENT);
+    let half = EXTENT.width / 2;
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_render_pass(&render_pass, &framebuffer, &render_area)
+            .bind_pipeline(&pipeline)
+            .set_viewport_and_scissor(&render_area)
+            .set_scissor(&blick::Rect::new(0, 0, half, EXTENT.height))
+            .bind_descriptor_set_with_offsets(0, &descriptor_set, &[0])
+            .draw(3, 1, 0, 0)
+            .set_scissor(&blick::Rect::new(half, 0, half, EXTENT.height))
+            .bind_descriptor_set_with_offsets(0, &descriptor_set, &[stride as u32])
+            .draw(3, 1, 0, 0);
+    }).unwrap();
+
+    let pixels = common::read_image(
+        device,
+        &color,
+        blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        0,
+    );
+    assert_eq!(common::texel(&pixels, EXTENT.width, 0, 0), [255, 0, 0, 255]);
+    assert_eq!(common::texel(&pixels, EXTENT.width, half - 1, 8), [255, 0, 0, 255]);
+    assert_eq!(common::texel(&pixels, EXTENT.width, half, 8), [0, 255, 0, 255]);
+    assert_eq!(common::texel(&pixels, EXTENT.width, EXTENT.width - 1, 15), [0, 255, 0, 255]);
+}
@@ -0,0 +1,122 @@
+mod common;
+
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 16,
+    height: 16,
+};
+const DEPTH_FORMAT: blick::ImageFormat = blick::ImageFormat::D32_SFLOAT;
+
+fn attachment(
+    view: &blick::ImageView,
+    layout: blick::ImageLayout,
+) -> blick::RenderingAttachment<'_> {
+    blick::RenderingAttachment {
+        image_view: view,
+        layout,
+        load_op: blick::AttachmentLoadOp::CLEAR,
+        store_op: blick::AttachmentStoreOp::STORE,
+        clear_color: blick::ClearColor::BLACK,
+    }
+}
+
+/// Draws into color and depth attachments without a render pass, or fails
+/// cleanly on devices without dynamic rendering
+#[test]
+fn render_with_depth_attachment() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let color = common::color_target(device, EXTENT);
+    let depth = common::image_2d(
+        device,
+        DEPTH_FORMAT,
+        EXTENT,
+        blick::ImageUsage::DEPTH_STENCIL_ATTACHMENT,
+    );
+    let color_view = common::view(device, &color, blick::ImageAspectFlags::COLOR);
+    let depth_view = common::view(device, &depth, blick::ImageAspectFlags::DEPTH);
+    let rendering_info = blick::RenderingInfo {
+        render_area: blick::Rect {
+            x: 0,
+            y: 0,
+            width: EXTENT.width,
+            height: EXTENT.height,
+        },
+        color_attachments: &[
+            attachment(&color_view, blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+        ],
+        depth_attachment: Some(
+            attachment(&depth_view, blick::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL),
+        ),
+    };
+
+    let pipeline = common::solid_pipeline(
+        device,
+        blick::RenderTarget::Dynamic {
+            color_formats: &[common::COLOR_FORMAT],
+            depth_format: Some(DEPTH_FORMAT),
+        },
+        common::FULLSCREEN_VS,
+        |_| {},
+    );
+
+    if !device.supports_dynamic_rendering() {
+        assert!(pipeline.is_err());
+        let mut command_buffer = device
+            .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+            .unwrap();
+        command_buffer.begin();
+        assert!(command_buffer.begin_rendering(&rendering_info).is_err());
+        command_buffer.end();
+        return;
+    }
+    let pipeline = pipeline.unwrap();
+
+    let barrier = |image, aspect_mask, new_layout, dst_access_mask| blick::ImageBarrier {
+        image,
+        src_access_mask: blick::AccessFlags::empty(),
+        dst_access_mask,
+        old_layout: blick::ImageLayout::UNDEFINED,
+        new_layout,
+        subresource_range: blick::ImageSubresourceRange::all(aspect_mask),
+        queue_transfer: None,
+    };
+    device.run_commands(|command_buffer| {
+        command_buffer.transition(
+            &[],
+            &[],
+            &[
+                barrier(
+                    &color,
+                    blick::ImageAspectFlags::COLOR,
+                    blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                ),
+                barrier(
+                    &depth,
+                    blick::ImageAspectFlags::DEPTH,
+                    blick::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL,
+                    blick::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                ),
+            ],
+            blick::PipelineStageFlags::TOP_OF_PIPE,
+            blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+                | blick::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+        );
+        command_buffer
+            .begin_rendering(&rendering_info)
+            .unwrap()
+            .bind_pipeline(&pipeline)
+            .set_viewport_and_scissor(&rendering_info.render_area)
+            .push_constants_typed(0, &[1.0f32, 1.0, 1.0, 1.0])
+            .draw(3, 1, 0, 0);
+    }).unwrap();
+
+    let pixels = common::read_image(
+        device,
+        &color,
+        blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        0,
+    );
+    assert!(pixels.iter().all(|&value| value == 255));
+}
@@ -0,0 +1,86 @@
+mod common;
+
+const COUNT: u32 = 64;
+
+const PRODUCER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> data;
+
+[numthreads(64, 1, 1)]
+void main(uint3 id : SV_DispatchThreadID) {
+    data[id.x] = id.x + 1;
+}
+"#;
+
+const CONSUMER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> data;
+[[vk::binding(1, 0)]] RWStructuredBuffer<uint> result;
+
+[numthreads(64, 1, 1)]
+void main(uint3 id : SV_DispatchThreadID) {
+    result[id.x] = data[id.x] * 2;
+}
+"#;
+
+/// The event set after the producing dispatch orders the consuming one
+/// after it, without a full pipeline barrier
+#[test]
+fn wait_for_event_between_dispatches() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let data = common::storage_buffer(device, COUNT as u64 * 4);
+    let result = common::readback_buffer(device, COUNT as u64 * 4);
+    let layout = common::storage_layout(device, 2);
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&data)],
+            },
+            blick::Descriptor {
+                binding: 1,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&result)],
+            },
+        ],
+    ).unwrap();
+
+    let producer = common::compute_pipeline(device, PRODUCER, &layout);
+    let consumer = common::compute_pipeline(device, CONSUMER, &layout);
+    let event = device.create_event().unwrap();
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&producer)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+        command_buffer.set_event(&event, blick::PipelineStageFlags::COMPUTE_SHADER);
+
+        command_buffer.wait_events(
+            &[&event],
+            blick::PipelineStageFlags::COMPUTE_SHADER,
+            blick::PipelineStageFlags::COMPUTE_SHADER,
+            &[],
+            &[
+                blick::BufferBarrier {
+                    buffer: &data,
+                    src_access_mask: blick::AccessFlags::SHADER_WRITE,
+                    dst_access_mask: blick::AccessFlags::SHADER_READ,
+                    queue_transfer: None,
+                },
+            ],
+            &[],
+        );
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&consumer)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+    }).unwrap();
+
+    let values = result.read_slice::<u32>(0, COUNT as usize).unwrap();
+    let expected = (0..COUNT).map(|i| (i + 1) * 2).collect::<Vec<_>>();
+    assert_eq!(values, expected);
+}
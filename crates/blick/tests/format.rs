@@ -0,0 +1,39 @@
+mod common;
+
+/// Vulkan requires D16_UNORM and one of D32_SFLOAT or X8_D24_UNORM_PACK32 to
+/// be usable as depth attachment, so the query can't come up empty
+#[test]
+fn depth_format_is_supported() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let format = device.supported_format(
+        &[
+            blick::ImageFormat::D32_SFLOAT,
+            blick::ImageFormat::X8_D24_UNORM_PACK32,
+            blick::ImageFormat::D16_UNORM,
+        ],
+        blick::ImageTiling::OPTIMAL,
+        blick::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+    ).unwrap();
+
+    common::image_2d(
+        device,
+        format,
+        blick::Extent2d { width: 16, height: 16 },
+        blick::ImageUsage::DEPTH_STENCIL_ATTACHMENT,
+    );
+}
+
+#[test]
+fn color_format_is_not_a_depth_format() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let format = device.supported_format(
+        &[common::COLOR_FORMAT],
+        blick::ImageTiling::OPTIMAL,
+        blick::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+    );
+    assert_eq!(format, None);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 16,
+    height: 16,
+};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// The fullscreen triangle winds clockwise, so with back faces culled it's
+/// only drawn in the right half where the front face is switched back
+#[test]
+fn front_face_toggled_between_draws() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    if !device.supports_extended_dynamic_state() {
+        eprintln!("Skipping test, VK_EXT_extended_dynamic_state not supported");
+        return;
+    }
+
+    let render_pass = common::render_pass(device, None);
+    let pipeline = common::solid_pipeline(
+        device,
+        blick::RenderTarget::RenderPass(&render_pass),
+        common::FULLSCREEN_VS,
+        |_| {},
+    ).unwrap();
+
+    let color = common::color_target(device, EXTENT);
+    let view = common::view(device, &color, blick::ImageAspectFlags::COLOR);
+    let framebuffer = common::framebuffer(device, &render_pass, &[&view], EXTENT);
+    let render_area = blick::Rect::from(EXTENT);
+    let half = EXTENT.width / 2;
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_render_pass(&render_pass, &framebuffer, &render_area)
+            .bind_pipeline(&pipeline)
+            .set_viewport_and_scissor(&render_area)
+            .set_cull_mode(blick::CullModeFlags::BACK)
+            .push_constants_typed(0, &[1.0f32; 4])
+            .set_scissor(&blick::Rect::new(0, 0, half, EXTENT.height))
+            .set_front_face(blick::FrontFace::COUNTER_CLOCKWISE)
+            .draw(3, 1, 0, 0)
+            .set_scissor(&blick::Rect::new(half, 0, half, EXTENT.height))
+            .set_front_face(blick::FrontFace::CLOCKWISE)
+            .draw(3, 1, 0, 0);
+    }).unwrap();
+
+    let pixels = common::read_image(
+        device,
+        &color,
+        blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        0,
+    );
+    assert_eq!(common::texel(&pixels, EXTENT.width, half - 1, 8), BLACK);
+    assert_eq!(common::texel(&pixels, EXTENT.width, half, 8), WHITE);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+const SHADER: &str = r#"
+[[vk::binding(0, 0)]] Texture2D<float4> image;
+[[vk::binding(1, 0)]] SamplerState image_sampler;
+[[vk::binding(2, 0)]] RWStructuredBuffer<float4> result;
+
+[numthreads(2, 2, 1)]
+void main(uint2 thread_id : SV_DispatchThreadID) {
+    float2 uv = (float2(thread_id) + 0.5) / 2.0;
+    result[thread_id.y * 2 + thread_id.x] = image.SampleLevel(image_sampler, uv, 0);
+}
+"#;
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+fn desc(extent: blick::Extent2d) -> blick::ImageDesc {
+    blick::ImageDesc {
+        image_type: blick::ImageType::TYPE_2D,
+        format: common::COLOR_FORMAT,
+        extent: blick::Extent3d {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        },
+        mip_levels: 1,
+        array_layers: 1,
+        samples: blick::SampleCountFlags::TYPE_1,
+        usage: blick::ImageUsage::SAMPLED,
+        mutable_format: false,
+        cube_compatible: false,
+        name: None,
+    }
+}
+
+/// Uploads a 2x2 checkerboard and samples each texel at its center
+#[test]
+fn sample_uploaded_checkerboard() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let checkerboard = [BLACK, WHITE, WHITE, BLACK].concat();
+    let image = device.create_image_init(
+        desc(blick::Extent2d { width: 2, height: 2 }),
+        &checkerboard,
+    ).unwrap();
+
+    let layout = device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::SAMPLED_IMAGE,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+                blick::DescriptorSetLayoutEntry {
+                    binding: 1,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::SAMPLER,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+                blick::DescriptorSetLayoutEntry {
+                    binding: 2,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::STORAGE_BUFFER,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+            ],
+        }
+    ).unwrap();
+
+    let sampler = device.create_sampler(blick::SamplerDesc {
+        mag_filter: blick::Filter::NEAREST,
+        min_filter: blick::Filter::NEAREST,
+        mipmap_mode: blick::SamplerMipmapMode::NEAREST,
+        address_mode: blick::SamplerAddressMode::CLAMP_TO_EDGE,
+        max_anisotropy: None,
+    }).unwrap();
+    let view = common::view(device, &image, blick::ImageAspectFlags::COLOR);
+    let result = common::readback_buffer(device, 4 * 16);
+
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::Image {
+                    view: &view,
+                    layout: blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                }],
+            },
+            blick::Descriptor {
+                binding: 1,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::Sampler { sampler: &sampler }],
+            },
+            blick::Descriptor {
+                binding: 2,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&result)],
+            },
+        ],
+    ).unwrap();
+
+    let pipeline = common::compute_pipeline(device, SHADER, &layout);
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&pipeline)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+    }).unwrap();
+
+    let black = [0.0, 0.0, 0.0, 1.0];
+    let white = [1.0; 4];
+    assert_eq!(
+        result.read_slice::<f32>(0, 16).unwrap(),
+        [black, white, white, black].concat(),
+    );
+}
+
+#[test]
+fn data_size_mismatch_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let extent = blick::Extent2d { width: 2, height: 2 };
+    assert!(device.create_image_init(desc(extent), &[0; 15]).is_err());
+    assert!(device.create_image_init(desc(extent), &[0; 17]).is_err());
+    assert!(device.create_image_init(desc(extent), &[0; 16]).is_ok());
+}
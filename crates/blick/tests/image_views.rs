@@ -0,0 +1,221 @@
+mod common;
+
+const FORMAT: blick::ImageFormat = blick::ImageFormat::R8G8B8A8_UNORM;
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 16,
+    height: 16,
+};
+
+fn view_desc() -> blick::ImageViewDesc {
+    blick::ImageViewDesc {
+        view_type: blick::ImageViewType::TYPE_2D,
+        aspect_mask: blick::ImageAspectFlags::COLOR,
+        format: FORMAT,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+fn setup(device: &blick::Device) -> (blick::Image, blick::RenderPass) {
+    let image = device.create_image(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: FORMAT,
+            extent: blick::Extent3d {
+                width: EXTENT.width,
+                height: EXTENT.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::COLOR_ATTACHMENT,
+            mutable_format: false,
+            cube_compatible: false,
+            name: None,
+        }
+    ).unwrap();
+
+    let render_pass = device.create_render_pass(
+        blick::RenderPassDesc {
+            color_attachments: &[
+                Some(blick::ColorAttachmentDesc {
+                    format: FORMAT,
+                    layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                }),
+            ],
+            samples: blick::SampleCountFlags::TYPE_1,
+            resolve_attachments: &[],
+            subpasses: &[],
+            dependencies: &[],
+            depth_stencil_attachment: None,
+        }
+    ).unwrap();
+
+    (image, render_pass)
+}
+
+fn framebuffer(
+    device: &blick::Device,
+    render_pass: &blick::RenderPass,
+    view: &blick::ImageView,
+) -> blick::Framebuffer {
+    device.create_framebuffer(
+        blick::FramebufferDesc {
+            render_pass,
+            attachments: &[
+                blick::Attachment {
+                    image_view: view,
+                },
+            ],
+            extent: EXTENT,
+        }
+    ).unwrap()
+}
+
+/// Destroyed views may have their handles reused, framebuffers created for
+/// them must not be returned for the new views
+#[test]
+fn uncached_views_evict_framebuffers() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let (image, render_pass) = setup(device);
+
+    for _ in 0..100 {
+        let view = image.create_view_uncached(view_desc()).unwrap();
+        framebuffer(device, &render_pass, &view);
+        drop(view);
+
+        let frame = device.end_frame();
+        device.wait_idle().unwrap();
+        device.release_frame(frame);
+    }
+
+    let stats = device.cache_stats();
+    assert_eq!(stats.framebuffer_hits, 0);
+    assert_eq!(stats.framebuffer_misses, 100);
+}
+
+#[test]
+fn clear_view_cache_evicts_framebuffers() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let (image, render_pass) = setup(device);
+
+    let view = device.create_image_view(&image, view_desc()).unwrap();
+    framebuffer(device, &render_pass, &view);
+    framebuffer(device, &render_pass, &view);
+
+    image.clear_view_cache();
+    let frame = device.end_frame();
+    device.wait_idle().unwrap();
+    device.release_frame(frame);
+
+    let view = device.create_image_view(&image, view_desc()).unwrap();
+    framebuffer(device, &render_pass, &view);
+
+    let stats = device.cache_stats();
+    assert_eq!(stats.framebuffer_hits, 1);
+    assert_eq!(stats.framebuffer_misses, 2);
+}
+
+fn cube_image(device: &blick::Device, cube_compatible: bool) -> blick::Image {
+    device.create_image(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: FORMAT,
+            extent: blick::Extent3d {
+                width: EXTENT.width,
+                height: EXTENT.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 6,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::SAMPLED,
+            mutable_format: false,
+            cube_compatible,
+            name: None,
+        }
+    ).unwrap()
+}
+
+#[test]
+fn cube_face_views_are_cached() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let image = cube_image(device, true);
+
+    let faces = (0..6)
+        .map(|layer| blick::ImageViewDesc {
+            base_array_layer: layer,
+            ..view_desc()
+        })
+        .collect::<Vec<_>>();
+    let cube = blick::ImageViewDesc {
+        view_type: blick::ImageViewType::CUBE,
+        layer_count: 6,
+        ..view_desc()
+    };
+
+    assert_eq!(device.create_image_views(&image, &faces).unwrap().len(), 6);
+    assert_eq!(image.cached_view_count(), 6);
+
+    device.create_image_view(&image, cube).unwrap();
+    device.create_image_views(&image, &faces).unwrap();
+    assert_eq!(image.cached_view_count(), 7);
+}
+
+#[test]
+fn cube_view_requires_cube_compatible() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let image = cube_image(device, false);
+
+    let result = device.create_image_view(
+        &image,
+        blick::ImageViewDesc {
+            view_type: blick::ImageViewType::CUBE,
+            layer_count: 6,
+            ..view_desc()
+        },
+    );
+    assert!(result.is_err());
+}
+
+/// Views differing only in format are separate cache entries
+#[test]
+fn srgb_and_unorm_views_are_cached_separately() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let image = device.create_image(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: FORMAT,
+            extent: blick::Extent3d {
+                width: EXTENT.width,
+                height: EXTENT.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::SAMPLED,
+            mutable_format: true,
+            cube_compatible: false,
+            name: None,
+        }
+    ).unwrap();
+
+    let srgb = blick::ImageViewDesc {
+        format: blick::ImageFormat::R8G8B8A8_SRGB,
+        ..view_desc()
+    };
+    device.create_image_view(&image, view_desc()).unwrap();
+    device.create_image_view(&image, srgb).unwrap();
+    device.create_image_view(&image, srgb).unwrap();
+    assert_eq!(image.cached_view_count(), 2);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 16,
+    height: 16,
+};
+
+/// Four fullscreen draws of which only two are counted
+const WRITE_DRAWS_SHADER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint4> draws;
+[[vk::binding(1, 0)]] RWStructuredBuffer<uint> draw_count;
+
+[numthreads(4, 1, 1)]
+void main(uint thread_id : SV_DispatchThreadID) {
+    // vertex_count, instance_count, first_vertex, first_instance
+    draws[thread_id] = uint4(3, 1, 0, 0);
+    if (thread_id == 0) {
+        draw_count[0] = 2;
+    }
+}
+"#;
+
+/// A compute shader writes both the draws and their count, each counted draw
+/// adds 64 to the color channels of the target cleared to black
+#[test]
+fn draw_indirect_count_from_compute() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    if !device.supports_draw_indirect_count() {
+        eprintln!("Skipping test, VK_KHR_draw_indirect_count not supported");
+        return;
+    }
+
+    let indirect_buffer = |size| device.create_buffer(blick::BufferDesc {
+        size,
+        usage: blick::BufferUsage::STORAGE | blick::BufferUsage::INDIRECT,
+        min_alignment: None,
+        memory_location: None,
+        name: None,
+    }).unwrap();
+    let draws = indirect_buffer(4 * 16);
+    let draw_count = indirect_buffer(4);
+
+    let layout = common::storage_layout(device, 2);
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&draws)],
+            },
+            blick::Descriptor {
+                binding: 1,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&draw_count)],
+            },
+        ],
+    ).unwrap();
+    let compute_pipeline = common::compute_pipeline(device, WRITE_DRAWS_SHADER, &layout);
+
+    let render_pass = common::render_pass(device, None);
+    let pipeline = common::solid_pipeline(
+        device,
+        blick::RenderTarget::RenderPass(&render_pass),
+        common::FULLSCREEN_VS,
+        |desc| desc.blend = Some(blick::BlendState {
+            src_color_factor: blick::BlendFactor::ONE,
+            dst_color_factor: blick::BlendFactor::ONE,
+            color_op: blick::BlendOp::ADD,
+            src_alpha_factor: blick::BlendFactor::ONE,
+            dst_alpha_factor: blick::BlendFactor::ONE,
+            alpha_op: blick::BlendOp::ADD,
+        }),
+    ).unwrap();
+
+    let color = common::color_target(device, EXTENT);
+    let view = common::view(device, &color, blick::ImageAspectFlags::COLOR);
+    let framebuffer = common::framebuffer(device, &render_pass, &[&view], EXTENT);
+    let render_area = blick::Rect::from(EXTENT);
+
+    let barrier = |buffer| blick::BufferBarrier {
+        buffer,
+        src_access_mask: blick::AccessFlags::SHADER_WRITE,
+        dst_access_mask: blick::AccessFlags::INDIRECT_COMMAND_READ,
+        queue_transfer: None,
+    };
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&compute_pipeline)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+        command_buffer.transition(
+            &[],
+            &[barrier(&draws), barrier(&draw_count)],
+            &[],
+            blick::PipelineStageFlags::COMPUTE_SHADER,
+            blick::PipelineStageFlags::DRAW_INDIRECT,
+        );
+        command_buffer.begin_render_pass(&render_pass, &framebuffer, &render_area)
+            .bind_pipeline(&pipeline)
+            .set_viewport_and_scissor(&render_area)
+            .push_constants_typed(0, &[64.0f32 / 255.0; 4])
+            .draw_indirect_count(&draws, 0, &draw_count, 0, 4, 16);
+    }).unwrap();
+
+    let pixels = common::read_image(
+        device,
+        &color,
+        blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        0,
+    );
+    assert_eq!(common::texel(&pixels, EXTENT.width, 8, 8), [128, 128, 128, 255]);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 4,
+    height: 4,
+};
+
+fn tracked_image(device: &blick::Device) -> blick::Image {
+    let image = device.create_image(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: common::COLOR_FORMAT,
+            extent: blick::Extent3d {
+                width: EXTENT.width,
+                height: EXTENT.height,
+                depth: 1,
+            },
+            mip_levels: 2,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::TRANSFER_SRC
+                | blick::ImageUsage::TRANSFER_DST
+                | blick::ImageUsage::SAMPLED,
+            mutable_format: false,
+            cube_compatible: false,
+            name: None,
+        }
+    ).unwrap();
+    image.track_layout(
+        blick::ImageLayout::UNDEFINED,
+        blick::AccessFlags::empty(),
+        blick::PipelineStageFlags::TOP_OF_PIPE,
+    );
+    image
+}
+
+/// Uploads through transition_to and moves mip 1 on its own with a manual
+/// barrier, validation catches any old_layout the tracker got wrong
+#[test]
+fn transitions_follow_tracked_layouts() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let image = tracked_image(device);
+
+    let texels = [0x40u8, 0x80, 0xc0, 0xff].repeat((EXTENT.width * EXTENT.height) as usize);
+    let staging = device.create_buffer_init(
+        blick::BufferUsage::TRANSFER_SRC,
+        &texels,
+    ).unwrap();
+
+    device.run_commands(|command_buffer| {
+        command_buffer.transition_to(
+            &image,
+            blick::ImageLayout::TRANSFER_DST_OPTIMAL,
+            blick::AccessFlags::TRANSFER_WRITE,
+            blick::PipelineStageFlags::TRANSFER,
+        );
+        command_buffer.copy_buffer_to_image(
+            &staging,
+            0,
+            &image,
+            blick::ImageLayout::TRANSFER_DST_OPTIMAL,
+            0,
+            blick::Extent3d {
+                width: EXTENT.width,
+                height: EXTENT.height,
+                depth: 1,
+            },
+        );
+        command_buffer.transition_to(
+            &image,
+            blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            blick::AccessFlags::SHADER_READ,
+            blick::PipelineStageFlags::FRAGMENT_SHADER,
+        );
+        command_buffer.transition(
+            &[],
+            &[],
+            &[blick::ImageBarrier {
+                image: &image,
+                src_access_mask: blick::AccessFlags::SHADER_READ,
+                dst_access_mask: blick::AccessFlags::SHADER_READ
+                    | blick::AccessFlags::SHADER_WRITE,
+                old_layout: blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                new_layout: blick::ImageLayout::GENERAL,
+                subresource_range: blick::ImageSubresourceRange::mip_level(
+                    blick::ImageAspectFlags::COLOR,
+                    1,
+                ),
+                queue_transfer: None,
+            }],
+            blick::PipelineStageFlags::FRAGMENT_SHADER,
+            blick::PipelineStageFlags::COMPUTE_SHADER,
+        );
+    }).unwrap();
+
+    assert_eq!(
+        image.tracked_layout(0, 0),
+        Some(blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+    );
+    assert_eq!(image.tracked_layout(1, 0), Some(blick::ImageLayout::GENERAL));
+
+    // Both levels are moved out of their differing layouts in one call
+    device.run_commands(|command_buffer| {
+        command_buffer.transition_to(
+            &image,
+            blick::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            blick::AccessFlags::TRANSFER_READ,
+            blick::PipelineStageFlags::TRANSFER,
+        );
+    }).unwrap();
+
+    assert_eq!(
+        image.tracked_layout(1, 0),
+        Some(blick::ImageLayout::TRANSFER_SRC_OPTIMAL),
+    );
+    let pixels = common::read_image(
+        device,
+        &image,
+        blick::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        0,
+    );
+    assert_eq!(pixels, texels);
+}
+
+#[test]
+fn tracked_layout_out_of_range_is_none() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let image = tracked_image(device);
+
+    assert_eq!(image.tracked_layout(1, 0), Some(blick::ImageLayout::UNDEFINED));
+    assert_eq!(image.tracked_layout(2, 0), None);
+    assert_eq!(image.tracked_layout(0, 1), None);
+}
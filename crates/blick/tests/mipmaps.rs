@@ -0,0 +1,138 @@
+mod common;
+
+const SIZE: u32 = 64;
+
+fn checkerboard() -> Vec<u8> {
+    (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let (x, y) = (i % SIZE, i / SIZE);
+            let value = if (x + y) % 2 == 0 { 255 } else { 0 };
+            [value, value, value, 255]
+        })
+        .collect()
+}
+
+/// Each blit averages 2x2 texels, the 1x1 level of a checkerboard ends up
+/// halfway between black and white
+#[test]
+fn generated_mip_levels_are_averaged() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let extent = blick::Extent3d {
+        width: SIZE,
+        height: SIZE,
+        depth: 1,
+    };
+    let mip_levels = blick::mip_level_count(extent);
+    assert_eq!(mip_levels, 7);
+
+    let image = device.create_image_init(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: blick::ImageFormat::R8G8B8A8_UNORM,
+            extent,
+            mip_levels,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::SAMPLED
+                | blick::ImageUsage::TRANSFER_SRC
+                | blick::ImageUsage::TRANSFER_DST,
+            mutable_format: false,
+            cube_compatible: false,
+            name: None,
+        },
+        &checkerboard(),
+    ).unwrap();
+    device.generate_mipmaps(&image).unwrap();
+
+    let texel = common::read_image(
+        device,
+        &image,
+        blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        mip_levels - 1,
+    );
+    assert_eq!(texel.len(), 4);
+    for channel in &texel[..3] {
+        assert!((125..=130).contains(channel), "{:?}", texel);
+    }
+    assert_eq!(texel[3], 255);
+}
+
+/// Only mip 2 leaves UNDEFINED. Had the second barrier covered all levels its
+/// old_layout would be wrong for the others, which validation catches.
+#[test]
+fn transition_single_mip_level() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let image = device.create_image(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: blick::ImageFormat::R8G8B8A8_UNORM,
+            extent: blick::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth: 1,
+            },
+            mip_levels: 4,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::TRANSFER_SRC | blick::ImageUsage::TRANSFER_DST,
+            mutable_format: false,
+            cube_compatible: false,
+            name: None,
+        }
+    ).unwrap();
+
+    let size = SIZE >> 2;
+    let texels = [0x20u8, 0x40, 0x60, 0x80].repeat((size * size) as usize);
+    let staging = device.create_buffer_init(blick::BufferUsage::TRANSFER_SRC, &texels).unwrap();
+
+    let barrier = |old_layout, new_layout| blick::ImageBarrier {
+        image: &image,
+        src_access_mask: blick::AccessFlags::TRANSFER_WRITE,
+        dst_access_mask: blick::AccessFlags::TRANSFER_WRITE | blick::AccessFlags::TRANSFER_READ,
+        old_layout,
+        new_layout,
+        subresource_range: blick::ImageSubresourceRange::mip_level(
+            blick::ImageAspectFlags::COLOR,
+            2,
+        ),
+        queue_transfer: None,
+    };
+    device.run_commands(|command_buffer| {
+        command_buffer.transition(
+            &[],
+            &[],
+            &[barrier(blick::ImageLayout::UNDEFINED, blick::ImageLayout::TRANSFER_DST_OPTIMAL)],
+            blick::PipelineStageFlags::TOP_OF_PIPE,
+            blick::PipelineStageFlags::TRANSFER,
+        );
+        command_buffer.copy_buffer_to_image(
+            &staging,
+            0,
+            &image,
+            blick::ImageLayout::TRANSFER_DST_OPTIMAL,
+            2,
+            blick::Extent3d {
+                width: size,
+                height: size,
+                depth: 1,
+            },
+        );
+        command_buffer.transition(
+            &[],
+            &[],
+            &[barrier(
+                blick::ImageLayout::TRANSFER_DST_OPTIMAL,
+                blick::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            )],
+            blick::PipelineStageFlags::TRANSFER,
+            blick::PipelineStageFlags::TRANSFER,
+        );
+    }).unwrap();
+
+    let pixels = common::read_image(device, &image, blick::ImageLayout::TRANSFER_SRC_OPTIMAL, 2);
+    assert_eq!(pixels, texels);
+}
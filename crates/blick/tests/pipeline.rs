@@ -0,0 +1,132 @@
+mod common;
+
+const SHADER: &str = r#"
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> data;
+
+[numthreads(1, 1, 1)]
+void main() {
+    data[0] = 1;
+}
+"#;
+
+fn desc<'a>(
+    hlsl: &'a str,
+    layouts: &'a [&'a blick::DescriptorSetLayout],
+) -> blick::ComputePipelineDesc<'a> {
+    blick::ComputePipelineDesc {
+        shader_module: blick::ShaderModuleDesc {
+            source: blick::ShaderSource::Hlsl(hlsl),
+            stage: blick::ShaderStageFlags::COMPUTE,
+            specialization: &[],
+        },
+        descriptor_set_layouts: layouts,
+        push_constant_ranges: &[],
+    }
+}
+
+/// Failures are returned rather than panicking, on the worker thread as well
+#[test]
+fn invalid_shader_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let layout = common::storage_layout(device, 1);
+
+    let hlsl = "[numthreads(1, 1, 1)] void main() { undefined(); }";
+    assert!(device.create_compute_pipeline(desc(hlsl, &[&layout])).is_err());
+
+    let pending = device.create_compute_pipeline_async(desc(hlsl, &[&layout])).unwrap();
+    assert!(pending.wait().is_err());
+
+    // Failed pipelines leave nothing behind for the validation layer to
+    // complain about
+    device.wait_idle().unwrap();
+}
+
+#[test]
+fn push_constant_range_must_fit_device_limit() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let layout = common::storage_layout(device, 1);
+
+    let max_size = device.info().limits.max_push_constants_size;
+    let ranges = [
+        // 512 bytes exceed the limit of most devices
+        (0, 512.max(max_size + 4)),
+        (max_size, 4),
+        (4, u32::MAX - 3),
+    ];
+    for (offset, size) in ranges {
+        let result = device.create_compute_pipeline(
+            blick::ComputePipelineDesc {
+                push_constant_ranges: &[
+                    blick::PushConstantRange {
+                        stage_flags: blick::ShaderStageFlags::COMPUTE,
+                        offset,
+                        size,
+                    },
+                ],
+                ..desc(SHADER, &[&layout])
+            }
+        );
+        assert!(result.is_err(), "Range {}+{} was accepted", offset, size);
+    }
+}
+
+const SPECIALIZED_SHADER: &str = r#"
+[[vk::constant_id(0)]] const uint GroupSize = 1;
+[[vk::binding(0, 0)]] RWStructuredBuffer<uint> data;
+
+[numthreads(64, 1, 1)]
+void main(uint thread_id : SV_DispatchThreadID) {
+    if (thread_id < GroupSize) {
+        InterlockedAdd(data[0], 1);
+    }
+}
+"#;
+
+/// One shader serves both pipelines, numthreads can't be specialized in HLSL
+/// so the constant limits how many of the 64 invocations do any work
+#[test]
+fn specialized_workgroup_sizes() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+    let layout = common::storage_layout(device, 1);
+
+    for group_size in [8, 32] {
+        let pipeline = device.create_compute_pipeline(
+            blick::ComputePipelineDesc {
+                shader_module: blick::ShaderModuleDesc {
+                    source: blick::ShaderSource::Hlsl(SPECIALIZED_SHADER),
+                    stage: blick::ShaderStageFlags::COMPUTE,
+                    specialization: &[(0, blick::SpecValue::U32(group_size))],
+                },
+                ..desc(SPECIALIZED_SHADER, &[&layout])
+            }
+        ).unwrap();
+
+        let data = device.create_buffer_init(
+            blick::BufferUsage::STORAGE | blick::BufferUsage::MAP_READ,
+            &[0u8; 4],
+        ).unwrap();
+        let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+        device.update_descriptor_set(
+            &descriptor_set,
+            &[
+                blick::Descriptor {
+                    binding: 0,
+                    array_element: 0,
+                    resources: &[blick::DescriptorResource::whole_buffer(&data)],
+                },
+            ],
+        ).unwrap();
+
+        device.run_commands(|command_buffer| {
+            command_buffer.begin_compute_pass()
+                .bind_pipeline(&pipeline)
+                .bind_descriptor_set(0, &descriptor_set)
+                .dispatch(1, 1, 1);
+        }).unwrap();
+
+        assert_eq!(data.read_slice::<u32>(0, 1).unwrap(), [group_size]);
+    }
+}
@@ -0,0 +1,16 @@
+mod common;
+
+#[test]
+fn query_range_out_of_pool_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let pool = device.create_query_pool(blick::QueryPoolDesc {
+        ty: blick::QueryType::Occlusion,
+        count: 4,
+    }).unwrap();
+
+    assert!(device.get_query_results(&pool, 2, 3).is_err());
+    // Would wrap around to a range within the pool
+    assert!(device.get_query_results(&pool, 2, u32::MAX).is_err());
+}
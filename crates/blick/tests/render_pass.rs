@@ -0,0 +1,214 @@
+mod common;
+
+fn color(format: blick::ImageFormat) -> Option<blick::ColorAttachmentDesc> {
+    Some(blick::ColorAttachmentDesc {
+        format,
+        layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    })
+}
+
+fn create_render_pass(
+    device: &blick::Device,
+    color_attachments: &[Option<blick::ColorAttachmentDesc>],
+) -> blick::RenderPass {
+    device.create_render_pass(
+        blick::RenderPassDesc {
+            color_attachments,
+            samples: blick::SampleCountFlags::TYPE_1,
+            resolve_attachments: &[],
+            subpasses: &[],
+            dependencies: &[],
+            depth_stencil_attachment: None,
+        }
+    ).unwrap()
+}
+
+/// Empty slots get no attachment description, the following slots refer to
+/// the descriptions without gaps
+#[test]
+fn color_attachment_slots_with_holes() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let rgba = blick::ImageFormat::R8G8B8A8_UNORM;
+    let r = blick::ImageFormat::R32_SFLOAT;
+
+    let render_pass = create_render_pass(device, &[None, color(rgba)]);
+    assert_eq!(render_pass.num_attachments(), 1);
+    assert_eq!(render_pass.color_attachment_count(0), Some(2));
+    assert_eq!(render_pass.color_attachment_index(0), None);
+    assert_eq!(render_pass.color_attachment_index(1), Some(0));
+
+    let render_pass = create_render_pass(device, &[color(rgba), None, color(r)]);
+    assert_eq!(render_pass.num_attachments(), 2);
+    assert_eq!(render_pass.color_attachment_count(0), Some(3));
+    assert_eq!(render_pass.color_attachment_index(0), Some(0));
+    assert_eq!(render_pass.color_attachment_index(1), None);
+    assert_eq!(render_pass.color_attachment_index(2), Some(1));
+
+    let render_pass = create_render_pass(device, &[color(rgba), color(r)]);
+    assert_eq!(render_pass.num_attachments(), 2);
+    assert_eq!(render_pass.color_attachment_count(0), Some(2));
+    assert_eq!(render_pass.color_attachment_index(0), Some(0));
+    assert_eq!(render_pass.color_attachment_index(1), Some(1));
+}
+
+#[test]
+fn attachment_smaller_than_framebuffer_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let render_pass = common::render_pass(device, None);
+    let image = common::color_target(device, blick::Extent2d { width: 16, height: 16 });
+    let view = common::view(device, &image, blick::ImageAspectFlags::COLOR);
+
+    let framebuffer = |extent| device.create_framebuffer(
+        blick::FramebufferDesc {
+            render_pass: &render_pass,
+            attachments: &[blick::Attachment { image_view: &view }],
+            extent,
+        }
+    );
+    assert!(framebuffer(blick::Extent2d { width: 16, height: 8 }).is_ok());
+    let err = framebuffer(blick::Extent2d { width: 16, height: 32 }).err().unwrap();
+    assert!(err.to_string().contains("smaller than the framebuffer"), "{}", err);
+}
+
+/// Subpass 0 renders with depth, subpass 1 reads its output as input
+/// attachment without depth
+#[test]
+fn subpasses_with_and_without_depth() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let rgba = blick::ImageFormat::R8G8B8A8_UNORM;
+    let render_pass = device.create_render_pass(
+        blick::RenderPassDesc {
+            color_attachments: &[color(rgba), color(rgba)],
+            samples: blick::SampleCountFlags::TYPE_1,
+            resolve_attachments: &[],
+            subpasses: &[
+                blick::SubpassDesc {
+                    color_attachments: &[Some(0)],
+                    input_attachments: &[],
+                    resolve_attachments: &[],
+                    depth_stencil_attachment: true,
+                },
+                blick::SubpassDesc {
+                    color_attachments: &[Some(1)],
+                    input_attachments: &[Some(0)],
+                    resolve_attachments: &[],
+                    depth_stencil_attachment: false,
+                },
+            ],
+            dependencies: &[
+                blick::SubpassDependency {
+                    src_subpass: 0,
+                    dst_subpass: 1,
+                    src_stage_mask: blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                    dst_stage_mask: blick::PipelineStageFlags::FRAGMENT_SHADER,
+                    src_access_mask: blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    dst_access_mask: blick::AccessFlags::INPUT_ATTACHMENT_READ,
+                    dependency_flags: blick::DependencyFlags::BY_REGION,
+                },
+            ],
+            depth_stencil_attachment: Some(blick::DepthStencilAttachmentDesc {
+                format: blick::ImageFormat::D32_SFLOAT,
+                layout: blick::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            }),
+        }
+    ).unwrap();
+
+    assert_eq!(render_pass.subpass_count(), 2);
+    assert_eq!(render_pass.num_attachments(), 3);
+    assert_eq!(render_pass.color_attachment_count(1), Some(1));
+    assert_eq!(render_pass.color_attachment_count(2), None);
+
+    let pipeline = |subpass| common::solid_pipeline(
+        device,
+        blick::RenderTarget::RenderPass(&render_pass),
+        common::FULLSCREEN_VS,
+        |desc| desc.subpass = subpass,
+    );
+    assert!(pipeline(1).is_ok());
+    let err = pipeline(2).err().unwrap();
+    assert!(err.to_string().contains("Subpass 2 out of range"), "{}", err);
+}
+
+#[test]
+fn subpass_depth_without_attachment_is_an_error() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let result = device.create_render_pass(
+        blick::RenderPassDesc {
+            color_attachments: &[color(blick::ImageFormat::R8G8B8A8_UNORM)],
+            samples: blick::SampleCountFlags::TYPE_1,
+            resolve_attachments: &[],
+            subpasses: &[
+                blick::SubpassDesc {
+                    color_attachments: &[Some(0)],
+                    input_attachments: &[],
+                    resolve_attachments: &[],
+                    depth_stencil_attachment: true,
+                },
+            ],
+            dependencies: &[],
+            depth_stencil_attachment: None,
+        }
+    );
+    assert!(result.is_err());
+}
+
+/// Dependency for an attachment written by a compute shader before the pass,
+/// which is part of the cache key
+#[test]
+fn external_color_dependency() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let format = blick::ImageFormat::R8G8B8A8_UNORM;
+    let color_attachments = [color(format)];
+    let desc = |dependencies| blick::RenderPassDesc {
+        color_attachments: &color_attachments,
+        samples: blick::SampleCountFlags::TYPE_1,
+        resolve_attachments: &[],
+        subpasses: &[],
+        dependencies,
+        depth_stencil_attachment: None,
+    };
+    let dependencies = [
+        blick::SubpassDependency {
+            src_subpass: blick::SUBPASS_EXTERNAL,
+            dst_subpass: 0,
+            src_stage_mask: blick::PipelineStageFlags::COMPUTE_SHADER,
+            dst_stage_mask: blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: blick::AccessFlags::SHADER_WRITE,
+            dst_access_mask: blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dependency_flags: blick::DependencyFlags::empty(),
+        },
+    ];
+
+    let _implicit = device.create_render_pass(desc(&[])).unwrap();
+    let explicit = device.create_render_pass(desc(&dependencies)).unwrap();
+    let stats = device.cache_stats();
+    assert_eq!(stats.render_pass_misses, 2);
+    device.create_render_pass(desc(&dependencies)).unwrap();
+    assert_eq!(device.cache_stats().render_pass_hits, stats.render_pass_hits + 1);
+
+    let extent = blick::Extent2d {
+        width: 4,
+        height: 4,
+    };
+    let image = common::image_2d(
+        device,
+        format,
+        extent,
+        blick::ImageUsage::COLOR_ATTACHMENT | blick::ImageUsage::STORAGE,
+    );
+    let view = common::view(device, &image, blick::ImageAspectFlags::COLOR);
+    let framebuffer = common::framebuffer(device, &explicit, &[&view], extent);
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_render_pass(&explicit, &framebuffer, &extent.into());
+    }).unwrap();
+}
@@ -0,0 +1,21 @@
+mod common;
+
+#[test]
+fn resources_of_both_kinds() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let buffer = common::storage_buffer(device, 1024);
+    let image = common::color_target(device, blick::Extent2d { width: 4, height: 4 });
+    let resources: Vec<blick::Resource> = vec![buffer.into(), image.into()];
+
+    assert_eq!(resources[0].kind(), blick::ResourceKind::Buffer);
+    assert_eq!(resources[0].size(), Some(1024));
+    assert!(resources[0].buffer_usage().unwrap().contains(blick::BufferUsage::STORAGE));
+    assert_eq!(resources[0].format(), None);
+
+    assert_eq!(resources[1].kind(), blick::ResourceKind::Image);
+    assert_eq!(resources[1].format(), Some(common::COLOR_FORMAT));
+    assert!(resources[1].image_usage().unwrap().contains(blick::ImageUsage::COLOR_ATTACHMENT));
+    assert_eq!(resources[1].size(), None);
+}
@@ -0,0 +1,30 @@
+mod common;
+
+#[test]
+fn max_sampler_anisotropy_is_at_least_one() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    assert!(device.max_sampler_anisotropy() >= 1.0);
+}
+
+#[test]
+fn anisotropic_sampler() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let sampler = device.create_sampler(blick::SamplerDesc {
+        mag_filter: blick::Filter::LINEAR,
+        min_filter: blick::Filter::LINEAR,
+        mipmap_mode: blick::SamplerMipmapMode::LINEAR,
+        address_mode: blick::SamplerAddressMode::REPEAT,
+        max_anisotropy: Some(16.0),
+    }).unwrap();
+
+    let max_anisotropy = device.max_sampler_anisotropy();
+    if max_anisotropy > 1.0 {
+        assert_eq!(sampler.max_anisotropy(), Some(max_anisotropy.min(16.0)));
+    } else {
+        assert_eq!(sampler.max_anisotropy(), None);
+    }
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use std::collections::VecDeque;
+
+const FRAMES_IN_FLIGHT: usize = 2;
+const FRAME_SIZE: u64 = 1 << 20;
+const CHUNK_SIZE: u64 = 256 << 10;
+const WRITE_SIZE: u64 = 64 << 10;
+
+/// Streams 1MB per frame, chunks are recalled once their frame is released
+/// so the belt stops growing after the first few frames
+#[test]
+fn recalled_chunks_are_reused() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let mut belt = device.create_staging_belt(CHUNK_SIZE);
+    let dst = common::storage_buffer(device, FRAME_SIZE);
+    let data = vec![0xab_u8; WRITE_SIZE as usize];
+
+    let mut in_flight = VecDeque::new();
+    for _ in 0..16 {
+        let mut command_buffer = device
+            .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+            .unwrap();
+        let fence = device.create_fence().unwrap();
+
+        command_buffer.begin();
+        for i in 0..FRAME_SIZE / WRITE_SIZE {
+            belt.write_buffer(&mut command_buffer, &dst, i * WRITE_SIZE, &data).unwrap();
+        }
+        command_buffer.end();
+        device.submit(&[&command_buffer], &[], &[], Some(&fence)).unwrap();
+
+        in_flight.push_back((device.end_frame(), fence));
+        if in_flight.len() > FRAMES_IN_FLIGHT {
+            let (frame, fence) = in_flight.pop_front().unwrap();
+            device.wait(&fence).unwrap();
+            device.release_frame(frame);
+            belt.recall(frame);
+        }
+
+        // Frames in flight, the frame being recorded and the active chunk
+        assert!(
+            belt.allocated_size() <= (FRAMES_IN_FLIGHT as u64 + 1) * FRAME_SIZE + CHUNK_SIZE,
+            "Staging belt grew to {} bytes",
+            belt.allocated_size(),
+        );
+    }
+
+    device.wait_idle().unwrap();
+    for (frame, _) in in_flight {
+        device.release_frame(frame);
+    }
+}
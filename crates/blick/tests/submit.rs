@@ -0,0 +1,126 @@
+mod common;
+
+/// Second batch copies what the first one wrote, ordered only by the
+/// semaphore between them
+#[test]
+fn submit_batch_orders_batches_through_semaphores() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let data = (0..256u32).collect::<Vec<_>>();
+    let src = device.create_buffer_init(
+        blick::BufferUsage::STORAGE | blick::BufferUsage::TRANSFER_SRC,
+        bytemuck::cast_slice(&data),
+    ).unwrap();
+    let intermediate = common::storage_buffer(device, src.size());
+    let dst = common::readback_buffer(device, src.size());
+
+    let record = |src: &blick::Buffer, dst: &blick::Buffer| {
+        let mut command_buffer = device
+            .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+            .unwrap();
+        command_buffer.begin();
+        command_buffer.copy_buffer(src, 0, dst, 0, src.size());
+        command_buffer.end();
+        command_buffer
+    };
+    let first = record(&src, &intermediate);
+    let second = record(&intermediate, &dst);
+
+    let semaphore = device.create_semaphore().unwrap();
+    let fence = device.create_fence().unwrap();
+    device.submit_batch(
+        &[
+            blick::SubmitDesc {
+                command_buffers: &[&first],
+                wait_semaphores: &[],
+                signal_semaphores: &[&semaphore],
+            },
+            blick::SubmitDesc {
+                command_buffers: &[&second],
+                wait_semaphores: &[(&semaphore, blick::PipelineStageFlags::TRANSFER)],
+                signal_semaphores: &[],
+            },
+        ],
+        Some(&fence),
+    ).unwrap();
+    device.wait(&fence).unwrap();
+
+    assert_eq!(dst.read_slice::<u32>(0, data.len()).unwrap(), data);
+}
+
+/// The render pass clears the white image to black, run_commands only
+/// returns once the clear has completed
+#[test]
+fn run_commands_clears_image() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let extent = blick::Extent2d {
+        width: 4,
+        height: 4,
+    };
+    let image = device.create_image_init(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: common::COLOR_FORMAT,
+            extent: blick::Extent3d {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::COLOR_ATTACHMENT
+                | blick::ImageUsage::SAMPLED
+                | blick::ImageUsage::TRANSFER_SRC,
+            mutable_format: false,
+            cube_compatible: false,
+            name: None,
+        },
+        &[255; 4 * 4 * 4],
+    ).unwrap();
+    let render_pass = common::render_pass(device, None);
+    let view = common::view(device, &image, blick::ImageAspectFlags::COLOR);
+    let framebuffer = common::framebuffer(device, &render_pass, &[&view], extent);
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_render_pass(&render_pass, &framebuffer, &extent.into());
+    }).unwrap();
+
+    let pixels = common::read_image(
+        device,
+        &image,
+        blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        0,
+    );
+    assert!(pixels.chunks(4).all(|texel| texel == [0, 0, 0, 255]));
+}
+
+/// Once the queue is idle the copy has read the staging buffer, which can be
+/// dropped before the copied data is read back
+#[test]
+fn wait_queue_idle_before_dropping_input() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let data = (0..64u8).collect::<Vec<_>>();
+    let staging = device.create_buffer_init(
+        blick::BufferUsage::MAP_WRITE | blick::BufferUsage::TRANSFER_SRC,
+        &data,
+    ).unwrap();
+    let readback = common::readback_buffer(device, data.len() as u64);
+
+    let mut command_buffer = device
+        .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+        .unwrap();
+    command_buffer.begin();
+    command_buffer.copy_buffer(&staging, 0, &readback, 0, data.len() as u64);
+    command_buffer.end();
+    device.submit(&[&command_buffer], &[], &[], None).unwrap();
+
+    device.wait_queue_idle(blick::QueueType::Universal).unwrap();
+    drop(staging);
+    assert_eq!(readback.read_slice::<u8>(0, data.len()).unwrap(), data);
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use raw_window_handle::{
+    HasRawDisplayHandle,
+    HasRawWindowHandle,
+    RawDisplayHandle,
+    RawWindowHandle,
+    WebDisplayHandle,
+    WebWindowHandle,
+};
+
+/// Window of a windowing library blick knows nothing about, with handles no
+/// Vulkan surface can be created for outside of a browser
+struct MockWindow;
+
+unsafe impl HasRawWindowHandle for MockWindow {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        RawWindowHandle::Web(WebWindowHandle::empty())
+    }
+}
+
+unsafe impl HasRawDisplayHandle for MockWindow {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        RawDisplayHandle::Web(WebDisplayHandle::empty())
+    }
+}
+
+/// Any raw handle provider is accepted, handles without surface support fail
+/// with an error before an instance is created
+#[test]
+fn unsupported_raw_handle_is_an_error() {
+    let result = blick::Backend::try_from_raw_handle(&MockWindow, 64, 64, common::config());
+    assert!(matches!(result, Err(blick::BackendError::InstanceCreationFailed(_))));
+}
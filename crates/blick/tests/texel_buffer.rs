@@ -0,0 +1,82 @@
+mod common;
+
+const SHADER: &str = r#"
+[[vk::binding(0, 0)]] RWBuffer<float4> texels;
+[[vk::binding(1, 0)]] RWStructuredBuffer<float4> result;
+
+[numthreads(1, 1, 1)]
+void main() {
+    result[0] = texels[1];
+}
+"#;
+
+/// Reads an R8G8B8A8_UNORM texel through a storage texel buffer, the
+/// view converts it to floats
+#[test]
+fn read_storage_texel_buffer() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let layout = device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::STORAGE_TEXEL_BUFFER,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+                blick::DescriptorSetLayoutEntry {
+                    binding: 1,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    ty: blick::DescriptorType::STORAGE_BUFFER,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+            ],
+        }
+    ).unwrap();
+
+    let buffer = device.create_buffer_init(
+        blick::BufferUsage::STORAGE_TEXEL,
+        &[0u8, 0, 0, 0, 255, 0, 255, 0],
+    ).unwrap();
+    let view = device.create_buffer_view(
+        &buffer,
+        blick::BufferViewDesc {
+            format: blick::ImageFormat::R8G8B8A8_UNORM,
+            offset: 0,
+            range: blick::WHOLE_SIZE,
+        },
+    ).unwrap();
+    let result = common::readback_buffer(device, 16);
+
+    let descriptor_set = device.create_descriptor_set(&layout).unwrap();
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::TexelBuffer { view: &view }],
+            },
+            blick::Descriptor {
+                binding: 1,
+                array_element: 0,
+                resources: &[blick::DescriptorResource::whole_buffer(&result)],
+            },
+        ],
+    ).unwrap();
+
+    let pipeline = common::compute_pipeline(device, SHADER, &layout);
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&pipeline)
+            .bind_descriptor_set(0, &descriptor_set)
+            .dispatch(1, 1, 1);
+    }).unwrap();
+
+    assert_eq!(result.read_slice::<f32>(0, 4).unwrap(), [1.0, 0.0, 1.0, 0.0]);
+}
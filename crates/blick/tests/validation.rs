@@ -0,0 +1,50 @@
+mod common;
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Zero sized buffers are invalid, vkCreateBuffer reports it to the layer
+fn trigger_validation_error(device: &blick::Device) {
+    let _ = device.create_buffer(blick::BufferDesc {
+        size: 0,
+        usage: blick::BufferUsage::STORAGE,
+        min_alignment: None,
+        memory_location: None,
+        name: None,
+    });
+}
+
+#[test]
+fn validation_error_panics_on_next_wait() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    trigger_validation_error(device);
+
+    let result = catch_unwind(AssertUnwindSafe(|| device.wait_idle()));
+    let payload = result.expect_err("Validation error didn't panic");
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("Validation error"), "{}", message);
+
+    // Raised once, later calls succeed
+    device.wait_idle().unwrap();
+}
+
+#[test]
+fn validation_callback_panic_is_raised_on_next_wait() {
+    let Ok(backend) = blick::Backend::new_headless(blick::BackendConfig {
+        break_on_validation_error: false,
+        validation_callback: Some(Box::new(|severity, _message| {
+            if severity == blick::ValidationSeverity::ERROR {
+                panic!("callback panic");
+            }
+        })),
+        ..common::config()
+    }) else { return };
+    let device = backend.device();
+
+    trigger_validation_error(device);
+
+    let result = catch_unwind(AssertUnwindSafe(|| device.wait_idle()));
+    let payload = result.expect_err("Callback panic wasn't raised");
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"callback panic"));
+}
@@ -0,0 +1,64 @@
+mod common;
+
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 16,
+    height: 16,
+};
+
+/// Covers the upper half of clip space when Y points up as in D3D
+const UPPER_HALF_VS: &str = r#"
+float4 main(uint vid : SV_VertexID) : SV_Position {
+    float2 positions[3] = { float2(-1, 0), float2(3, 0), float2(-1, 4) };
+    return float4(positions[vid], 0, 1);
+}
+"#;
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// The same triangle lands in the top half with a flipped viewport and in the
+/// bottom half without
+#[test]
+fn flipped_viewport_follows_d3d_convention() {
+    let Some(backend) = common::backend() else { return };
+    let device = backend.device();
+
+    let render_pass = common::render_pass(device, None);
+    let pipeline = common::solid_pipeline(
+        device,
+        blick::RenderTarget::RenderPass(&render_pass),
+        UPPER_HALF_VS,
+        |_| {},
+    ).unwrap();
+
+    let flipped = common::color_target(device, EXTENT);
+    let unflipped = common::color_target(device, EXTENT);
+    let flipped_view = common::view(device, &flipped, blick::ImageAspectFlags::COLOR);
+    let unflipped_view = common::view(device, &unflipped, blick::ImageAspectFlags::COLOR);
+    let flipped_framebuffer = common::framebuffer(device, &render_pass, &[&flipped_view], EXTENT);
+    let unflipped_framebuffer = common::framebuffer(device, &render_pass, &[&unflipped_view], EXTENT);
+    let render_area = blick::Rect::from(EXTENT);
+
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_render_pass(&render_pass, &flipped_framebuffer, &render_area)
+            .bind_pipeline(&pipeline)
+            .set_viewport_and_scissor(&render_area)
+            .set_viewport_flipped(&render_area)
+            .push_constants_typed(0, &[1.0f32; 4])
+            .draw(3, 1, 0, 0);
+        command_buffer.begin_render_pass(&render_pass, &unflipped_framebuffer, &render_area)
+            .bind_pipeline(&pipeline)
+            .set_viewport_and_scissor(&render_area)
+            .push_constants_typed(0, &[1.0f32; 4])
+            .draw(3, 1, 0, 0);
+    }).unwrap();
+
+    let layout = blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL;
+    let pixels = common::read_image(device, &flipped, layout, 0);
+    assert_eq!(common::texel(&pixels, EXTENT.width, 8, 2), WHITE);
+    assert_eq!(common::texel(&pixels, EXTENT.width, 8, 13), BLACK);
+
+    let pixels = common::read_image(device, &unflipped, layout, 0);
+    assert_eq!(common::texel(&pixels, EXTENT.width, 8, 2), BLACK);
+    assert_eq!(common::texel(&pixels, EXTENT.width, 8, 13), WHITE);
+}
@@ -0,0 +1,393 @@
+//! Tests presenting to a window. winit wants the event loop on the main
+//! thread, so these run from main instead of the test harness.
+
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use winit::dpi::PhysicalSize;
+use winit::event::{Event, StartCause, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::platform::run_return::EventLoopExtRunReturn;
+use winit::window::{Window, WindowBuilder};
+
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(64, 64);
+
+type WindowTest = fn(&mut EventLoop<()>, &Window);
+
+fn main() {
+    let tests: &[(&str, WindowTest)] = &[
+        ("capture_solid_frame", capture_solid_frame),
+        ("blit_on_first_frame", blit_on_first_frame),
+        ("format_kept_across_resize", format_kept_across_resize),
+        ("recreation_callback_fires_on_resize", recreation_callback_fires_on_resize),
+        ("swapchain_views_reused_across_frames", swapchain_views_reused_across_frames),
+        ("rapid_resizes_with_frames_in_flight", rapid_resizes_with_frames_in_flight),
+        ("surface_capabilities_cached", surface_capabilities_cached),
+    ];
+
+    let no_display = std::env::var_os("DISPLAY").is_none()
+        && std::env::var_os("WAYLAND_DISPLAY").is_none();
+    if cfg!(all(unix, not(target_os = "macos"))) && no_display {
+        eprintln!("Skipping window tests, no display");
+        return;
+    }
+
+    let mut event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("blick tests")
+        .with_inner_size(SIZE)
+        .with_resizable(true)
+        .build(&event_loop)
+        .unwrap();
+
+    for (name, test) in tests {
+        println!("test {} ...", name);
+        test(&mut event_loop, &window);
+    }
+}
+
+/// None if there is no Vulkan device, in which case the calling test is
+/// skipped
+fn backend(window: &Window) -> Option<blick::Backend> {
+    match blick::Backend::try_new(window, common::config()) {
+        Ok(backend) => Some(backend),
+        Err(err) => {
+            eprintln!("Skipping test, no Vulkan device: {}", err);
+            None
+        }
+    }
+}
+
+/// Resizes the window and runs the event loop until the resize arrives or a
+/// second has passed, window managers may not honor the size exactly.
+/// Returns the resulting inner size.
+fn resize(
+    event_loop: &mut EventLoop<()>,
+    window: &Window,
+    size: PhysicalSize<u32>,
+) -> PhysicalSize<u32> {
+    window.set_inner_size(size);
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    event_loop.run_return(|event, _, control_flow| {
+        control_flow.set_wait_until(deadline);
+        match event {
+            Event::WindowEvent { event: WindowEvent::Resized(_), .. }
+            | Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
+                control_flow.set_exit();
+            }
+            _ => {}
+        }
+    });
+
+    window.inner_size()
+}
+
+/// Render pass with the swapchain image as its only attachment, left ready
+/// to be presented
+fn swapchain_render_pass(backend: &blick::Backend) -> blick::RenderPass {
+    backend.device().create_render_pass(
+        blick::RenderPassDesc {
+            color_attachments: &[
+                Some(blick::ColorAttachmentDesc {
+                    format: backend.swapchain_format(),
+                    layout: blick::ImageLayout::PRESENT_SRC_KHR,
+                }),
+            ],
+            samples: blick::SampleCountFlags::TYPE_1,
+            resolve_attachments: &[],
+            subpasses: &[],
+            dependencies: &[],
+            depth_stencil_attachment: None,
+        }
+    ).unwrap()
+}
+
+fn capture_solid_frame(_: &mut EventLoop<()>, window: &Window) {
+    let Some(mut backend) = backend(window) else { return };
+    if !backend.swapchain_desc().usage.contains(blick::ImageUsage::TRANSFER_SRC) {
+        eprintln!("Skipping test, swapchain images can't be read back");
+        return;
+    }
+
+    let render_pass = swapchain_render_pass(&backend);
+    let pipeline = common::solid_pipeline(
+        backend.device(),
+        blick::RenderTarget::RenderPass(&render_pass),
+        common::FULLSCREEN_VS,
+        |_| {},
+    ).unwrap();
+    let mut command_buffer = backend.device()
+        .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+        .unwrap();
+
+    let frame = backend.begin_frame().unwrap();
+    let extent = backend.swapchain_desc().extent;
+    let device = backend.device();
+    let view = common::view(device, &frame.swapchain_image.image, blick::ImageAspectFlags::COLOR);
+    let framebuffer = device.create_framebuffer(
+        blick::FramebufferDesc {
+            render_pass: &render_pass,
+            attachments: &[blick::Attachment { image_view: &view }],
+            extent,
+        }
+    ).unwrap();
+
+    let render_area = blick::Rect::from(extent);
+    command_buffer.begin();
+    command_buffer.begin_render_pass(&render_pass, &framebuffer, &render_area)
+        .bind_pipeline(&pipeline)
+        .set_viewport_and_scissor(&render_area)
+        .push_constants_typed(0, &[1.0f32, 0.0, 0.0, 1.0])
+        .draw(3, 1, 0, 0);
+    command_buffer.end();
+
+    backend.submit_frame_commands(&frame, &[&command_buffer]).unwrap();
+    let capture = backend.capture_frame(&frame).unwrap();
+    backend.end_frame(frame).unwrap();
+
+    let red = match capture.format {
+        blick::ImageFormat::B8G8R8A8_SRGB | blick::ImageFormat::B8G8R8A8_UNORM => [0, 0, 255, 255],
+        _ => [255, 0, 0, 255],
+    };
+    assert_eq!(capture.extent, extent);
+    assert_eq!(common::texel(&capture.pixels, capture.extent.width, 0, 0), red);
+}
+
+/// Swapchain images are acquired in SwapchainImage::layout, which is
+/// UNDEFINED on the first frame when they aren't transitioned on creation
+fn blit_on_first_frame(_: &mut EventLoop<()>, window: &Window) {
+    let Some(mut backend) = backend(window) else { return };
+    if !backend.swapchain_desc().usage.contains(blick::ImageUsage::TRANSFER_DST) {
+        eprintln!("Skipping test, swapchain images can't be transfer destinations");
+        return;
+    }
+
+    let device = backend.device();
+    let green = [0u8, 255, 0, 255].repeat(4);
+    let source = device.create_image_init(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: common::COLOR_FORMAT,
+            extent: blick::Extent3d {
+                width: 2,
+                height: 2,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::TRANSFER_SRC | blick::ImageUsage::SAMPLED,
+            mutable_format: false,
+            cube_compatible: false,
+            name: None,
+        },
+        &green,
+    ).unwrap();
+    let mut command_buffer = device
+        .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+        .unwrap();
+
+    let frame = backend.begin_frame().unwrap();
+    let swapchain_image = &frame.swapchain_image.image;
+    let barrier = |image, src_access_mask, dst_access_mask, old_layout, new_layout| {
+        blick::ImageBarrier {
+            image,
+            src_access_mask,
+            dst_access_mask,
+            old_layout,
+            new_layout,
+            subresource_range: blick::ImageSubresourceRange::all(
+                blick::ImageAspectFlags::COLOR,
+            ),
+            queue_transfer: None,
+        }
+    };
+
+    command_buffer.begin();
+    command_buffer.transition(
+        &[],
+        &[],
+        &[
+            barrier(
+                &source,
+                blick::AccessFlags::empty(),
+                blick::AccessFlags::TRANSFER_READ,
+                blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                blick::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            ),
+            barrier(
+                swapchain_image,
+                blick::AccessFlags::empty(),
+                blick::AccessFlags::TRANSFER_WRITE,
+                frame.swapchain_image.layout,
+                blick::ImageLayout::TRANSFER_DST_OPTIMAL,
+            ),
+        ],
+        // The acquire semaphore is waited on at COLOR_ATTACHMENT_OUTPUT
+        blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        blick::PipelineStageFlags::TRANSFER,
+    );
+    command_buffer.blit_image(
+        &source,
+        blick::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        0,
+        swapchain_image,
+        blick::ImageLayout::TRANSFER_DST_OPTIMAL,
+        0,
+        blick::Filter::NEAREST,
+    );
+    command_buffer.transition(
+        &[],
+        &[],
+        &[barrier(
+            swapchain_image,
+            blick::AccessFlags::TRANSFER_WRITE,
+            blick::AccessFlags::empty(),
+            blick::ImageLayout::TRANSFER_DST_OPTIMAL,
+            blick::ImageLayout::PRESENT_SRC_KHR,
+        )],
+        blick::PipelineStageFlags::TRANSFER,
+        blick::PipelineStageFlags::BOTTOM_OF_PIPE,
+    );
+    command_buffer.end();
+
+    // Validation errors panic when the frame is waited on
+    backend.submit_frame(frame, &[&command_buffer]).unwrap();
+}
+
+fn format_kept_across_resize(event_loop: &mut EventLoop<()>, window: &Window) {
+    let Some(mut backend) = backend(window) else { return };
+    let format = backend.swapchain_format();
+    let present_mode = backend.swapchain_present_mode();
+
+    for size in [PhysicalSize::new(96, 80), SIZE] {
+        let size = resize(event_loop, window, size);
+        backend.resize_swapchain(size.width, size.height);
+
+        assert_eq!(backend.swapchain_format(), format);
+        assert_eq!(backend.swapchain_desc().format, format);
+        assert_eq!(backend.swapchain_present_mode(), present_mode);
+        assert!(!backend.take_swapchain_format_changed());
+    }
+}
+
+fn recreation_callback_fires_on_resize(event_loop: &mut EventLoop<()>, window: &Window) {
+    let Some(mut backend) = backend(window) else { return };
+
+    let extents = Rc::new(RefCell::new(Vec::new()));
+    backend.on_swapchain_recreated(Box::new({
+        let extents = extents.clone();
+        move |desc| extents.borrow_mut().push(desc.extent)
+    }));
+
+    let size = resize(event_loop, window, PhysicalSize::new(96, 80));
+    let old_extent = backend.swapchain_desc().extent;
+    backend.resize_swapchain(size.width, size.height);
+    let new_extent = backend.swapchain_desc().extent;
+
+    // Nothing is recreated if the window manager kept the old size
+    let expected = if new_extent == old_extent { vec![] } else { vec![new_extent] };
+    assert_eq!(*extents.borrow(), expected);
+
+    backend.recreate_swapchain(size.width, size.height);
+    assert_eq!(extents.borrow().len(), expected.len() + 1);
+
+    resize(event_loop, window, SIZE);
+}
+
+/// Views of swapchain images live as long as the swapchain, so after every
+/// image has been acquired once no more views or framebuffers are created
+fn swapchain_views_reused_across_frames(_: &mut EventLoop<()>, window: &Window) {
+    let Some(mut backend) = backend(window) else { return };
+    let render_pass = swapchain_render_pass(&backend);
+    let mut command_buffer = backend.device()
+        .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+        .unwrap();
+    let image_count = backend.swapchain_desc().image_count as u64;
+    let frame_count = 4 * image_count;
+
+    for _ in 0..frame_count {
+        let frame = backend.begin_frame().unwrap();
+        let extent = backend.swapchain_desc().extent;
+        let device = backend.device();
+        let image = &frame.swapchain_image.image;
+        let view = common::view(device, image, blick::ImageAspectFlags::COLOR);
+        assert_eq!(image.cached_view_count(), 1);
+
+        let framebuffer = device.create_framebuffer(
+            blick::FramebufferDesc {
+                render_pass: &render_pass,
+                attachments: &[blick::Attachment { image_view: &view }],
+                extent,
+            }
+        ).unwrap();
+        command_buffer.begin();
+        command_buffer.begin_render_pass(&render_pass, &framebuffer, &extent.into());
+        command_buffer.end();
+
+        backend.submit_frame(frame, &[&command_buffer]).unwrap();
+    }
+
+    let stats = backend.device().cache_stats();
+    assert!(stats.framebuffer_misses <= image_count);
+    assert_eq!(stats.framebuffer_hits + stats.framebuffer_misses, frame_count);
+}
+
+/// Frames are presented without waiting, so every recreation happens while
+/// the previous swapchain still has a frame in flight
+fn rapid_resizes_with_frames_in_flight(event_loop: &mut EventLoop<()>, window: &Window) {
+    let Some(mut backend) = backend(window) else { return };
+    let render_pass = swapchain_render_pass(&backend);
+
+    for i in 0..8 {
+        let size = PhysicalSize::new(64 + 8 * i, 64 + 4 * i);
+        window.set_inner_size(size);
+        backend.recreate_swapchain(size.width, size.height);
+
+        let frame = match backend.begin_frame() {
+            Ok(frame) => frame,
+            Err(blick::BeginFrameError::OutdatedSwapchain) => continue,
+            Err(err) => panic!("Failed to begin frame: {:?}", err),
+        };
+        let extent = backend.swapchain_desc().extent;
+        let device = backend.device();
+        let view = common::view(device, &frame.swapchain_image.image, blick::ImageAspectFlags::COLOR);
+        let framebuffer = common::framebuffer(device, &render_pass, &[&view], extent);
+
+        let mut command_buffer = device
+            .create_command_buffer(blick::CommandBufferLevel::PRIMARY)
+            .unwrap();
+        command_buffer.begin();
+        command_buffer.begin_render_pass(&render_pass, &framebuffer, &extent.into());
+        command_buffer.end();
+
+        backend.submit_frame_commands(&frame, &[&command_buffer]).unwrap();
+        backend.present(frame).unwrap();
+    }
+
+    // Validation errors panic here
+    backend.device().wait_idle().unwrap();
+
+    resize(event_loop, window, SIZE);
+}
+
+fn surface_capabilities_cached(event_loop: &mut EventLoop<()>, window: &Window) {
+    let Some(mut backend) = backend(window) else { return };
+
+    let max_extent = backend.surface_capabilities().max_image_extent;
+    assert!(max_extent.width > 0 && max_extent.height > 0);
+
+    // Refreshed on recreation, the current extent follows the window
+    let size = resize(event_loop, window, PhysicalSize::new(96, 80));
+    backend.recreate_swapchain(size.width, size.height);
+    let capabilities = backend.surface_capabilities();
+    assert!(capabilities.max_image_extent.width > 0 && capabilities.max_image_extent.height > 0);
+    if capabilities.current_extent.width != u32::MAX {
+        assert_eq!(capabilities.current_extent, backend.swapchain_desc().extent);
+    }
+
+    resize(event_loop, window, SIZE);
+}
@@ -0,0 +1,264 @@
+const EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 64,
+    height: 64,
+};
+const FORMAT: blick::ImageFormat = blick::ImageFormat::R8G8B8A8_UNORM;
+const COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+
+/// Writes the size and color of a quad from a compute shader, the vertex
+/// shader reads the size and the fragment shader the color. Renders headless
+/// and checks the read back image for the computed color.
+fn main() {
+    let backend = blick::Backend::new_headless(
+        blick::BackendConfig {
+            debugging: true,
+            device_selection: blick::DeviceSelection::default(),
+            transition_swapchain_images: false,
+            app_name: "compute_graphics".to_owned(),
+            app_version: (0, 1, 0),
+            engine_name: "blick".to_owned(),
+            engine_version: (0, 1, 0),
+            render_pass_cache_size: blick::DEFAULT_CACHE_SIZE,
+            framebuffer_cache_size: blick::DEFAULT_CACHE_SIZE,
+            renderdoc: false,
+            validation_severity: blick::DEFAULT_VALIDATION_SEVERITY,
+            validation_callback: None,
+            break_on_validation_error: cfg!(debug_assertions),
+            validation_features: blick::ValidationFeatures::SYNCHRONIZATION,
+        },
+    ).expect("Failed to create backend");
+    let device = backend.device();
+
+    let descriptor_set_layout = device.create_descriptor_set_layout(
+        blick::DescriptorSetLayoutDesc {
+            entries: &[
+                blick::DescriptorSetLayoutEntry {
+                    binding: 0,
+                    stage_flags: blick::ShaderStageFlags::COMPUTE
+                        | blick::ShaderStageFlags::VERTEX
+                        | blick::ShaderStageFlags::FRAGMENT,
+                    ty: blick::DescriptorType::STORAGE_BUFFER,
+                    count: 1,
+                    flags: blick::DescriptorBindingFlags::empty(),
+                },
+            ],
+        }
+    ).unwrap();
+
+    let buffer = device.create_buffer(
+        blick::BufferDesc {
+            size: 4*4*2,
+            usage: blick::BufferUsage::STORAGE,
+            min_alignment: None,
+            memory_location: None,
+            name: Some("quad".to_owned()),
+        }
+    ).unwrap();
+
+    let descriptor_set = device.create_descriptor_set(
+        &descriptor_set_layout,
+    ).unwrap();
+
+    device.update_descriptor_set(
+        &descriptor_set,
+        &[
+            blick::Descriptor {
+                binding: 0,
+                array_element: 0,
+                resources: &[
+                    blick::DescriptorResource::whole_buffer(&buffer),
+                ],
+            },
+        ],
+    ).unwrap();
+
+    let image = device.create_image(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format: FORMAT,
+            extent: blick::Extent3d {
+                width: EXTENT.width,
+                height: EXTENT.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::COLOR_ATTACHMENT | blick::ImageUsage::TRANSFER_SRC,
+            mutable_format: false,
+            cube_compatible: false,
+            name: Some("target".to_owned()),
+        }
+    ).unwrap();
+
+    let readback = device.create_buffer(
+        blick::BufferDesc {
+            size: (EXTENT.width * EXTENT.height * 4) as u64,
+            usage: blick::BufferUsage::MAP_READ | blick::BufferUsage::TRANSFER_DST,
+            min_alignment: None,
+            memory_location: None,
+            name: Some("readback".to_owned()),
+        }
+    ).unwrap();
+
+    // Left in TRANSFER_SRC_OPTIMAL for the readback
+    let render_pass = device.create_render_pass(
+        blick::RenderPassDesc {
+            color_attachments: &[
+                Some(blick::ColorAttachmentDesc {
+                    format: FORMAT,
+                    layout: blick::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                })
+            ],
+            samples: blick::SampleCountFlags::TYPE_1,
+            resolve_attachments: &[],
+            subpasses: &[],
+            dependencies: &[
+                blick::SubpassDependency {
+                    src_subpass: 0,
+                    dst_subpass: blick::SUBPASS_EXTERNAL,
+                    src_stage_mask: blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                    dst_stage_mask: blick::PipelineStageFlags::TRANSFER,
+                    src_access_mask: blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    dst_access_mask: blick::AccessFlags::TRANSFER_READ,
+                    dependency_flags: blick::DependencyFlags::empty(),
+                },
+            ],
+            depth_stencil_attachment: None,
+        }
+    ).unwrap();
+
+    let view = device.create_image_view(
+        &image,
+        blick::ImageViewDesc {
+            view_type: blick::ImageViewType::TYPE_2D,
+            aspect_mask: blick::ImageAspectFlags::COLOR,
+            format: FORMAT,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    ).unwrap();
+
+    let framebuffer = device.create_framebuffer(
+        blick::FramebufferDesc {
+            render_pass: &render_pass,
+            attachments: &[
+                blick::Attachment {
+                    image_view: &view,
+                }
+            ],
+            extent: EXTENT,
+        }
+    ).unwrap();
+
+    let compute_pipeline = device.create_compute_pipeline(
+        blick::ComputePipelineDesc {
+            shader_module: blick::ShaderModuleDesc {
+                source: blick::ShaderSource::Hlsl(
+                    include_str!("../../../assets/shaders/compute_graphics_cs.hlsl"),
+                ),
+                stage: blick::ShaderStageFlags::COMPUTE,
+                specialization: &[],
+            },
+            descriptor_set_layouts: &[
+                &descriptor_set_layout,
+            ],
+            push_constant_ranges: &[
+                blick::PushConstantRange {
+                    stage_flags: blick::ShaderStageFlags::COMPUTE,
+                    offset: 0,
+                    size: 4*4,
+                },
+            ],
+        }
+    ).unwrap();
+
+    let pipeline = device.create_graphics_pipeline(
+        blick::GraphicsPipelineDesc {
+            shader_modules: &[
+                blick::ShaderModuleDesc {
+                    source: blick::ShaderSource::Hlsl(
+                        include_str!("../../../assets/shaders/compute_graphics_vs.hlsl"),
+                    ),
+                    stage: blick::ShaderStageFlags::VERTEX,
+                    specialization: &[],
+                },
+                blick::ShaderModuleDesc {
+                    source: blick::ShaderSource::Hlsl(
+                        include_str!("../../../assets/shaders/compute_graphics_ps.hlsl"),
+                    ),
+                    stage: blick::ShaderStageFlags::FRAGMENT,
+                    specialization: &[],
+                },
+            ],
+            descriptor_set_layouts: &[&descriptor_set_layout],
+            push_constant_ranges: &[],
+            target: blick::RenderTarget::RenderPass(&render_pass),
+            subpass: 0,
+            samples: blick::SampleCountFlags::TYPE_1,
+            stencil: blick::StencilState::default(),
+            depth_bias: None,
+            blend: None,
+            dynamic_states: blick::DynamicStates::empty(),
+        }
+    ).unwrap();
+
+    let extent = blick::Rect::from(EXTENT);
+    device.run_commands(|command_buffer| {
+        command_buffer.begin_compute_pass()
+            .bind_pipeline(&compute_pipeline)
+            .bind_descriptor_set(0, &descriptor_set)
+            .push_constants_typed(0, &COLOR)
+            .dispatch(1, 1, 1);
+
+        // Both the vertex and fragment shader read the computed data
+        command_buffer.transition(
+            &[],
+            &[
+                blick::BufferBarrier {
+                    buffer: &buffer,
+                    src_access_mask: blick::AccessFlags::SHADER_WRITE,
+                    dst_access_mask: blick::AccessFlags::SHADER_READ,
+                    queue_transfer: None,
+                }
+            ],
+            &[],
+            blick::PipelineStageFlags::COMPUTE_SHADER,
+            blick::PipelineStageFlags::VERTEX_SHADER
+                | blick::PipelineStageFlags::FRAGMENT_SHADER,
+        );
+
+        command_buffer.begin_render_pass(
+                &render_pass,
+                &framebuffer,
+                &extent,
+        )
+            .bind_pipeline(&pipeline)
+            .bind_descriptor_set(0, &descriptor_set)
+            .set_viewport_and_scissor(&extent)
+            .draw(3, 1, 0, 0);
+
+        command_buffer.copy_image_to_buffer(
+            &image,
+            blick::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            0,
+            image.desc.extent,
+            &readback,
+            0,
+        );
+    }).unwrap();
+
+    let pixels = readback.read_slice::<[u8; 4]>(
+        0,
+        (EXTENT.width * EXTENT.height) as usize,
+    ).unwrap();
+    let pixel = |x: u32, y: u32| pixels[(y * EXTENT.width + x) as usize];
+
+    let expected = COLOR.map(|c| (c * 255.0) as u8);
+    // The scaled triangle covers the center but not the corners
+    assert_eq!(pixel(EXTENT.width / 2, EXTENT.height / 2), expected);
+    assert_eq!(pixel(0, 0), [0, 0, 0, 255]);
+    println!("Rendered output matches the computed data");
+}
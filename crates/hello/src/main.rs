@@ -15,6 +15,19 @@ fn main() {
         &window,
         blick::BackendConfig {
             debugging: true,
+            device_selection: blick::DeviceSelection::default(),
+            transition_swapchain_images: true,
+            app_name: "hello".to_owned(),
+            app_version: (0, 1, 0),
+            engine_name: "blick".to_owned(),
+            engine_version: (0, 1, 0),
+            render_pass_cache_size: blick::DEFAULT_CACHE_SIZE,
+            framebuffer_cache_size: blick::DEFAULT_CACHE_SIZE,
+            renderdoc: false,
+            validation_severity: blick::DEFAULT_VALIDATION_SEVERITY,
+            validation_callback: None,
+            break_on_validation_error: cfg!(debug_assertions),
+            validation_features: blick::ValidationFeatures::empty(),
         },
     );
 
@@ -53,6 +66,7 @@ struct Renderer {
     descriptor_set_layout: blick::DescriptorSetLayout,
     descriptor_set: blick::DescriptorSet,
     buffer: blick::Buffer,
+    uniform_buffer: blick::Buffer,
     render_pass: blick::RenderPass,
     compute_pipeline: blick::ComputePipeline,
     pipeline: blick::GraphicsPipeline,
@@ -64,7 +78,9 @@ impl Renderer {
         render_backend: blick::Backend,
     ) -> Self {
         let device = render_backend.device();
-        let command_buffer = device.create_command_buffer().unwrap();
+        let command_buffer = device.create_command_buffer(
+            blick::CommandBufferLevel::PRIMARY,
+        ).unwrap();
 
         let descriptor_set_layout = device.create_descriptor_set_layout(
             blick::DescriptorSetLayoutDesc {
@@ -74,6 +90,14 @@ impl Renderer {
                         stage_flags: blick::ShaderStageFlags::ALL,
                         ty: blick::DescriptorType::STORAGE_BUFFER,
                         count: 1,
+                        flags: blick::DescriptorBindingFlags::empty(),
+                    },
+                    blick::DescriptorSetLayoutEntry {
+                        binding: 1,
+                        stage_flags: blick::ShaderStageFlags::VERTEX,
+                        ty: blick::DescriptorType::UNIFORM_BUFFER,
+                        count: 1,
+                        flags: blick::DescriptorBindingFlags::empty(),
                     },
                 ],
             }
@@ -83,6 +107,21 @@ impl Renderer {
             blick::BufferDesc {
                 size: 4*4*3,
                 usage: blick::BufferUsage::STORAGE,
+                min_alignment: None,
+                memory_location: None,
+                name: Some("colors".to_owned()),
+            }
+        ).unwrap();
+
+        // Written from the CPU every frame, end_frame waits for the device so
+        // a single buffer is enough
+        let uniform_buffer = device.create_buffer(
+            blick::BufferDesc {
+                size: std::mem::size_of::<[[f32; 4]; 4]>() as u64,
+                usage: blick::BufferUsage::UNIFORM | blick::BufferUsage::MAP_WRITE,
+                min_alignment: None,
+                memory_location: None,
+                name: Some("transform".to_owned()),
             }
         ).unwrap();
 
@@ -95,11 +134,17 @@ impl Renderer {
             &[
                 blick::Descriptor {
                     binding: 0,
-                    resource: &blick::DescriptorResource::Buffer {
-                        buffer: &buffer,
-                        offset: 0,
-                        range: blick::WHOLE_SIZE,
-                    },
+                    array_element: 0,
+                    resources: &[
+                        blick::DescriptorResource::whole_buffer(&buffer),
+                    ],
+                },
+                blick::Descriptor {
+                    binding: 1,
+                    array_element: 0,
+                    resources: &[
+                        blick::DescriptorResource::whole_buffer(&uniform_buffer),
+                    ],
                 },
             ],
         ).unwrap();
@@ -112,7 +157,23 @@ impl Renderer {
                         format: render_backend.swapchain_desc().format,
                         layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
                     })
-                ]
+                ],
+                samples: blick::SampleCountFlags::TYPE_1,
+                resolve_attachments: &[],
+                subpasses: &[],
+                // Wait for the swapchain image before the layout transition
+                dependencies: &[
+                    blick::SubpassDependency {
+                        src_subpass: blick::SUBPASS_EXTERNAL,
+                        dst_subpass: 0,
+                        src_stage_mask: blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                        dst_stage_mask: blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                        src_access_mask: blick::AccessFlags::empty(),
+                        dst_access_mask: blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                        dependency_flags: blick::DependencyFlags::empty(),
+                    },
+                ],
+                depth_stencil_attachment: None,
             }
         ).unwrap();
 
@@ -124,6 +185,7 @@ impl Renderer {
                     ),
                     // TODO: Do we really need to spec stage for compute pipeline
                     stage: blick::ShaderStageFlags::COMPUTE,
+                    specialization: &[],
                 },
                 descriptor_set_layouts: &[
                     &descriptor_set_layout,
@@ -146,17 +208,25 @@ impl Renderer {
                             include_str!("../../../assets/shaders/triangle_vs.hlsl"),
                         ),
                         stage: blick::ShaderStageFlags::VERTEX,
+                        specialization: &[],
                     },
                     blick::ShaderModuleDesc {
                         source: blick::ShaderSource::Hlsl(
                             include_str!("../../../assets/shaders/triangle_ps.hlsl"),
                         ),
                         stage: blick::ShaderStageFlags::FRAGMENT,
+                        specialization: &[],
                     },
                 ],
                 descriptor_set_layouts: &[&descriptor_set_layout],
                 push_constant_ranges: &[],
-                render_pass: &render_pass,
+                target: blick::RenderTarget::RenderPass(&render_pass),
+                subpass: 0,
+                samples: blick::SampleCountFlags::TYPE_1,
+                stencil: blick::StencilState::default(),
+                depth_bias: None,
+                blend: None,
+                dynamic_states: blick::DynamicStates::empty(),
             }
         ).unwrap();
 
@@ -166,6 +236,7 @@ impl Renderer {
             descriptor_set_layout,
             descriptor_set,
             buffer,
+            uniform_buffer,
             render_pass,
             compute_pipeline,
             pipeline,
@@ -180,9 +251,10 @@ impl Renderer {
                 // TODO:
                 panic!("Skip frame: Swapchain out of date");
             },
+            Err(err) => panic!("begin_frame: {:?}", err),
         };
 
-        let extent = frame.swapchain_image.image.desc.extent;
+        let extent = self.backend.swapchain_desc().extent;
 
         let device = self.backend.device();
         let framebuffer = device.create_framebuffer(
@@ -198,23 +270,23 @@ impl Renderer {
                                 format: frame.swapchain_image.image.desc.format,
                                 base_mip_level: 0,
                                 level_count: 1,
+                                base_array_layer: 0,
+                                layer_count: 1,
                             }
                         ).unwrap()
                     }
                 ],
-                extent: blick::Extent2d {
-                    width: extent.width,
-                    height: extent.height,
-                },
+                extent,
             }
         ).unwrap();
 
-        let extent = blick::Rect {
-            x: 0,
-            y: 0,
-            width: extent.width,
-            height: extent.height,
-        };
+        self.uniform_buffer.write_slice(
+            0,
+            &[transform(self.frame_idx, extent.width as f32 / extent.height as f32)],
+        ).unwrap();
+        self.uniform_buffer.flush(0, self.uniform_buffer.size()).unwrap();
+
+        let extent = blick::Rect::from(extent);
 
         self.command_buffer.begin();
 
@@ -225,25 +297,20 @@ impl Renderer {
         ];
         let values = all_values[(self.frame_idx / 60) % 3];
 
-        let push_constants = unsafe {
-            std::slice::from_raw_parts(values.as_ptr() as *const u8, 3*4)
-        };
-
         self.command_buffer.begin_compute_pass()
             .bind_pipeline(&self.compute_pipeline)
             .bind_descriptor_set(0, &self.descriptor_set)
-            .push_constants(
-                    0,
-                push_constants
-            )
+            .push_constants_typed(0, &values)
             .dispatch(3, 1, 1);
 
         self.command_buffer.transition(
+            &[],
             &[
                 blick::BufferBarrier {
                     buffer: &self.buffer,
                     src_access_mask: blick::AccessFlags::SHADER_WRITE,
                     dst_access_mask: blick::AccessFlags::SHADER_READ,
+                    queue_transfer: None,
                 }
             ],
             &[],
@@ -263,6 +330,7 @@ impl Renderer {
 
 
         self.command_buffer.transition(
+            &[],
             &[],
             &[
                 blick::ImageBarrier {
@@ -271,7 +339,10 @@ impl Renderer {
                     dst_access_mask: blick::AccessFlags::empty(),
                     old_layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
                     new_layout: blick::ImageLayout::PRESENT_SRC_KHR,
-                    aspect_mask: blick::ImageAspectFlags::COLOR,
+                    subresource_range: blick::ImageSubresourceRange::all(
+                        blick::ImageAspectFlags::COLOR,
+                    ),
+                    queue_transfer: None,
                 }
             ],
             blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
@@ -280,28 +351,30 @@ impl Renderer {
 
         self.command_buffer.end();
 
-        self.backend.device().submit(
-            &[&self.command_buffer],
-            &[],
-            &[&frame.render_finished],
-            None,
-        ).unwrap();
-
-        match self.backend.end_frame(frame) {
+        match self.backend.submit_frame(frame, &[&self.command_buffer]) {
             Ok(_) => {},
             Err(blick::EndFrameError::OutdatedSwapchain) => {
-                panic!("end_frame: Swapchain out of date");
+                panic!("submit_frame: Swapchain out of date");
             },
+            Err(err) => panic!("submit_frame: {:?}", err),
         }
 
         self.frame_idx += 1;
     }
 
     fn resize(&mut self, width: u32, height: u32) {
-        if self.backend.swapchain_desc().extent.width == width
-        && self.backend.swapchain_desc().extent.height == height {
-            return;
-        }
         self.backend.resize_swapchain(width, height);
     }
 }
+
+/// Rotation around z corrected for aspect ratio, column major as expected by
+/// the shader
+fn transform(frame_idx: usize, aspect: f32) -> [[f32; 4]; 4] {
+    let (sin, cos) = (frame_idx as f32 * 0.01).sin_cos();
+    [
+        [cos / aspect, sin, 0.0, 0.0],
+        [-sin / aspect, cos, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
@@ -0,0 +1,517 @@
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{EventLoop, ControlFlow};
+use winit::platform::run_return::EventLoopExtRunReturn;
+
+const GBUFFER_EXTENT: blick::Extent2d = blick::Extent2d {
+    width: 512,
+    height: 512,
+};
+const ALBEDO_FORMAT: blick::ImageFormat = blick::ImageFormat::R8G8B8A8_UNORM;
+const NORMAL_FORMAT: blick::ImageFormat = blick::ImageFormat::A2B10G10R10_UNORM_PACK32;
+
+/// Renders a triangle to two color attachments at once (albedo and normals)
+/// and shows both side by side on the swapchain. The attachment slot between
+/// them is left unused to exercise holes in the color attachment list.
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let window = winit::window::WindowBuilder::new()
+        .with_title("blick - mrt")
+        .with_inner_size(winit::dpi::LogicalSize::new(800, 600))
+        .build(&event_loop)
+        .expect("Failed to create window");
+
+    let backend = blick::Backend::new(
+        &window,
+        blick::BackendConfig {
+            debugging: true,
+            device_selection: blick::DeviceSelection::default(),
+            transition_swapchain_images: true,
+            app_name: "mrt".to_owned(),
+            app_version: (0, 1, 0),
+            engine_name: "blick".to_owned(),
+            engine_version: (0, 1, 0),
+            render_pass_cache_size: blick::DEFAULT_CACHE_SIZE,
+            framebuffer_cache_size: blick::DEFAULT_CACHE_SIZE,
+            renderdoc: false,
+            validation_severity: blick::DEFAULT_VALIDATION_SEVERITY,
+            validation_callback: None,
+            break_on_validation_error: cfg!(debug_assertions),
+            validation_features: blick::ValidationFeatures::empty(),
+        },
+    );
+
+    let mut renderer = Renderer::new(backend);
+
+    let mut running = true;
+    while running {
+        event_loop.run_return(|event, _, control_flow| {
+            match event {
+                Event::WindowEvent { event, .. } => {
+                    match event {
+                        WindowEvent::CloseRequested => {
+                            *control_flow = ControlFlow::Exit;
+                            running = false;
+                        },
+                        WindowEvent::Resized(size) => {
+                            renderer.resize(size.width, size.height);
+                        },
+                        _ => {}
+                    }
+                },
+                winit::event::Event::MainEventsCleared => {
+                    *control_flow = ControlFlow::Exit;
+                },
+                _ => {}
+            }
+        });
+        renderer.draw_frame();
+    }
+}
+
+fn create_target(
+    device: &blick::Device,
+    format: blick::ImageFormat,
+    name: &str,
+) -> (blick::Image, blick::ImageView) {
+    let image = device.create_image(
+        blick::ImageDesc {
+            image_type: blick::ImageType::TYPE_2D,
+            format,
+            extent: blick::Extent3d {
+                width: GBUFFER_EXTENT.width,
+                height: GBUFFER_EXTENT.height,
+                depth: 1,
+            },
+            mip_levels: 1,
+            array_layers: 1,
+            samples: blick::SampleCountFlags::TYPE_1,
+            usage: blick::ImageUsage::COLOR_ATTACHMENT | blick::ImageUsage::SAMPLED,
+            mutable_format: false,
+            cube_compatible: false,
+            name: Some(name.to_owned()),
+        }
+    ).unwrap();
+
+    let view = device.create_image_view(
+        &image,
+        blick::ImageViewDesc {
+            view_type: blick::ImageViewType::TYPE_2D,
+            aspect_mask: blick::ImageAspectFlags::COLOR,
+            format,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    ).unwrap();
+
+    (image, view)
+}
+
+#[allow(dead_code)]
+struct Renderer {
+    backend: blick::Backend,
+    command_buffer: blick::CommandBuffer,
+
+    // G-buffer pass
+    colors: blick::Buffer,
+    triangle_set_layout: blick::DescriptorSetLayout,
+    triangle_set: blick::DescriptorSet,
+    albedo_image: blick::Image,
+    albedo_view: blick::ImageView,
+    normal_image: blick::Image,
+    normal_view: blick::ImageView,
+    gbuffer_pass: blick::RenderPass,
+    gbuffer_framebuffer: blick::Framebuffer,
+    triangle_pipeline: blick::GraphicsPipeline,
+
+    // Swapchain pass
+    sampler: blick::Sampler,
+    composite_set_layout: blick::DescriptorSetLayout,
+    composite_set: blick::DescriptorSet,
+    render_pass: blick::RenderPass,
+    composite_pipeline: blick::GraphicsPipeline,
+}
+
+impl Renderer {
+    pub fn new(
+        render_backend: blick::Backend,
+    ) -> Self {
+        let device = render_backend.device();
+        let command_buffer = device.create_command_buffer(
+            blick::CommandBufferLevel::PRIMARY,
+        ).unwrap();
+
+        let color_values: [[f32; 4]; 3] = [
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 1.0, 0.0, 1.0],
+            [0.0, 0.0, 1.0, 1.0],
+        ];
+        let colors = device.create_buffer_init(
+            blick::BufferUsage::STORAGE,
+            unsafe {
+                std::slice::from_raw_parts(
+                    color_values.as_ptr() as *const u8,
+                    std::mem::size_of_val(&color_values),
+                )
+            },
+        ).unwrap();
+
+        let triangle_set_layout = device.create_descriptor_set_layout(
+            blick::DescriptorSetLayoutDesc {
+                entries: &[
+                    blick::DescriptorSetLayoutEntry {
+                        binding: 0,
+                        stage_flags: blick::ShaderStageFlags::VERTEX,
+                        ty: blick::DescriptorType::STORAGE_BUFFER,
+                        count: 1,
+                        flags: blick::DescriptorBindingFlags::empty(),
+                    },
+                ],
+            }
+        ).unwrap();
+
+        let triangle_set = device.create_descriptor_set(&triangle_set_layout).unwrap();
+        device.update_descriptor_set(
+            &triangle_set,
+            &[
+                blick::Descriptor {
+                    binding: 0,
+                    array_element: 0,
+                    resources: &[
+                        blick::DescriptorResource::whole_buffer(&colors),
+                    ],
+                },
+            ],
+        ).unwrap();
+
+        let (albedo_image, albedo_view) = create_target(device, ALBEDO_FORMAT, "albedo");
+        let (normal_image, normal_view) = create_target(device, NORMAL_FORMAT, "normal");
+
+        // Slot 1 is a hole, shader outputs go to SV_TARGET0 and SV_TARGET2
+        let gbuffer_pass = device.create_render_pass(
+            blick::RenderPassDesc {
+                color_attachments: &[
+                    Some(blick::ColorAttachmentDesc {
+                        format: ALBEDO_FORMAT,
+                        layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    }),
+                    None,
+                    Some(blick::ColorAttachmentDesc {
+                        format: NORMAL_FORMAT,
+                        layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    }),
+                ],
+                samples: blick::SampleCountFlags::TYPE_1,
+                resolve_attachments: &[],
+                subpasses: &[],
+                dependencies: &[],
+                depth_stencil_attachment: None,
+            }
+        ).unwrap();
+
+        // Framebuffer attachments only cover the Some slots, in order
+        let gbuffer_framebuffer = device.create_framebuffer(
+            blick::FramebufferDesc {
+                render_pass: &gbuffer_pass,
+                attachments: &[
+                    blick::Attachment {
+                        image_view: &albedo_view,
+                    },
+                    blick::Attachment {
+                        image_view: &normal_view,
+                    },
+                ],
+                extent: GBUFFER_EXTENT,
+            }
+        ).unwrap();
+
+        let triangle_pipeline = device.create_graphics_pipeline(
+            blick::GraphicsPipelineDesc {
+                shader_modules: &[
+                    blick::ShaderModuleDesc {
+                        source: blick::ShaderSource::Hlsl(
+                            include_str!("../../../assets/shaders/triangle_vs.hlsl"),
+                        ),
+                        stage: blick::ShaderStageFlags::VERTEX,
+                        specialization: &[],
+                    },
+                    blick::ShaderModuleDesc {
+                        source: blick::ShaderSource::Hlsl(
+                            include_str!("../../../assets/shaders/mrt_ps.hlsl"),
+                        ),
+                        stage: blick::ShaderStageFlags::FRAGMENT,
+                        specialization: &[],
+                    },
+                ],
+                descriptor_set_layouts: &[&triangle_set_layout],
+                push_constant_ranges: &[],
+                target: blick::RenderTarget::RenderPass(&gbuffer_pass),
+                subpass: 0,
+                samples: blick::SampleCountFlags::TYPE_1,
+                stencil: blick::StencilState::default(),
+                depth_bias: None,
+                blend: None,
+                dynamic_states: blick::DynamicStates::empty(),
+            }
+        ).unwrap();
+
+        let sampler = device.create_sampler(
+            blick::SamplerDesc {
+                mag_filter: blick::Filter::LINEAR,
+                min_filter: blick::Filter::LINEAR,
+                mipmap_mode: blick::SamplerMipmapMode::NEAREST,
+                address_mode: blick::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_anisotropy: None,
+            }
+        ).unwrap();
+
+        let composite_set_layout = device.create_descriptor_set_layout(
+            blick::DescriptorSetLayoutDesc {
+                entries: &[
+                    blick::DescriptorSetLayoutEntry {
+                        binding: 0,
+                        stage_flags: blick::ShaderStageFlags::FRAGMENT,
+                        ty: blick::DescriptorType::SAMPLED_IMAGE,
+                        count: 1,
+                        flags: blick::DescriptorBindingFlags::empty(),
+                    },
+                    blick::DescriptorSetLayoutEntry {
+                        binding: 1,
+                        stage_flags: blick::ShaderStageFlags::FRAGMENT,
+                        ty: blick::DescriptorType::SAMPLED_IMAGE,
+                        count: 1,
+                        flags: blick::DescriptorBindingFlags::empty(),
+                    },
+                    blick::DescriptorSetLayoutEntry {
+                        binding: 2,
+                        stage_flags: blick::ShaderStageFlags::FRAGMENT,
+                        ty: blick::DescriptorType::SAMPLER,
+                        count: 1,
+                        flags: blick::DescriptorBindingFlags::empty(),
+                    },
+                ],
+            }
+        ).unwrap();
+
+        let composite_set = device.create_descriptor_set(&composite_set_layout).unwrap();
+        device.update_descriptor_set(
+            &composite_set,
+            &[
+                blick::Descriptor {
+                    binding: 0,
+                    array_element: 0,
+                    resources: &[
+                        blick::DescriptorResource::Image {
+                            view: &albedo_view,
+                            layout: blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        },
+                    ],
+                },
+                blick::Descriptor {
+                    binding: 1,
+                    array_element: 0,
+                    resources: &[
+                        blick::DescriptorResource::Image {
+                            view: &normal_view,
+                            layout: blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        },
+                    ],
+                },
+                blick::Descriptor {
+                    binding: 2,
+                    array_element: 0,
+                    resources: &[
+                        blick::DescriptorResource::Sampler {
+                            sampler: &sampler,
+                        },
+                    ],
+                },
+            ],
+        ).unwrap();
+
+        let render_pass = device.create_render_pass(
+            blick::RenderPassDesc {
+                color_attachments: &[
+                    Some(blick::ColorAttachmentDesc {
+                        format: render_backend.swapchain_desc().format,
+                        layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    })
+                ],
+                samples: blick::SampleCountFlags::TYPE_1,
+                resolve_attachments: &[],
+                subpasses: &[],
+                dependencies: &[],
+                depth_stencil_attachment: None,
+            }
+        ).unwrap();
+
+        let composite_pipeline = device.create_graphics_pipeline(
+            blick::GraphicsPipelineDesc {
+                shader_modules: &[
+                    blick::ShaderModuleDesc {
+                        source: blick::ShaderSource::Hlsl(
+                            include_str!("../../../assets/shaders/fullscreen_vs.hlsl"),
+                        ),
+                        stage: blick::ShaderStageFlags::VERTEX,
+                        specialization: &[],
+                    },
+                    blick::ShaderModuleDesc {
+                        source: blick::ShaderSource::Hlsl(
+                            include_str!("../../../assets/shaders/composite_ps.hlsl"),
+                        ),
+                        stage: blick::ShaderStageFlags::FRAGMENT,
+                        specialization: &[],
+                    },
+                ],
+                descriptor_set_layouts: &[&composite_set_layout],
+                push_constant_ranges: &[],
+                target: blick::RenderTarget::RenderPass(&render_pass),
+                subpass: 0,
+                samples: blick::SampleCountFlags::TYPE_1,
+                stencil: blick::StencilState::default(),
+                depth_bias: None,
+                blend: None,
+                dynamic_states: blick::DynamicStates::empty(),
+            }
+        ).unwrap();
+
+        Self {
+            backend: render_backend,
+            command_buffer,
+            colors,
+            triangle_set_layout,
+            triangle_set,
+            albedo_image,
+            albedo_view,
+            normal_image,
+            normal_view,
+            gbuffer_pass,
+            gbuffer_framebuffer,
+            triangle_pipeline,
+            sampler,
+            composite_set_layout,
+            composite_set,
+            render_pass,
+            composite_pipeline,
+        }
+    }
+
+    pub fn draw_frame(&mut self) {
+        let frame = match self.backend.begin_frame() {
+            Ok(frame) => frame,
+            Err(blick::BeginFrameError::OutdatedSwapchain) => {
+                // TODO:
+                panic!("Skip frame: Swapchain out of date");
+            },
+            Err(err) => panic!("begin_frame: {:?}", err),
+        };
+
+        let device = self.backend.device();
+        let extent = self.backend.swapchain_desc().extent;
+
+        let framebuffer = device.create_framebuffer(
+            blick::FramebufferDesc {
+                render_pass: &self.render_pass,
+                attachments: &[
+                    blick::Attachment {
+                        image_view: &device.create_image_view(
+                            &frame.swapchain_image.image,
+                            blick::ImageViewDesc {
+                                view_type: blick::ImageViewType::TYPE_2D,
+                                aspect_mask: blick::ImageAspectFlags::COLOR,
+                                format: frame.swapchain_image.image.desc.format,
+                                base_mip_level: 0,
+                                level_count: 1,
+                                base_array_layer: 0,
+                                layer_count: 1,
+                            }
+                        ).unwrap()
+                    }
+                ],
+                extent,
+            }
+        ).unwrap();
+
+        let gbuffer_area = blick::Rect::from(GBUFFER_EXTENT);
+        let swapchain_area = blick::Rect::from(extent);
+
+        self.command_buffer.begin();
+
+        self.command_buffer.begin_render_pass(
+                &self.gbuffer_pass,
+                &self.gbuffer_framebuffer,
+                &gbuffer_area,
+        )
+            .bind_pipeline(&self.triangle_pipeline)
+            .bind_descriptor_set(0, &self.triangle_set)
+            .set_viewport_and_scissor(&gbuffer_area)
+            .draw(3, 1, 0, 0);
+
+        let gbuffer_barrier = |image| blick::ImageBarrier {
+            image,
+            src_access_mask: blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: blick::AccessFlags::SHADER_READ,
+            old_layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            new_layout: blick::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            subresource_range: blick::ImageSubresourceRange::all(
+                blick::ImageAspectFlags::COLOR,
+            ),
+            queue_transfer: None,
+        };
+        self.command_buffer.transition(
+            &[],
+            &[],
+            &[
+                gbuffer_barrier(&self.albedo_image),
+                gbuffer_barrier(&self.normal_image),
+            ],
+            blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            blick::PipelineStageFlags::FRAGMENT_SHADER,
+        );
+
+        self.command_buffer.begin_render_pass(
+                &self.render_pass,
+                &framebuffer,
+                &swapchain_area,
+        )
+            .bind_pipeline(&self.composite_pipeline)
+            .bind_descriptor_set(0, &self.composite_set)
+            .set_viewport_and_scissor(&swapchain_area)
+            .draw(3, 1, 0, 0);
+
+        self.command_buffer.transition(
+            &[],
+            &[],
+            &[
+                blick::ImageBarrier {
+                    image: &frame.swapchain_image.image,
+                    src_access_mask: blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    dst_access_mask: blick::AccessFlags::empty(),
+                    old_layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    new_layout: blick::ImageLayout::PRESENT_SRC_KHR,
+                    subresource_range: blick::ImageSubresourceRange::all(
+                        blick::ImageAspectFlags::COLOR,
+                    ),
+                    queue_transfer: None,
+                }
+            ],
+            blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            blick::PipelineStageFlags::BOTTOM_OF_PIPE,
+        );
+
+        self.command_buffer.end();
+
+        match self.backend.submit_frame(frame, &[&self.command_buffer]) {
+            Ok(_) => {},
+            Err(blick::EndFrameError::OutdatedSwapchain) => {
+                panic!("submit_frame: Swapchain out of date");
+            },
+            Err(err) => panic!("submit_frame: {:?}", err),
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.backend.resize_swapchain(width, height);
+    }
+}
@@ -0,0 +1,318 @@
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{EventLoop, ControlFlow};
+use winit::platform::run_return::EventLoopExtRunReturn;
+
+/// position.xy, padding.xy, color.rgba
+const VERTICES: [[f32; 8]; 4] = [
+    [-0.5, -0.5, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+    [0.5, -0.5, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0],
+    [0.5, 0.5, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0],
+    [-0.5, 0.5, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0],
+];
+const INDICES: [u32; 6] = [0, 1, 2, 0, 2, 3];
+
+/// Draws a quad from two indexed triangles, alternating between 16 and 32 bit
+/// index buffers every second.
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let window = winit::window::WindowBuilder::new()
+        .with_title("blick - quad")
+        .with_inner_size(winit::dpi::LogicalSize::new(800, 600))
+        .build(&event_loop)
+        .expect("Failed to create window");
+
+    let backend = blick::Backend::new(
+        &window,
+        blick::BackendConfig {
+            debugging: true,
+            device_selection: blick::DeviceSelection::default(),
+            transition_swapchain_images: true,
+            app_name: "quad".to_owned(),
+            app_version: (0, 1, 0),
+            engine_name: "blick".to_owned(),
+            engine_version: (0, 1, 0),
+            render_pass_cache_size: blick::DEFAULT_CACHE_SIZE,
+            framebuffer_cache_size: blick::DEFAULT_CACHE_SIZE,
+            renderdoc: false,
+            validation_severity: blick::DEFAULT_VALIDATION_SEVERITY,
+            validation_callback: None,
+            break_on_validation_error: cfg!(debug_assertions),
+            validation_features: blick::ValidationFeatures::empty(),
+        },
+    );
+
+    let mut renderer = Renderer::new(backend);
+
+    let mut running = true;
+    while running {
+        event_loop.run_return(|event, _, control_flow| {
+            match event {
+                Event::WindowEvent { event, .. } => {
+                    match event {
+                        WindowEvent::CloseRequested => {
+                            *control_flow = ControlFlow::Exit;
+                            running = false;
+                        },
+                        WindowEvent::Resized(size) => {
+                            renderer.resize(size.width, size.height);
+                        },
+                        _ => {}
+                    }
+                },
+                winit::event::Event::MainEventsCleared => {
+                    *control_flow = ControlFlow::Exit;
+                },
+                _ => {}
+            }
+        });
+        renderer.draw_frame();
+    }
+}
+
+#[allow(dead_code)]
+struct Renderer {
+    backend: blick::Backend,
+    command_buffer: blick::CommandBuffer,
+    descriptor_set_layout: blick::DescriptorSetLayout,
+    descriptor_set: blick::DescriptorSet,
+    vertex_buffer: blick::Buffer,
+    index_buffer_u16: blick::Buffer,
+    index_buffer_u32: blick::Buffer,
+    render_pass: blick::RenderPass,
+    pipeline: blick::GraphicsPipeline,
+    frame_idx: usize,
+}
+
+impl Renderer {
+    pub fn new(
+        render_backend: blick::Backend,
+    ) -> Self {
+        let device = render_backend.device();
+        let command_buffer = device.create_command_buffer(
+            blick::CommandBufferLevel::PRIMARY,
+        ).unwrap();
+
+        let descriptor_set_layout = device.create_descriptor_set_layout(
+            blick::DescriptorSetLayoutDesc {
+                entries: &[
+                    blick::DescriptorSetLayoutEntry {
+                        binding: 0,
+                        stage_flags: blick::ShaderStageFlags::VERTEX,
+                        ty: blick::DescriptorType::STORAGE_BUFFER,
+                        count: 1,
+                        flags: blick::DescriptorBindingFlags::empty(),
+                    },
+                ],
+            }
+        ).unwrap();
+
+        let vertex_data = VERTICES
+            .iter()
+            .flatten()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let vertex_buffer = device.create_buffer_init(
+            blick::BufferUsage::STORAGE,
+            &vertex_data,
+        ).unwrap();
+
+        // Index buffers aren't mappable, uploaded through staging buffers
+        let index_data_u16 = INDICES
+            .iter()
+            .flat_map(|index| (*index as u16).to_ne_bytes())
+            .collect::<Vec<_>>();
+        let index_buffer_u16 = device.create_buffer_init(
+            blick::BufferUsage::INDEX,
+            &index_data_u16,
+        ).unwrap();
+
+        let index_data_u32 = INDICES
+            .iter()
+            .flat_map(|index| index.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let index_buffer_u32 = device.create_buffer_init(
+            blick::BufferUsage::INDEX,
+            &index_data_u32,
+        ).unwrap();
+
+        let descriptor_set = device.create_descriptor_set(
+            &descriptor_set_layout,
+        ).unwrap();
+
+        device.update_descriptor_set(
+            &descriptor_set,
+            &[
+                blick::Descriptor {
+                    binding: 0,
+                    array_element: 0,
+                    resources: &[
+                        blick::DescriptorResource::whole_buffer(&vertex_buffer),
+                    ],
+                },
+            ],
+        ).unwrap();
+
+        let render_pass = device.create_render_pass(
+            blick::RenderPassDesc {
+                color_attachments: &[
+                    Some(blick::ColorAttachmentDesc {
+                        format: render_backend.swapchain_format(),
+                        layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    })
+                ],
+                samples: blick::SampleCountFlags::TYPE_1,
+                resolve_attachments: &[],
+                subpasses: &[],
+                // Wait for the swapchain image before the layout transition
+                dependencies: &[
+                    blick::SubpassDependency {
+                        src_subpass: blick::SUBPASS_EXTERNAL,
+                        dst_subpass: 0,
+                        src_stage_mask: blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                        dst_stage_mask: blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                        src_access_mask: blick::AccessFlags::empty(),
+                        dst_access_mask: blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                        dependency_flags: blick::DependencyFlags::empty(),
+                    },
+                ],
+                depth_stencil_attachment: None,
+            }
+        ).unwrap();
+
+        let pipeline = device.create_graphics_pipeline(
+            blick::GraphicsPipelineDesc {
+                shader_modules: &[
+                    blick::ShaderModuleDesc {
+                        source: blick::ShaderSource::Hlsl(
+                            include_str!("../../../assets/shaders/quad_vs.hlsl"),
+                        ),
+                        stage: blick::ShaderStageFlags::VERTEX,
+                        specialization: &[],
+                    },
+                    blick::ShaderModuleDesc {
+                        source: blick::ShaderSource::Hlsl(
+                            include_str!("../../../assets/shaders/triangle_ps.hlsl"),
+                        ),
+                        stage: blick::ShaderStageFlags::FRAGMENT,
+                        specialization: &[],
+                    },
+                ],
+                descriptor_set_layouts: &[&descriptor_set_layout],
+                push_constant_ranges: &[],
+                target: blick::RenderTarget::RenderPass(&render_pass),
+                subpass: 0,
+                samples: blick::SampleCountFlags::TYPE_1,
+                stencil: blick::StencilState::default(),
+                depth_bias: None,
+                blend: None,
+                dynamic_states: blick::DynamicStates::empty(),
+            }
+        ).unwrap();
+
+        Self {
+            backend: render_backend,
+            command_buffer,
+            descriptor_set_layout,
+            descriptor_set,
+            vertex_buffer,
+            index_buffer_u16,
+            index_buffer_u32,
+            render_pass,
+            pipeline,
+            frame_idx: 0,
+        }
+    }
+
+    pub fn draw_frame(&mut self) {
+        let frame = match self.backend.begin_frame() {
+            Ok(frame) => frame,
+            Err(blick::BeginFrameError::OutdatedSwapchain) => {
+                // TODO:
+                panic!("Skip frame: Swapchain out of date");
+            },
+            Err(err) => panic!("begin_frame: {:?}", err),
+        };
+
+        let extent = self.backend.swapchain_desc().extent;
+
+        let device = self.backend.device();
+        let framebuffer = device.create_framebuffer(
+            blick::FramebufferDesc {
+                render_pass: &self.render_pass,
+                attachments: &[
+                    blick::Attachment {
+                        image_view: &device.create_image_view(
+                            &frame.swapchain_image.image,
+                            blick::ImageViewDesc {
+                                view_type: blick::ImageViewType::TYPE_2D,
+                                aspect_mask: blick::ImageAspectFlags::COLOR,
+                                format: frame.swapchain_image.image.desc.format,
+                                base_mip_level: 0,
+                                level_count: 1,
+                                base_array_layer: 0,
+                                layer_count: 1,
+                            }
+                        ).unwrap()
+                    }
+                ],
+                extent,
+            }
+        ).unwrap();
+
+        let extent = blick::Rect::from(extent);
+
+        let (index_buffer, index_type) = match (self.frame_idx / 60) % 2 {
+            0 => (&self.index_buffer_u16, blick::IndexType::UINT16),
+            _ => (&self.index_buffer_u32, blick::IndexType::UINT32),
+        };
+
+        self.command_buffer.begin();
+
+        self.command_buffer.begin_render_pass(
+                &self.render_pass,
+                &framebuffer,
+                &extent,
+        )
+            .bind_pipeline(&self.pipeline)
+            .bind_descriptor_set(0, &self.descriptor_set)
+            .bind_index_buffer(index_buffer, 0, index_type)
+            .set_viewport_and_scissor(&extent)
+            .draw_indexed(INDICES.len() as u32, 1, 0, 0, 0);
+
+        self.command_buffer.transition(
+            &[],
+            &[],
+            &[
+                blick::ImageBarrier {
+                    image: &frame.swapchain_image.image,
+                    src_access_mask: blick::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                    dst_access_mask: blick::AccessFlags::empty(),
+                    old_layout: blick::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                    new_layout: blick::ImageLayout::PRESENT_SRC_KHR,
+                    subresource_range: blick::ImageSubresourceRange::all(
+                        blick::ImageAspectFlags::COLOR,
+                    ),
+                    queue_transfer: None,
+                }
+            ],
+            blick::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            blick::PipelineStageFlags::BOTTOM_OF_PIPE,
+        );
+
+        self.command_buffer.end();
+
+        match self.backend.submit_frame(frame, &[&self.command_buffer]) {
+            Ok(_) => {},
+            Err(blick::EndFrameError::OutdatedSwapchain) => {
+                panic!("submit_frame: Swapchain out of date");
+            },
+            Err(err) => panic!("submit_frame: {:?}", err),
+        }
+
+        self.frame_idx += 1;
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.backend.resize_swapchain(width, height);
+    }
+}